@@ -0,0 +1,705 @@
+#![allow(dead_code)]
+
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use std::ops::{Add, Sub, Mul, Div, Neg};
+
+use bigint::BigInt;
+use rational::Rational;
+
+// Significant digits used for big floats when no precision is set
+pub const DEFAULT_PRECISION: usize = 50;
+// Largest working precision, elementary functions take seconds there
+pub const MAX_PRECISION: usize = 1000;
+// Extra digits carried through transcendental functions
+const GUARD_DIGITS: usize = 10;
+
+thread_local! {
+    static PRECISION: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+// Sets the working precision in significant decimal digits. While a
+// precision is set, elementary functions of exact values are evaluated
+// with big floats, None goes back to machine floats. At most
+// MAX_PRECISION digits.
+pub fn set_precision(digits: Option<usize>) {
+    let digits = digits.filter(|digits| *digits > 0).map(|digits| digits.min(MAX_PRECISION));
+    PRECISION.with(|precision| precision.set(digits));
+}
+
+pub fn precision() -> Option<usize> {
+    PRECISION.with(|precision| precision.get())
+}
+
+fn digits() -> usize {
+    precision().unwrap_or(DEFAULT_PRECISION)
+}
+
+fn pow10(exponent: usize) -> BigInt {
+    BigInt::from(10).pow(exponent as u32)
+}
+
+fn decimal_digits(integer: &BigInt) -> usize {
+    if integer.is_zero() {
+        return 0
+    }
+    integer.abs().to_string().len()
+}
+
+// Arbitrary-precision decimal float with value mantissa * 10^exponent.
+// The mantissa never has trailing zeros, so equal values are structurally
+// equal. Results are correctly rounded to the working precision for
+// + - * / and sqrt, using round half to even.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct BigFloat {
+    mantissa: BigInt,
+    exponent: i64
+}
+
+impl BigFloat {
+    fn normalize(mantissa: BigInt, exponent: i64) -> BigFloat {
+        if mantissa.is_zero() {
+            return BigFloat { mantissa, exponent: 0 }
+        }
+        let ten = BigInt::from(10);
+        let mut mantissa = mantissa;
+        let mut exponent = exponent;
+        loop {
+            let (quotient, remainder) = mantissa.divrem(&ten);
+            if !remainder.is_zero() {
+                break;
+            }
+            mantissa = quotient;
+            exponent += 1;
+        }
+        BigFloat { mantissa, exponent }
+    }
+
+    // Rounds mantissa * 10^exponent to the given number of digits. Sticky
+    // marks a nonzero tail below the mantissa that was already discarded.
+    fn round(mantissa: BigInt, exponent: i64, sticky: bool, precision: usize) -> BigFloat {
+        let length = decimal_digits(&mantissa);
+        if length <= precision {
+            return BigFloat::normalize(mantissa, exponent)
+        }
+        let dropped = length - precision;
+        let divisor = pow10(dropped);
+        let (quotient, remainder) = mantissa.divrem(&divisor);
+        let round_away = match (remainder.abs() * BigInt::from(2)).cmp(&divisor) {
+            Ordering::Greater => true,
+            Ordering::Equal => sticky || !quotient.is_even(),
+            Ordering::Less => false
+        };
+        let quotient = if round_away {
+            quotient + BigInt::from(mantissa.signum() as i64)
+        } else {
+            quotient
+        };
+        BigFloat::normalize(quotient, exponent + dropped as i64)
+    }
+
+    pub fn zero() -> BigFloat {
+        BigFloat { mantissa: BigInt::zero(), exponent: 0 }
+    }
+
+    pub fn one() -> BigFloat {
+        BigFloat { mantissa: BigInt::one(), exponent: 0 }
+    }
+
+    pub fn from_big_integer(integer: &BigInt) -> BigFloat {
+        BigFloat::round(integer.clone(), 0, false, digits())
+    }
+
+    pub fn from_rational(rational: &Rational) -> BigFloat {
+        BigFloat::from_big_integer(rational.numer()).div_with(&BigFloat::from_big_integer(rational.denom()), digits())
+    }
+
+    // Exact decimal expansion of a finite float, rounded to the working precision
+    pub fn from_f64(value: f64) -> Option<BigFloat> {
+        if !value.is_finite() {
+            return None
+        }
+        if value == 0. {
+            return Some(BigFloat::zero())
+        }
+        let bits = value.to_bits();
+        let biased = ((bits >> 52) & 0x7ff) as i64;
+        let fraction = (bits & ((1 << 52) - 1)) as i64;
+        let (mantissa, exponent) = if biased == 0 {
+            (fraction, -1074)
+        } else {
+            (fraction | (1 << 52), biased - 1075)
+        };
+        let mantissa = if value < 0. { -BigInt::from(mantissa) } else { BigInt::from(mantissa) };
+        if exponent >= 0 {
+            return Some(BigFloat::round(mantissa.shl(exponent as u32), 0, false, digits()))
+        }
+        // m * 2^-k = m * 5^k * 10^-k
+        let scaled = mantissa * BigInt::from(5).pow((-exponent) as u32);
+        Some(BigFloat::round(scaled, exponent, false, digits()))
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        format!("{}e{}", self.mantissa, self.exponent).parse().unwrap_or(f64::NAN)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+
+    pub fn is_unity(&self) -> bool {
+        self.mantissa.is_one() && self.exponent == 0
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.mantissa.is_negative()
+    }
+
+    pub fn abs(&self) -> BigFloat {
+        BigFloat { mantissa: self.mantissa.abs(), exponent: self.exponent }
+    }
+
+    // Decimal exponent of the leading digit
+    fn top(&self) -> i64 {
+        self.exponent + decimal_digits(&self.mantissa) as i64 - 1
+    }
+
+    // Nearest integer, rounding halves away from zero
+    pub fn round_to_integer(&self) -> BigInt {
+        if self.exponent >= 0 {
+            return self.mantissa.clone() * pow10(self.exponent as usize)
+        }
+        let divisor = pow10((-self.exponent) as usize);
+        let (quotient, remainder) = self.mantissa.divrem(&divisor);
+        if remainder.abs() * BigInt::from(2) >= divisor {
+            quotient + BigInt::from(self.mantissa.signum() as i64)
+        } else {
+            quotient
+        }
+    }
+
+    fn add_with(&self, other: &BigFloat, precision: usize) -> BigFloat {
+        if self.is_zero() {
+            return BigFloat::round(other.mantissa.clone(), other.exponent, false, precision)
+        }
+        if other.is_zero() {
+            return BigFloat::round(self.mantissa.clone(), self.exponent, false, precision)
+        }
+        // An operand entirely below the last digit of the other cannot
+        // change the rounded sum
+        let gap = self.top() - other.top();
+        if gap > precision as i64 + 1 {
+            return BigFloat::round(self.mantissa.clone(), self.exponent, false, precision)
+        }
+        if -gap > precision as i64 + 1 {
+            return BigFloat::round(other.mantissa.clone(), other.exponent, false, precision)
+        }
+        let exponent = self.exponent.min(other.exponent);
+        let lhs = self.mantissa.clone() * pow10((self.exponent - exponent) as usize);
+        let rhs = other.mantissa.clone() * pow10((other.exponent - exponent) as usize);
+        BigFloat::round(lhs + rhs, exponent, false, precision)
+    }
+
+    fn mul_with(&self, other: &BigFloat, precision: usize) -> BigFloat {
+        BigFloat::round(self.mantissa.clone() * other.mantissa.clone(),
+                        self.exponent + other.exponent, false, precision)
+    }
+
+    fn div_with(&self, other: &BigFloat, precision: usize) -> BigFloat {
+        assert!(!other.is_zero(), "attempt to divide by zero");
+        // Scale the dividend so the quotient has at least one extra digit
+        let scale = (precision + 1 + decimal_digits(&other.mantissa))
+            .saturating_sub(decimal_digits(&self.mantissa));
+        let (quotient, remainder) = (self.mantissa.clone() * pow10(scale)).divrem(&other.mantissa);
+        BigFloat::round(quotient, self.exponent - scale as i64 - other.exponent,
+                        !remainder.is_zero(), precision)
+    }
+
+    fn sqrt_with(&self, precision: usize) -> BigFloat {
+        assert!(!self.is_negative(), "square root of a negative number");
+        if self.is_zero() {
+            return BigFloat::zero()
+        }
+        // Scale so the root has at least one extra digit and the exponent is even
+        let mut scale = (2 * (precision + 1)).saturating_sub(decimal_digits(&self.mantissa)) as i64;
+        if (self.exponent - scale) % 2 != 0 {
+            scale += 1;
+        }
+        let radicand = self.mantissa.clone() * pow10(scale as usize);
+        let root = radicand.isqrt();
+        let sticky = root.clone() * root.clone() != radicand;
+        BigFloat::round(root, (self.exponent - scale) / 2, sticky, precision)
+    }
+
+    fn powi_with(&self, pow: i64, precision: usize) -> BigFloat {
+        let working = precision + GUARD_DIGITS;
+        let mut base = if pow < 0 { BigFloat::one().div_with(self, working) } else { self.clone() };
+        let mut exponent = pow.unsigned_abs();
+        let mut result = BigFloat::one();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.mul_with(&base, working);
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.mul_with(&base, working);
+            }
+        }
+        result.rounded(precision)
+    }
+
+    fn rounded(&self, precision: usize) -> BigFloat {
+        BigFloat::round(self.mantissa.clone(), self.exponent, false, precision)
+    }
+
+    // Sum of a series whose terms are produced by next_term, until the
+    // terms drop below 10^-precision
+    fn sum_series<F>(first: BigFloat, precision: usize, mut next_term: F) -> BigFloat
+        where F: FnMut(&BigFloat, usize) -> BigFloat {
+        let mut sum = first.clone();
+        let mut term = first;
+        let mut index = 1;
+        while !term.is_zero() && term.top() >= -(precision as i64) {
+            term = next_term(&term, index);
+            sum = sum.add_with(&term, precision);
+            index += 1;
+        }
+        sum
+    }
+
+    fn exp_with(&self, precision: usize) -> BigFloat {
+        if self.is_zero() {
+            return BigFloat::one()
+        }
+        // Halve the argument k times, then square the result k times
+        let magnitude = self.to_f64().abs();
+        let halvings = if magnitude > 1e-3 { (magnitude.log2().max(0.) as usize) + 10 } else { 0 };
+        let working = precision + GUARD_DIGITS + halvings * 3 / 10 + 1;
+        let reduced = self.mul_with(&BigFloat::round(BigInt::from(5).pow(halvings as u32), -(halvings as i64), false, working), working);
+        let mut result = BigFloat::sum_series(BigFloat::one(), working, |term, n| {
+            term.mul_with(&reduced, working).div_with(&BigFloat::from_big_integer(&BigInt::from(n as i64)), working)
+        });
+        for _ in 0..halvings {
+            result = result.mul_with(&result, working);
+        }
+        result.rounded(precision)
+    }
+
+    fn ln_with(&self, precision: usize) -> BigFloat {
+        assert!(!self.is_negative() && !self.is_zero(), "logarithm of a non-positive number");
+        let working = precision + GUARD_DIGITS;
+        // ln(m * 10^t) = ln(m) + t ln(10) with m in [1, 10)
+        let shift = self.top();
+        let normalized = BigFloat { mantissa: self.mantissa.clone(), exponent: self.exponent - shift };
+        let result = normalized.ln_newton(working);
+        if shift == 0 {
+            return result.rounded(precision)
+        }
+        let extra = decimal_digits(&BigInt::from(shift));
+        let ln_ten = BigFloat::from_big_integer(&BigInt::from(10)).ln_newton(working + extra);
+        let scaled = ln_ten.mul_with(&BigFloat::round(BigInt::from(shift), 0, false, working + extra), working + extra);
+        result.add_with(&scaled, working).rounded(precision)
+    }
+
+    // Newton iteration y <- y + 2 (x - e^y) / (x + e^y), for x in [1, 10)
+    fn ln_newton(&self, precision: usize) -> BigFloat {
+        let mut result = BigFloat::from_f64(self.to_f64().ln()).unwrap_or_else(BigFloat::zero);
+        let two = BigFloat::from_big_integer(&BigInt::from(2));
+        for _ in 0..64 {
+            let power = result.exp_with(precision);
+            let delta = two.mul_with(&self.add_with(&-power.clone(), precision), precision)
+                .div_with(&self.add_with(&power, precision), precision);
+            result = result.add_with(&delta, precision);
+            if delta.is_zero() || delta.top() < -(precision as i64) {
+                break;
+            }
+        }
+        result
+    }
+
+    // atan(1 / n) for an integer n > 1
+    fn atan_reciprocal(n: i64, precision: usize) -> BigFloat {
+        let n_squared = BigFloat::from_big_integer(&BigInt::from(n * n));
+        let first = BigFloat::one().div_with(&BigFloat::from_big_integer(&BigInt::from(n)), precision);
+        let mut power = first.clone();
+        BigFloat::sum_series(first, precision, |_, k| {
+            power = -power.div_with(&n_squared, precision);
+            power.div_with(&BigFloat::from_big_integer(&BigInt::from(2 * k as i64 + 1)), precision)
+        })
+    }
+
+    // Machin's formula pi = 16 atan(1/5) - 4 atan(1/239)
+    pub fn pi_with(precision: usize) -> BigFloat {
+        let working = precision + GUARD_DIGITS;
+        let sixteen = BigFloat::from_big_integer(&BigInt::from(16));
+        let four = BigFloat::from_big_integer(&BigInt::from(4));
+        let pi = sixteen.mul_with(&BigFloat::atan_reciprocal(5, working), working)
+            .add_with(&-four.mul_with(&BigFloat::atan_reciprocal(239, working), working), working);
+        pi.rounded(precision)
+    }
+
+    pub fn pi() -> BigFloat {
+        BigFloat::pi_with(digits())
+    }
+
+    // Reduces the argument to [-pi, pi] and sums the Taylor series of sin
+    // (first_power = 1) or cos (first_power = 0)
+    fn sin_cos_with(&self, first_power: usize, precision: usize) -> BigFloat {
+        let working = precision + GUARD_DIGITS + self.top().max(0) as usize;
+        let two_pi = BigFloat::pi_with(working).mul_with(&BigFloat::from_big_integer(&BigInt::from(2)), working);
+        let turns = BigFloat::from_big_integer(&self.div_with(&two_pi, working).round_to_integer());
+        let reduced = self.add_with(&-turns.mul_with(&two_pi, working), working);
+        let squared = reduced.mul_with(&reduced, working);
+        let first = if first_power == 1 { reduced.rounded(working) } else { BigFloat::one() };
+        let result = BigFloat::sum_series(first, working, |term, k| {
+            let n = 2 * k + first_power;
+            let divisor = BigFloat::from_big_integer(&BigInt::from((n * (n - 1)) as i64));
+            -term.mul_with(&squared, working).div_with(&divisor, working)
+        });
+        result.rounded(precision)
+    }
+
+    fn half() -> BigFloat {
+        BigFloat { mantissa: BigInt::from(5), exponent: -1 }
+    }
+
+    // Halves the angle, atan(x) = 2 atan(x / (1 + sqrt(1 + x^2))), until
+    // |x| < 0.01 and the Taylor series converges quickly. Beyond 1,
+    // atan(x) = +-pi/2 - atan(1/x).
+    fn atan_with(&self, precision: usize) -> BigFloat {
+        if self.is_zero() {
+            return BigFloat::zero()
+        }
+        let working = precision + GUARD_DIGITS;
+        let one = BigFloat::one();
+        if self.abs() > one {
+            let half_pi = BigFloat::pi_with(working).mul_with(&BigFloat::half(), working);
+            let half_pi = if self.is_negative() { -half_pi } else { half_pi };
+            let complement = one.div_with(self, working).atan_with(working);
+            return half_pi.add_with(&-complement, working).rounded(precision)
+        }
+        let mut reduced = self.clone();
+        let mut halvings = 0;
+        while reduced.top() >= -2 {
+            let root = one.add_with(&reduced.mul_with(&reduced, working), working).sqrt_with(working);
+            reduced = reduced.div_with(&one.add_with(&root, working), working);
+            halvings += 1;
+        }
+        let squared = reduced.mul_with(&reduced, working);
+        let mut power = reduced.clone();
+        let result = BigFloat::sum_series(reduced, working, |_, k| {
+            power = -power.mul_with(&squared, working);
+            power.div_with(&BigFloat::from_big_integer(&BigInt::from(2 * k as i64 + 1)), working)
+        });
+        let scale = BigFloat::from_big_integer(&BigInt::from(1i64 << halvings));
+        result.mul_with(&scale, working).rounded(precision)
+    }
+
+    // asin(x) = atan(x / sqrt(1 - x^2)) for |x| <= 1
+    fn asin_with(&self, precision: usize) -> BigFloat {
+        let one = BigFloat::one();
+        assert!(self.abs() <= one, "arcsine outside [-1, 1]");
+        let working = precision + GUARD_DIGITS;
+        if self.abs() == one {
+            let half_pi = BigFloat::pi_with(working).mul_with(&BigFloat::half(), working);
+            let half_pi = if self.is_negative() { -half_pi } else { half_pi };
+            return half_pi.rounded(precision)
+        }
+        // (1 - x)(1 + x) keeps the digits 1 - x^2 would cancel
+        let cosine = one.add_with(&-self.clone(), working).mul_with(&one.add_with(self, working), working).sqrt_with(working);
+        self.div_with(&cosine, working).atan_with(working).rounded(precision)
+    }
+
+    // acos(x) = 2 atan(sqrt((1 - x) / (1 + x))) for |x| <= 1, which stays
+    // accurate near 1 where pi/2 - asin(x) would cancel
+    fn acos_with(&self, precision: usize) -> BigFloat {
+        let one = BigFloat::one();
+        assert!(self.abs() <= one, "arccosine outside [-1, 1]");
+        let working = precision + GUARD_DIGITS;
+        if *self == -one.clone() {
+            return BigFloat::pi_with(precision)
+        }
+        let ratio = one.add_with(&-self.clone(), working).div_with(&one.add_with(self, working), working);
+        let angle = ratio.sqrt_with(working).atan_with(working);
+        angle.mul_with(&BigFloat::from_big_integer(&BigInt::from(2)), working).rounded(precision)
+    }
+
+    // (e^x - e^-x) / 2 for sinh and (e^x + e^-x) / 2 for cosh. The
+    // difference cancels about as many digits as x has leading zeros.
+    fn sinh_cosh_with(&self, sign: i64, precision: usize) -> BigFloat {
+        let working = precision + GUARD_DIGITS + (-self.top()).max(0) as usize;
+        let power = self.exp_with(working);
+        let inverse = BigFloat::one().div_with(&power, working);
+        let inverse = if sign < 0 { -inverse } else { inverse };
+        power.add_with(&inverse, working).mul_with(&BigFloat::half(), working).rounded(precision)
+    }
+
+    // sinh(x) / cosh(x), which is +-1 to the precision once e^-2|x| drops
+    // below its last digit
+    fn tanh_with(&self, precision: usize) -> BigFloat {
+        if self.is_zero() {
+            return BigFloat::zero()
+        }
+        if self.abs() > BigFloat::from_big_integer(&BigInt::from(precision as i64 + 1)) {
+            let one = BigFloat::one();
+            return if self.is_negative() { -one } else { one }
+        }
+        let working = precision + GUARD_DIGITS;
+        self.sinh_cosh_with(-1, working).div_with(&self.sinh_cosh_with(1, working), working).rounded(precision)
+    }
+
+    pub fn sqrt(&self) -> BigFloat {
+        self.sqrt_with(digits())
+    }
+
+    pub fn powi(&self, pow: i64) -> BigFloat {
+        self.powi_with(pow, digits())
+    }
+
+    pub fn exp(&self) -> BigFloat {
+        self.exp_with(digits())
+    }
+
+    pub fn ln(&self) -> BigFloat {
+        self.ln_with(digits())
+    }
+
+    pub fn sin(&self) -> BigFloat {
+        self.sin_cos_with(1, digits())
+    }
+
+    pub fn cos(&self) -> BigFloat {
+        self.sin_cos_with(0, digits())
+    }
+
+    pub fn atan(&self) -> BigFloat {
+        self.atan_with(digits())
+    }
+
+    pub fn asin(&self) -> BigFloat {
+        self.asin_with(digits())
+    }
+
+    pub fn acos(&self) -> BigFloat {
+        self.acos_with(digits())
+    }
+
+    pub fn sinh(&self) -> BigFloat {
+        self.sinh_cosh_with(-1, digits())
+    }
+
+    pub fn cosh(&self) -> BigFloat {
+        self.sinh_cosh_with(1, digits())
+    }
+
+    pub fn tanh(&self) -> BigFloat {
+        self.tanh_with(digits())
+    }
+}
+
+impl fmt::Display for BigFloat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0")
+        }
+        let sign = if self.is_negative() { "-" } else { "" };
+        let digits = self.mantissa.abs().to_string();
+        let point = digits.len() as i64 + self.exponent;
+        if self.exponent >= 0 && point <= 21 {
+            write!(f, "{}{}{}", sign, digits, "0".repeat(self.exponent as usize))
+        } else if point > 0 && point <= 21 {
+            let (whole, fraction) = digits.split_at(point as usize);
+            write!(f, "{}{}.{}", sign, whole, fraction)
+        } else if point <= 0 && point > -7 {
+            write!(f, "{}0.{}{}", sign, "0".repeat((-point) as usize), digits)
+        } else {
+            let (first, rest) = digits.split_at(1);
+            let separator = if rest.is_empty() { "" } else { "." };
+            write!(f, "{}{}{}{}e{}", sign, first, separator, rest, point - 1)
+        }
+    }
+}
+
+impl fmt::Debug for BigFloat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParseBigFloatError;
+
+// Parses decimal literals such as 1.25, .5 and 6.02e23, rounded to the
+// working precision
+impl FromStr for BigFloat {
+    type Err = ParseBigFloatError;
+
+    fn from_str(string: &str) -> Result<BigFloat, ParseBigFloatError> {
+        let (number, exponent) = match string.find(['e', 'E']) {
+            Some(index) => {
+                let exponent: i64 = string[index + 1..].parse().map_err(|_| ParseBigFloatError)?;
+                (&string[..index], exponent)
+            },
+            None => (string, 0)
+        };
+        let (whole, fraction) = match number.find('.') {
+            Some(index) => (&number[..index], &number[index + 1..]),
+            None => (number, "")
+        };
+        let (negative, whole) = match whole.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, whole.strip_prefix('+').unwrap_or(whole))
+        };
+        if whole.is_empty() && fraction.is_empty() {
+            return Err(ParseBigFloatError)
+        }
+        if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseBigFloatError)
+        }
+        let literal = format!("{}{}", whole, fraction);
+        let mantissa: BigInt = literal.parse().map_err(|_| ParseBigFloatError)?;
+        let mantissa = if negative { -mantissa } else { mantissa };
+        Ok(BigFloat::round(mantissa, exponent - fraction.len() as i64, false, digits()))
+    }
+}
+
+impl PartialOrd for BigFloat {
+    fn partial_cmp(&self, other: &BigFloat) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigFloat {
+    fn cmp(&self, other: &BigFloat) -> Ordering {
+        match (self.mantissa.signum(), other.mantissa.signum()) {
+            (lhs, rhs) if lhs != rhs => return lhs.cmp(&rhs),
+            (0, _) => return Ordering::Equal,
+            _ => {}
+        }
+        let exponent = self.exponent.min(other.exponent);
+        let lhs = self.mantissa.clone() * pow10((self.exponent - exponent) as usize);
+        let rhs = other.mantissa.clone() * pow10((other.exponent - exponent) as usize);
+        lhs.cmp(&rhs)
+    }
+}
+
+impl Neg for BigFloat {
+    type Output = BigFloat;
+
+    fn neg(self) -> BigFloat {
+        BigFloat { mantissa: -self.mantissa, exponent: self.exponent }
+    }
+}
+
+impl Add for BigFloat {
+    type Output = BigFloat;
+
+    fn add(self, other: BigFloat) -> BigFloat {
+        self.add_with(&other, digits())
+    }
+}
+
+impl Sub for BigFloat {
+    type Output = BigFloat;
+
+    fn sub(self, other: BigFloat) -> BigFloat {
+        self.add_with(&-other, digits())
+    }
+}
+
+impl Mul for BigFloat {
+    type Output = BigFloat;
+
+    fn mul(self, other: BigFloat) -> BigFloat {
+        self.mul_with(&other, digits())
+    }
+}
+
+impl Div for BigFloat {
+    type Output = BigFloat;
+
+    fn div(self, other: BigFloat) -> BigFloat {
+        self.div_with(&other, digits())
+    }
+}
+
+#[cfg(test)]
+fn decimal(string: &str) -> BigFloat {
+    string.parse().unwrap()
+}
+
+#[test]
+fn test_bigfloat_parsing_and_display() {
+    assert_eq!(decimal("1.25").to_string(), "1.25");
+    assert_eq!(decimal(".5").to_string(), "0.5");
+    assert_eq!(decimal("-6.02e23").to_string(), "-6.02e23");
+    assert_eq!(decimal("6.02e25").to_string(), "6.02e25");
+    assert_eq!(decimal("1e-9").to_string(), "1e-9");
+    assert_eq!(decimal("0.000120").to_string(), "0.00012");
+    assert_eq!(decimal("100"), BigFloat::from_big_integer(&BigInt::from(100)));
+    assert!("1.2.3".parse::<BigFloat>().is_err());
+    assert!(".".parse::<BigFloat>().is_err());
+    assert_eq!(BigFloat::from_f64(0.1).unwrap().to_string(),
+               "0.1000000000000000055511151231257827021181583404541");
+    assert_eq!(decimal("2.5").to_f64(), 2.5);
+}
+
+#[test]
+fn test_bigfloat_correct_rounding() {
+    let one = BigFloat::one();
+    let three = decimal("3");
+
+    assert_eq!((one.clone() / three.clone()).to_string(),
+               "0.33333333333333333333333333333333333333333333333333");
+    assert_eq!((decimal("2") / three.clone()).to_string(),
+               "0.66666666666666666666666666666666666666666666666667");
+    assert_eq!((decimal("0.1") + decimal("0.2")).to_string(), "0.3");
+    assert_eq!((decimal("1e60") + one.clone()).to_string(), "1e60");
+    assert_eq!((decimal("1e49") + decimal("0.5")).to_string(), "1e49");
+    assert_eq!((decimal("1e49") + decimal("1.5")).to_string(), "1.0000000000000000000000000000000000000000000000002e49");
+    assert_eq!(BigFloat::round(BigInt::from(25), 0, true, 1).to_string(), "30");
+    assert_eq!((three.clone() * (one.clone() / three.clone())).to_string(),
+               "0.99999999999999999999999999999999999999999999999999");
+    assert_eq!(decimal("2").sqrt().to_string(), "1.4142135623730950488016887242096980785696718753769");
+    assert_eq!(decimal("1e-100").sqrt().to_string(), "1e-50");
+    assert!(one.clone() / three < decimal("0.34"));
+    assert_eq!(decimal("-1.5").round_to_integer(), BigInt::from(-2));
+    assert_eq!(decimal("1.5").powi(-2).to_string(), "0.44444444444444444444444444444444444444444444444444");
+}
+
+#[test]
+fn test_bigfloat_transcendental_functions() {
+    assert_eq!(BigFloat::one().exp().to_string(), "2.7182818284590452353602874713526624977572470937");
+    assert_eq!(decimal("2").ln().to_string(), "0.69314718055994530941723212145817656807550013436026");
+    assert_eq!(BigFloat::pi().to_string(), "3.1415926535897932384626433832795028841971693993751");
+    assert_eq!(BigFloat::one().sin().to_string(), "0.84147098480789650665250232163029899962256306079837");
+    assert_eq!(BigFloat::one().cos().to_string(), "0.54030230586813971740093660744297660373231042061792");
+    assert_eq!(decimal("-20").exp().to_string(), "2.0611536224385578279659403801558209763758072755991e-9");
+    assert_eq!(decimal("1e10").ln().to_string(), "23.025850929940456840179914546843642076011014886288");
+    assert_eq!(decimal("100").sin().to_string(), "-0.50636564110975879365655761045978543206503272129066");
+    // Inverse trigonometric and hyperbolic functions
+    assert_eq!(decimal("-3").atan().to_string(), "-1.2490457723982544258299170772810901230778294041299");
+    assert_eq!(decimal("0.5").asin().to_string(), "0.52359877559829887307710723054658381403286156656252");
+    assert_eq!(decimal("0.999").asin().to_string(), "1.5260712396261631879816254589682003721944041429254");
+    assert_eq!(decimal("0.99999999").acos().to_string(), "0.0001414213563551606353430918368885808679749165219846");
+    assert_eq!(decimal("-1").acos(), BigFloat::pi());
+    assert_eq!(BigFloat::one().sinh().to_string(), "1.1752011936438014568823818505956008151557179813341");
+    assert_eq!(BigFloat::one().cosh().to_string(), "1.5430806348152437784779056207570616826015291123659");
+    assert_eq!(decimal("0.5").tanh().to_string(), "0.46211715726000975850231848364367254873028928033011");
+    assert_eq!(decimal("1e-20").sinh().to_string(), "1.0000000000000000000000000000000000000000166666667e-20");
+    assert_eq!(decimal("-200").tanh(), -BigFloat::one());
+}
+
+#[test]
+fn test_bigfloat_precision_setting() {
+    set_precision(Some(10));
+    assert_eq!(precision(), Some(10));
+    assert_eq!((BigFloat::one() / decimal("7")).to_string(), "0.1428571429");
+    assert_eq!(BigFloat::pi().to_string(), "3.141592654");
+    set_precision(Some(usize::MAX));
+    assert_eq!(precision(), Some(MAX_PRECISION));
+    set_precision(Some(0));
+    assert_eq!(precision(), None);
+}
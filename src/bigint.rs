@@ -0,0 +1,582 @@
+#![allow(dead_code)]
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use std::ops::{Add, Sub, Mul, Div, Rem, Neg};
+
+type Limb = u32;
+type DoubleLimb = u64;
+
+const LIMB_BITS: u32 = 32;
+// Largest power of ten that fits in a limb, used for decimal conversion
+const DECIMAL_BASE: Limb = 1_000_000_000;
+const DECIMAL_DIGITS: usize = 9;
+
+// Arbitrary-precision signed integer. The magnitude is stored as
+// little-endian base 2^32 limbs without trailing zero limbs, and zero
+// is never negative, so structurally equal values are numerically equal.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    magnitude: Vec<Limb>
+}
+
+fn trim(magnitude: &mut Vec<Limb>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn cmp_magnitude(lhs: &[Limb], rhs: &[Limb]) -> Ordering {
+    if lhs.len() != rhs.len() {
+        return lhs.len().cmp(&rhs.len())
+    }
+    for (a, b) in lhs.iter().rev().zip(rhs.iter().rev()) {
+        if a != b {
+            return a.cmp(b)
+        }
+    }
+    Ordering::Equal
+}
+
+fn add_magnitude(lhs: &[Limb], rhs: &[Limb]) -> Vec<Limb> {
+    let (long, short) = if lhs.len() >= rhs.len() { (lhs, rhs) } else { (rhs, lhs) };
+    let mut result = Vec::with_capacity(long.len() + 1);
+    let mut carry: DoubleLimb = 0;
+    for (i, limb) in long.iter().enumerate() {
+        let sum = *limb as DoubleLimb + *short.get(i).unwrap_or(&0) as DoubleLimb + carry;
+        result.push(sum as Limb);
+        carry = sum >> LIMB_BITS;
+    }
+    if carry > 0 {
+        result.push(carry as Limb);
+    }
+    result
+}
+
+// Requires lhs >= rhs in magnitude
+fn sub_magnitude(lhs: &[Limb], rhs: &[Limb]) -> Vec<Limb> {
+    let mut result = Vec::with_capacity(lhs.len());
+    let mut borrow = false;
+    for (i, limb) in lhs.iter().enumerate() {
+        let (diff, underflow_a) = limb.overflowing_sub(*rhs.get(i).unwrap_or(&0));
+        let (diff, underflow_b) = diff.overflowing_sub(borrow as Limb);
+        result.push(diff);
+        borrow = underflow_a || underflow_b;
+    }
+    trim(&mut result);
+    result
+}
+
+fn mul_magnitude(lhs: &[Limb], rhs: &[Limb]) -> Vec<Limb> {
+    if lhs.is_empty() || rhs.is_empty() {
+        return Vec::new()
+    }
+    let mut result = vec![0 as Limb; lhs.len() + rhs.len()];
+    for (i, a) in lhs.iter().enumerate() {
+        let mut carry: DoubleLimb = 0;
+        for (j, b) in rhs.iter().enumerate() {
+            let product = *a as DoubleLimb * *b as DoubleLimb + result[i + j] as DoubleLimb + carry;
+            result[i + j] = product as Limb;
+            carry = product >> LIMB_BITS;
+        }
+        result[i + rhs.len()] = carry as Limb;
+    }
+    trim(&mut result);
+    result
+}
+
+fn mul_add_small(magnitude: &mut Vec<Limb>, factor: Limb, addend: Limb) {
+    let mut carry = addend as DoubleLimb;
+    for limb in magnitude.iter_mut() {
+        let product = *limb as DoubleLimb * factor as DoubleLimb + carry;
+        *limb = product as Limb;
+        carry = product >> LIMB_BITS;
+    }
+    if carry > 0 {
+        magnitude.push(carry as Limb);
+    }
+    trim(magnitude);
+}
+
+fn divrem_small(magnitude: &[Limb], divisor: Limb) -> (Vec<Limb>, Limb) {
+    let mut quotient = vec![0 as Limb; magnitude.len()];
+    let mut remainder: DoubleLimb = 0;
+    for i in (0..magnitude.len()).rev() {
+        let current = (remainder << LIMB_BITS) | magnitude[i] as DoubleLimb;
+        quotient[i] = (current / divisor as DoubleLimb) as Limb;
+        remainder = current % divisor as DoubleLimb;
+    }
+    trim(&mut quotient);
+    (quotient, remainder as Limb)
+}
+
+fn shl_magnitude(magnitude: &[Limb], shift: u32) -> Vec<Limb> {
+    if magnitude.is_empty() {
+        return Vec::new()
+    }
+    let limbs = (shift / LIMB_BITS) as usize;
+    let bits = shift % LIMB_BITS;
+    let mut result = vec![0 as Limb; limbs];
+    if bits == 0 {
+        result.extend_from_slice(magnitude);
+    } else {
+        let mut carry: Limb = 0;
+        for limb in magnitude {
+            result.push((limb << bits) | carry);
+            carry = limb >> (LIMB_BITS - bits);
+        }
+        result.push(carry);
+    }
+    trim(&mut result);
+    result
+}
+
+fn shr_magnitude(magnitude: &[Limb], shift: u32) -> Vec<Limb> {
+    let limbs = (shift / LIMB_BITS) as usize;
+    if limbs >= magnitude.len() {
+        return Vec::new()
+    }
+    let bits = shift % LIMB_BITS;
+    let source = &magnitude[limbs..];
+    let mut result: Vec<Limb> = if bits == 0 {
+        source.to_vec()
+    } else {
+        (0..source.len()).map(|i| {
+            let high = source.get(i + 1).map_or(0, |next| next << (LIMB_BITS - bits));
+            (source[i] >> bits) | high
+        }).collect()
+    };
+    trim(&mut result);
+    result
+}
+
+// Schoolbook long division (Knuth, TAOCP vol. 2, algorithm D)
+fn divrem_magnitude(dividend: &[Limb], divisor: &[Limb]) -> (Vec<Limb>, Vec<Limb>) {
+    assert!(!divisor.is_empty(), "attempt to divide by zero");
+    if cmp_magnitude(dividend, divisor) == Ordering::Less {
+        return (Vec::new(), dividend.to_vec())
+    }
+    if divisor.len() == 1 {
+        let (quotient, remainder) = divrem_small(dividend, divisor[0]);
+        let remainder = if remainder == 0 { Vec::new() } else { vec![remainder] };
+        return (quotient, remainder)
+    }
+
+    // Normalize so the top limb of the divisor has its high bit set
+    let shift = divisor[divisor.len() - 1].leading_zeros();
+    let n = divisor.len();
+    let m = dividend.len() - n;
+    let v = shl_magnitude(divisor, shift);
+    let mut u = shl_magnitude(dividend, shift);
+    u.resize(dividend.len() + 1, 0);
+
+    let base: DoubleLimb = 1 << LIMB_BITS;
+    let mut quotient = vec![0 as Limb; m + 1];
+    for j in (0..m + 1).rev() {
+        let numerator = ((u[j + n] as DoubleLimb) << LIMB_BITS) | u[j + n - 1] as DoubleLimb;
+        let mut qhat = numerator / v[n - 1] as DoubleLimb;
+        let mut rhat = numerator % v[n - 1] as DoubleLimb;
+        while qhat >= base || qhat * v[n - 2] as DoubleLimb > ((rhat << LIMB_BITS) | u[j + n - 2] as DoubleLimb) {
+            qhat -= 1;
+            rhat += v[n - 1] as DoubleLimb;
+            if rhat >= base {
+                break;
+            }
+        }
+
+        // Multiply and subtract qhat * v from the current window of u
+        let mut borrow: i64 = 0;
+        let mut carry: DoubleLimb = 0;
+        for i in 0..n {
+            let product = qhat * v[i] as DoubleLimb + carry;
+            carry = product >> LIMB_BITS;
+            let diff = u[i + j] as i64 - borrow - (product & (base - 1)) as i64;
+            u[i + j] = diff as Limb;
+            borrow = if diff < 0 { 1 } else { 0 };
+        }
+        let diff = u[j + n] as i64 - borrow - carry as i64;
+        u[j + n] = diff as Limb;
+
+        // qhat was one too large, so add the divisor back
+        if diff < 0 {
+            qhat -= 1;
+            let mut carry: DoubleLimb = 0;
+            for i in 0..n {
+                let sum = u[i + j] as DoubleLimb + v[i] as DoubleLimb + carry;
+                u[i + j] = sum as Limb;
+                carry = sum >> LIMB_BITS;
+            }
+            u[j + n] = u[j + n].wrapping_add(carry as Limb);
+        }
+        quotient[j] = qhat as Limb;
+    }
+
+    trim(&mut quotient);
+    u.truncate(n);
+    trim(&mut u);
+    (quotient, shr_magnitude(&u, shift))
+}
+
+impl BigInt {
+    fn from_parts(negative: bool, mut magnitude: Vec<Limb>) -> BigInt {
+        trim(&mut magnitude);
+        let negative = negative && !magnitude.is_empty();
+        BigInt { negative, magnitude }
+    }
+
+    pub fn zero() -> BigInt {
+        BigInt::from_parts(false, Vec::new())
+    }
+
+    pub fn one() -> BigInt {
+        BigInt::from(1)
+    }
+
+    // Truncates towards zero, non-finite values become zero
+    pub fn from_f64(value: f64) -> BigInt {
+        if !value.is_finite() || value.abs() < 1. {
+            return BigInt::zero()
+        }
+        let bits = value.to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as i32 - 1075;
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let magnitude = vec![mantissa as Limb, (mantissa >> LIMB_BITS) as Limb];
+        let magnitude = if exponent >= 0 {
+            shl_magnitude(&magnitude, exponent as u32)
+        } else {
+            shr_magnitude(&magnitude, (-exponent) as u32)
+        };
+        BigInt::from_parts(value < 0., magnitude)
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None
+        }
+        let magnitude = self.magnitude.iter().rev()
+            .fold(0u64, |acc, limb| (acc << LIMB_BITS) | *limb as u64);
+        if self.negative {
+            if magnitude <= i64::MAX as u64 + 1 {
+                Some((magnitude as i64).wrapping_neg())
+            } else {
+                None
+            }
+        } else if magnitude <= i64::MAX as u64 {
+            Some(magnitude as i64)
+        } else {
+            None
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let magnitude = self.magnitude.iter().rev()
+            .fold(0., |acc, limb| acc * (1u64 << LIMB_BITS) as f64 + *limb as f64);
+        if self.negative { -magnitude } else { magnitude }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.magnitude.is_empty()
+    }
+
+    pub fn is_one(&self) -> bool {
+        !self.negative && self.magnitude == [1]
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_even(&self) -> bool {
+        self.magnitude.first().is_none_or(|limb| limb & 1 == 0)
+    }
+
+    pub fn signum(&self) -> i32 {
+        if self.is_zero() { 0 } else if self.negative { -1 } else { 1 }
+    }
+
+    pub fn abs(&self) -> BigInt {
+        BigInt::from_parts(false, self.magnitude.clone())
+    }
+
+    // Number of significant bits in the magnitude
+    pub fn bits(&self) -> u64 {
+        match self.magnitude.last() {
+            Some(top) => (self.magnitude.len() as u64 - 1) * LIMB_BITS as u64 +
+                         (LIMB_BITS - top.leading_zeros()) as u64,
+            None => 0
+        }
+    }
+
+    pub fn shl(&self, shift: u32) -> BigInt {
+        BigInt::from_parts(self.negative, shl_magnitude(&self.magnitude, shift))
+    }
+
+    // Shifts the magnitude, rounding towards zero
+    pub fn shr(&self, shift: u32) -> BigInt {
+        BigInt::from_parts(self.negative, shr_magnitude(&self.magnitude, shift))
+    }
+
+    // Quotient rounded towards zero and remainder with the sign of the dividend
+    pub fn divrem(&self, other: &BigInt) -> (BigInt, BigInt) {
+        let (quotient, remainder) = divrem_magnitude(&self.magnitude, &other.magnitude);
+        (BigInt::from_parts(self.negative != other.negative, quotient),
+         BigInt::from_parts(self.negative, remainder))
+    }
+
+    // Quotient rounded towards negative infinity and non-negative remainder
+    // for a positive divisor
+    pub fn div_mod_floor(&self, other: &BigInt) -> (BigInt, BigInt) {
+        let (quotient, remainder) = self.divrem(other);
+        if !remainder.is_zero() && remainder.negative != other.negative {
+            (quotient - BigInt::one(), remainder + other.clone())
+        } else {
+            (quotient, remainder)
+        }
+    }
+
+    pub fn pow(&self, pow: u32) -> BigInt {
+        let mut base = self.clone();
+        let mut exponent = pow;
+        let mut result = BigInt::one();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base.clone();
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.clone() * base;
+            }
+        }
+        result
+    }
+
+    pub fn gcd(&self, other: &BigInt) -> BigInt {
+        let mut a = self.abs();
+        let mut b = other.abs();
+        while !b.is_zero() {
+            let (_, remainder) = a.divrem(&b);
+            a = b;
+            b = remainder;
+        }
+        a
+    }
+
+    // Largest integer whose square does not exceed self, for self >= 0
+    pub fn isqrt(&self) -> BigInt {
+        assert!(!self.negative, "square root of a negative integer");
+        if self.is_zero() {
+            return BigInt::zero()
+        }
+        // Newton iteration from an initial guess above the root
+        let mut x = BigInt::one().shl((self.bits() / 2 + 1) as u32);
+        loop {
+            let (quotient, _) = self.divrem(&x);
+            let y = (x.clone() + quotient).shr(1);
+            if y >= x {
+                return x
+            }
+            x = y;
+        }
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(value: i64) -> BigInt {
+        let magnitude = value.unsigned_abs();
+        BigInt::from_parts(value < 0, vec![magnitude as Limb, (magnitude >> LIMB_BITS) as Limb])
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0")
+        }
+        let mut chunks: Vec<Limb> = Vec::new();
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = divrem_small(&magnitude, DECIMAL_BASE);
+            chunks.push(remainder);
+            magnitude = quotient;
+        }
+        let mut digits = String::new();
+        if self.negative {
+            digits.push('-');
+        }
+        digits.push_str(&chunks.pop().unwrap_or(0).to_string());
+        for chunk in chunks.iter().rev() {
+            digits.push_str(&format!("{:0width$}", chunk, width = DECIMAL_DIGITS));
+        }
+        write!(f, "{}", digits)
+    }
+}
+
+impl fmt::Debug for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParseBigIntError;
+
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    fn from_str(string: &str) -> Result<BigInt, ParseBigIntError> {
+        let (negative, digits) = match string.as_bytes().first() {
+            Some(b'-') => (true, &string[1..]),
+            Some(b'+') => (false, &string[1..]),
+            _ => (false, string)
+        };
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseBigIntError)
+        }
+        let mut magnitude: Vec<Limb> = Vec::new();
+        let first_chunk = digits.len() % DECIMAL_DIGITS;
+        let mut start = 0;
+        let mut end = if first_chunk == 0 { DECIMAL_DIGITS } else { first_chunk };
+        while start < digits.len() {
+            let chunk: Limb = digits[start..end].parse().map_err(|_| ParseBigIntError)?;
+            let factor = (10 as Limb).pow((end - start) as u32);
+            mul_add_small(&mut magnitude, factor, chunk);
+            start = end;
+            end += DECIMAL_DIGITS;
+        }
+        Ok(BigInt::from_parts(negative, magnitude))
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &BigInt) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &BigInt) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude)
+        }
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        BigInt::from_parts(!self.negative, self.magnitude)
+    }
+}
+
+impl Add for BigInt {
+    type Output = BigInt;
+
+    fn add(self, other: BigInt) -> BigInt {
+        if self.negative == other.negative {
+            return BigInt::from_parts(self.negative, add_magnitude(&self.magnitude, &other.magnitude))
+        }
+        match cmp_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::from_parts(other.negative, sub_magnitude(&other.magnitude, &self.magnitude)),
+            _ => BigInt::from_parts(self.negative, sub_magnitude(&self.magnitude, &other.magnitude))
+        }
+    }
+}
+
+impl Sub for BigInt {
+    type Output = BigInt;
+
+    fn sub(self, other: BigInt) -> BigInt {
+        self + (-other)
+    }
+}
+
+impl Mul for BigInt {
+    type Output = BigInt;
+
+    fn mul(self, other: BigInt) -> BigInt {
+        BigInt::from_parts(self.negative != other.negative, mul_magnitude(&self.magnitude, &other.magnitude))
+    }
+}
+
+impl Div for BigInt {
+    type Output = BigInt;
+
+    fn div(self, other: BigInt) -> BigInt {
+        self.divrem(&other).0
+    }
+}
+
+impl Rem for BigInt {
+    type Output = BigInt;
+
+    fn rem(self, other: BigInt) -> BigInt {
+        self.divrem(&other).1
+    }
+}
+
+#[cfg(test)]
+fn big(string: &str) -> BigInt {
+    string.parse().unwrap()
+}
+
+#[test]
+fn test_bigint_conversions() {
+    for value in [0, 1, -1, 42, -4_294_967_296, i64::MAX, i64::MIN].iter() {
+        let converted = BigInt::from(*value);
+        assert_eq!(converted.to_i64(), Some(*value));
+        assert_eq!(converted.to_string(), value.to_string());
+        assert_eq!(big(&value.to_string()), converted);
+    }
+    assert_eq!((BigInt::from(i64::MAX) + BigInt::one()).to_i64(), None);
+    assert_eq!(big("-0"), BigInt::zero());
+    assert!("12a".parse::<BigInt>().is_err());
+    assert!("".parse::<BigInt>().is_err());
+    assert_eq!(BigInt::from_f64(-1e20).to_string(), "-100000000000000000000");
+    assert_eq!(BigInt::from_f64(2.9), BigInt::from(2));
+    assert_eq!(big("1180591620717411303424").to_f64(), 2f64.powi(70));
+}
+
+#[test]
+fn test_bigint_arithmetic() {
+    let a = big("123456789012345678901234567890");
+    let b = big("-987654321098765432109876543210");
+
+    assert_eq!((a.clone() + b.clone()).to_string(), "-864197532086419753208641975320");
+    assert_eq!((a.clone() - b.clone()).to_string(), "1111111110111111111011111111100");
+    assert_eq!((a.clone() * b.clone()).to_string(),
+               "-121932631137021795226185032733622923332237463801111263526900");
+    assert_eq!((b.clone() / a.clone()).to_string(), "-8");
+    assert_eq!((b.clone() % a.clone()).to_string(), "-9000000000900000000090");
+    assert_eq!(b.div_mod_floor(&a).0.to_string(), "-9");
+    assert_eq!(a.gcd(&b).to_string(), "9000000000900000000090");
+    assert!(b < a);
+    assert!(-a.clone() < BigInt::zero());
+}
+
+#[test]
+fn test_bigint_division() {
+    let factorial = (1..31).fold(BigInt::one(), |acc, n| acc * BigInt::from(n));
+    assert_eq!(factorial.to_string(), "265252859812191058636308480000000");
+
+    let divisor = big("4294967297000000000001");
+    let (quotient, remainder) = factorial.divrem(&divisor);
+    assert!(remainder < divisor);
+    assert_eq!(quotient * divisor + remainder, factorial);
+    assert_eq!(factorial.clone() / factorial.clone(), BigInt::one());
+    assert_eq!(big("340282366920938463463374607431768211456").isqrt(), big("18446744073709551616"));
+    assert_eq!(big("99").isqrt(), BigInt::from(9));
+}
+
+#[test]
+fn test_bigint_pow() {
+    assert_eq!(BigInt::from(2).pow(70).to_string(), "1180591620717411303424");
+    assert_eq!(BigInt::from(-3).pow(3), BigInt::from(-27));
+    assert_eq!(BigInt::from(7).pow(0), BigInt::one());
+    assert_eq!(BigInt::from(1).shl(100).shr(99), BigInt::from(2));
+}
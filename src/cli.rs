@@ -1,25 +1,42 @@
 use std::io;
 use std::io::{Write};
-use symengine::{Engine};
+use sym::{Engine, EngineError};
 
 pub fn run() {
     show_welcome();
     let mut engine = Engine::new();
     loop {
-        let input = get_input();
+        let input = match get_input() {
+            Some(input) => input,
+            None => {
+                println!();
+                show_farewell();
+                break;
+            }
+        };
         match input.trim() {
             "quit" => {
                 show_farewell(); 
                 break;
             },
             "help" => show_help(),
-            _ => engine.interpret(&input)
+            "" => continue,
+            _ => match engine.interpret(&input) {
+                Ok(output) => println!("  {}", output),
+                Err(error) => show_error(&input, &error)
+            }
         }
     }
 }
 
-fn parse_input(string: &String) {
-    println!("I have no idea how to parse this stuff");
+// Errors with a position in the input get a caret under it
+fn show_error(input: &String, error: &EngineError) {
+    println!("  error: {}", error);
+    if let Some(span) = error.span() {
+        for line in span.underline(input).lines() {
+            println!("  {}", line);
+        }
+    }
 }
 
 fn show_welcome() {
@@ -40,10 +57,16 @@ fn show_farewell() {
     println!("   FAke software! Sad!");
 }
 
-fn get_input() -> String {
+// The next line of input, None at the end of the input or once reading
+// or writing fails
+fn get_input() -> Option<String> {
     let mut input = String::new();
     print!(">> ");
-    io::stdout().flush();
-    io::stdin().read_line(&mut input);
-    return input
+    if io::stdout().flush().is_err() {
+        return None
+    }
+    match io::stdin().read_line(&mut input) {
+        Ok(0) | Err(_) => return None,
+        Ok(_) => return Some(input)
+    }
 }
\ No newline at end of file
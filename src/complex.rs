@@ -0,0 +1,299 @@
+#![allow(dead_code)]
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Sub, Mul, Div, Neg};
+
+pub type RealPart = f64;
+
+// Complex number with real and imaginary parts
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Complex {
+    pub re: RealPart,
+    pub im: RealPart
+}
+
+impl Complex {
+    pub fn new(re: RealPart, im: RealPart) -> Complex {
+        Complex { re, im }
+    }
+
+    pub fn from_real(re: RealPart) -> Complex {
+        Complex::new(re, 0.)
+    }
+
+    pub fn i() -> Complex {
+        Complex::new(0., 1.)
+    }
+
+    pub fn zero() -> Complex {
+        Complex::new(0., 0.)
+    }
+
+    pub fn one() -> Complex {
+        Complex::new(1., 0.)
+    }
+
+    pub fn from_polar(radius: RealPart, angle: RealPart) -> Complex {
+        Complex::new(radius * angle.cos(), radius * angle.sin())
+    }
+
+    pub fn is_real(&self) -> bool {
+        self.im == 0.
+    }
+
+    pub fn conj(&self) -> Complex {
+        Complex::new(self.re, -self.im)
+    }
+
+    pub fn norm_sqr(&self) -> RealPart {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn abs(&self) -> RealPart {
+        self.re.hypot(self.im)
+    }
+
+    pub fn arg(&self) -> RealPart {
+        self.im.atan2(self.re)
+    }
+
+    pub fn recip(&self) -> Complex {
+        Complex::one() / *self
+    }
+
+    pub fn exp(&self) -> Complex {
+        Complex::from_polar(self.re.exp(), self.im)
+    }
+
+    // Principal branch of the natural logarithm
+    pub fn ln(&self) -> Complex {
+        Complex::new(self.abs().ln(), self.arg())
+    }
+
+    pub fn powi(&self, pow: i64) -> Complex {
+        let mut base = if pow < 0 { self.recip() } else { *self };
+        let mut exponent = pow.unsigned_abs();
+        let mut result = Complex::one();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent >>= 1;
+        }
+        result
+    }
+
+    // Principal square root, with a non-negative real part
+    pub fn sqrt(&self) -> Complex {
+        let radius = self.abs();
+        let re = ((radius + self.re) / 2.).sqrt();
+        let im = ((radius - self.re) / 2.).sqrt();
+        Complex::new(re, if self.im < 0. { -im } else { im })
+    }
+
+    pub fn sin(&self) -> Complex {
+        Complex::new(self.re.sin() * self.im.cosh(), self.re.cos() * self.im.sinh())
+    }
+
+    pub fn cos(&self) -> Complex {
+        Complex::new(self.re.cos() * self.im.cosh(), -self.re.sin() * self.im.sinh())
+    }
+
+    pub fn tan(&self) -> Complex {
+        self.sin() / self.cos()
+    }
+
+    pub fn sinh(&self) -> Complex {
+        Complex::new(self.re.sinh() * self.im.cos(), self.re.cosh() * self.im.sin())
+    }
+
+    pub fn cosh(&self) -> Complex {
+        Complex::new(self.re.cosh() * self.im.cos(), self.re.sinh() * self.im.sin())
+    }
+
+    pub fn tanh(&self) -> Complex {
+        self.sinh() / self.cosh()
+    }
+
+    // asin(z) = -i ln(iz + sqrt(1 - z^2))
+    pub fn asin(&self) -> Complex {
+        let root = (Complex::one() - *self * *self).sqrt();
+        -Complex::i() * (Complex::i() * *self + root).ln()
+    }
+
+    // acos(z) = pi/2 - asin(z)
+    pub fn acos(&self) -> Complex {
+        Complex::from_real(::std::f64::consts::FRAC_PI_2) - self.asin()
+    }
+
+    // atan(z) = i/2 (ln(1 - iz) - ln(1 + iz))
+    pub fn atan(&self) -> Complex {
+        let iz = Complex::i() * *self;
+        Complex::new(0., 0.5) * ((Complex::one() - iz).ln() - (Complex::one() + iz).ln())
+    }
+
+    // Principal value of self^pow, defined as exp(pow * ln(self)). 0^pow is
+    // 0 for Re pow > 0, infinite for Re pow < 0 and NaN otherwise, except
+    // 0^0 = 1.
+    pub fn powc(&self, pow: Complex) -> Complex {
+        if *self == Complex::zero() {
+            if pow == Complex::zero() {
+                return Complex::one()
+            } else if pow.re > 0. {
+                return Complex::zero()
+            } else if pow.re < 0. {
+                return Complex::from_real(f64::INFINITY)
+            }
+            return Complex::new(f64::NAN, f64::NAN)
+        }
+        (pow * self.ln()).exp()
+    }
+}
+
+impl fmt::Display for Complex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.im == 0. {
+            write!(f, "{}", self.re)
+        } else if self.re == 0. {
+            write!(f, "{}i", self.im)
+        } else if self.im < 0. {
+            write!(f, "{} - {}i", self.re, -self.im)
+        } else {
+            write!(f, "{} + {}i", self.re, self.im)
+        }
+    }
+}
+
+// Complex numbers are only ordered along the real axis
+impl PartialOrd for Complex {
+    fn partial_cmp(&self, other: &Complex) -> Option<Ordering> {
+        if self.is_real() && other.is_real() {
+            self.re.partial_cmp(&other.re)
+        } else if self == other {
+            Some(Ordering::Equal)
+        } else {
+            None
+        }
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(self.re * other.re - self.im * other.im,
+                     self.re * other.im + self.im * other.re)
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+
+    // Smith's algorithm, which avoids overflow in the intermediate products
+    fn div(self, other: Complex) -> Complex {
+        if other.re.abs() >= other.im.abs() {
+            let ratio = other.im / other.re;
+            let denom = other.re + other.im * ratio;
+            Complex::new((self.re + self.im * ratio) / denom,
+                         (self.im - self.re * ratio) / denom)
+        } else {
+            let ratio = other.re / other.im;
+            let denom = other.re * ratio + other.im;
+            Complex::new((self.re * ratio + self.im) / denom,
+                         (self.im * ratio - self.re) / denom)
+        }
+    }
+}
+
+#[cfg(test)]
+fn assert_close(lhs: Complex, rhs: Complex) {
+    assert!((lhs - rhs).abs() < 1e-12, "{} != {}", lhs, rhs);
+}
+
+#[test]
+fn test_complex_arithmetic() {
+    let a = Complex::new(1., 2.);
+    let b = Complex::new(3., -1.);
+
+    assert_eq!(a + b, Complex::new(4., 1.));
+    assert_eq!(a - b, Complex::new(-2., 3.));
+    assert_eq!(a * b, Complex::new(5., 5.));
+    assert_close(a / b, Complex::new(0.1, 0.7));
+    assert_close((a / b) * b, a);
+    assert_eq!(Complex::i() * Complex::i(), Complex::from_real(-1.));
+    assert_eq!(-a, Complex::new(-1., -2.));
+}
+
+#[test]
+fn test_complex_powers_and_exp() {
+    let i = Complex::i();
+    let pi = Complex::from_real(::std::f64::consts::PI);
+
+    // Euler's identity
+    assert_close((i * pi).exp(), Complex::from_real(-1.));
+    assert_close(i.powi(4), Complex::one());
+    assert_close(i.powi(-1), -i);
+    assert_close(Complex::new(1., 1.).powi(2), Complex::new(0., 2.));
+    // i^i = exp(-pi/2)
+    assert_close(i.powc(i), Complex::from_real((-::std::f64::consts::FRAC_PI_2).exp()));
+    assert_close(Complex::from_real(-1.).ln(), i * pi);
+
+    // Powers of zero
+    let zero = Complex::zero();
+    assert_eq!(zero.powc(Complex::new(2., -1.)), zero);
+    assert_eq!(zero.powc(zero), Complex::one());
+    assert_eq!(zero.powc(Complex::from_real(-1.)), Complex::from_real(f64::INFINITY));
+    assert_eq!(zero.powc(Complex::new(-0.5, 3.)), Complex::from_real(f64::INFINITY));
+    assert!(zero.powc(i).re.is_nan());
+}
+
+#[test]
+fn test_complex_display() {
+    assert_eq!(format!("{}", Complex::new(1., 2.)), "1 + 2i");
+    assert_eq!(format!("{}", Complex::new(1., -2.)), "1 - 2i");
+    assert_eq!(format!("{}", Complex::new(0., 3.)), "3i");
+    assert_eq!(format!("{}", Complex::new(4., 0.)), "4");
+}
+
+#[test]
+fn test_complex_elementary_functions() {
+    let z = Complex::new(0.5, -1.5);
+
+    assert_close(Complex::from_real(-4.).sqrt(), Complex::new(0., 2.));
+    assert_close(z.sqrt() * z.sqrt(), z);
+    assert_close(z.sin() * z.sin() + z.cos() * z.cos(), Complex::one());
+    assert_close(z.cosh() * z.cosh() - z.sinh() * z.sinh(), Complex::one());
+    assert_close(z.tan(), z.sin() / z.cos());
+    assert_close(z.tanh(), z.sinh() / z.cosh());
+    assert_close(z.asin().sin(), z);
+    assert_close(z.acos().cos(), z);
+    assert_close(z.atan().tan(), z);
+    assert_close(Complex::from_real(0.5).asin(), Complex::from_real(0.5f64.asin()));
+    assert_close(Complex::i().sin(), Complex::new(0., 1f64.sinh()));
+}
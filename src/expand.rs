@@ -0,0 +1,229 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::iter;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use numeric::{Numeric, NumericError};
+use symexpr_rc;
+use symexpr_rc::{Expr, UnaryFunction, BinaryFunction, NaryFunction};
+
+// Largest number of terms expand multiplies out a power or product into
+const MAX_TERMS: usize = 1 << 12;
+
+// expr with products and quotients distributed over sums, powers of sums
+// with a natural exponent multiplied out and exp(a + b) = exp(a) exp(b),
+// bottom-up. collect undoes it for the powers of a variable. Overflow if
+// a power or product would have more than MAX_TERMS terms.
+pub fn expand(expr: &Rc<Expr>) -> Result<Rc<Expr>, NumericError> {
+    match expr.deref() {
+        Expr::Unary(unary_expr) => {
+            let argument = expand(unary_expr.argument())?;
+            match unary_expr.function() {
+                UnaryFunction::Neg => return Ok(symexpr_rc::sum(terms_of(&argument).iter().map(symexpr_rc::neg).collect())),
+                UnaryFunction::Exp => return Ok(symexpr_rc::product(terms_of(&argument).iter().map(symexpr_rc::exp).collect())),
+                _ if Rc::ptr_eq(&argument, unary_expr.argument()) => return Ok(Rc::clone(expr)),
+                function => return Ok(symexpr_rc::apply(function.clone(), &argument))
+            }
+        },
+        Expr::Binary(binary_expr) => {
+            let lhs = expand(binary_expr.lhs())?;
+            let rhs = expand(binary_expr.rhs())?;
+            match binary_expr.function() {
+                BinaryFunction::Pow => {
+                    let terms = terms_of(&lhs);
+                    match natural(&rhs) {
+                        Some(exponent) if terms.len() > 1 => {
+                            if multinomial_terms(terms.len(), exponent) > MAX_TERMS {
+                                return Err(NumericError::Overflow)
+                            }
+                            let mut expansion = Vec::new();
+                            multinomial(&terms, exponent, Numeric::one(), &mut Vec::new(), &mut expansion);
+                            return Ok(symexpr_rc::sum(expansion))
+                        },
+                        _ => {}
+                    }
+                },
+                BinaryFunction::Div => {
+                    return Ok(symexpr_rc::sum(terms_of(&lhs).iter().map(|term| symexpr_rc::div(term, &rhs)).collect()))
+                },
+                BinaryFunction::Mod => {}
+            }
+            if Rc::ptr_eq(&lhs, binary_expr.lhs()) && Rc::ptr_eq(&rhs, binary_expr.rhs()) {
+                return Ok(Rc::clone(expr))
+            }
+            return Ok(Expr::binary_from_heap(&lhs, &rhs, binary_expr.function().clone()).move_to_heap())
+        },
+        Expr::Nary(nary_expr) => {
+            let operands = nary_expr.operands().iter().map(expand).collect::<Result<Vec<Rc<Expr>>, NumericError>>()?;
+            match nary_expr.function() {
+                NaryFunction::Add => return Ok(symexpr_rc::sum(operands)),
+                NaryFunction::Mul => {
+                    // Every choice of one term from each factor
+                    let mut products: Vec<Vec<Rc<Expr>>> = vec![Vec::new()];
+                    for operand in operands {
+                        let terms = terms_of(&operand);
+                        if products.len() * terms.len() > MAX_TERMS {
+                            return Err(NumericError::Overflow)
+                        }
+                        products = products.iter()
+                            .flat_map(|factors| terms.iter().map(move |term| {
+                                let mut factors = factors.clone();
+                                factors.push(Rc::clone(term));
+                                factors
+                            }))
+                            .collect();
+                    }
+                    return Ok(symexpr_rc::sum(products.into_iter().map(symexpr_rc::product).collect()))
+                }
+            }
+        },
+        _ => return Ok(Rc::clone(expr))
+    }
+}
+
+// The terms of a sum grouped by their factors that depend on the variable
+// key, a x + b x + x^2 = (a + b) x + x^2. Expressions other than sums are
+// returned as they are.
+pub fn collect(expr: &Rc<Expr>, key: usize) -> Rc<Expr> {
+    let terms = match expr.deref() {
+        Expr::Nary(nary_expr) if *nary_expr.function() == NaryFunction::Add => nary_expr.operands(),
+        _ => return Rc::clone(expr)
+    };
+    let mut coefficients: HashMap<Rc<Expr>, Vec<Rc<Expr>>> = HashMap::new();
+    for term in terms {
+        let (dependent, independent): (Vec<Rc<Expr>>, Vec<Rc<Expr>>) = factors_of(term).into_iter()
+            .partition(|factor| factor.depends_on_variable(key));
+        coefficients.entry(symexpr_rc::product(dependent))
+            .or_default()
+            .push(symexpr_rc::product(independent));
+    }
+    return symexpr_rc::sum(coefficients.into_iter()
+        .map(|(power, coefficients)| symexpr_rc::mul(&symexpr_rc::sum(coefficients), &power))
+        .collect())
+}
+
+fn terms_of(expr: &Rc<Expr>) -> Vec<Rc<Expr>> {
+    match expr.deref() {
+        Expr::Nary(nary_expr) if *nary_expr.function() == NaryFunction::Add => return nary_expr.operands().clone(),
+        _ => return vec![Rc::clone(expr)]
+    }
+}
+
+// The factors of a product, a negation is a factor of -1
+fn factors_of(expr: &Rc<Expr>) -> Vec<Rc<Expr>> {
+    match expr.deref() {
+        Expr::Nary(nary_expr) if *nary_expr.function() == NaryFunction::Mul => return nary_expr.operands().clone(),
+        Expr::Unary(unary_expr) if *unary_expr.function() == UnaryFunction::Neg => {
+            let mut factors = factors_of(unary_expr.argument());
+            factors.push(Expr::from_integer(-1).move_to_heap());
+            return factors
+        },
+        _ => return vec![Rc::clone(expr)]
+    }
+}
+
+fn natural(expr: &Expr) -> Option<usize> {
+    match expr {
+        Expr::Numeric(Numeric::Integer(exponent)) if *exponent >= 0 => return Some(*exponent as usize),
+        _ => return None
+    }
+}
+
+// The number of terms of (t1 + ... + tk)^n, n + k - 1 choose k - 1, or
+// MAX_TERMS + 1 if there are more
+fn multinomial_terms(k: usize, n: usize) -> usize {
+    let mut count: usize = 1;
+    for i in 1..k {
+        count = match count.checked_mul(n + i) {
+            Some(product) => product / i,
+            None => return MAX_TERMS + 1
+        };
+        if count > MAX_TERMS {
+            return MAX_TERMS + 1
+        }
+    }
+    return count
+}
+
+// The terms of (t1 + ... + tk)^n by the multinomial theorem, the sum of
+// n! / (n1! ... nk!) t1^n1 ... tk^nk over n1 + ... + nk = n. The terms so
+// far have coefficient and factors.
+fn multinomial(terms: &[Rc<Expr>], n: usize, coefficient: Numeric, factors: &mut Vec<Rc<Expr>>, expansion: &mut Vec<Rc<Expr>>) {
+    let (first, rest) = match terms.split_first() {
+        Some(split) => split,
+        None => return
+    };
+    if rest.is_empty() {
+        let mut product = factors.clone();
+        product.extend(iter::repeat_n(Rc::clone(first), n));
+        product.push(Expr::from_numeric(coefficient).move_to_heap());
+        expansion.push(symexpr_rc::product(product));
+        return
+    }
+    // n choose k
+    let mut binomial = Numeric::one();
+    for k in 0..=n {
+        let length = factors.len();
+        factors.extend(iter::repeat_n(Rc::clone(first), k));
+        multinomial(rest, n - k, coefficient.clone() * binomial.clone(), factors, expansion);
+        factors.truncate(length);
+        binomial = binomial * Numeric::from_integer((n - k) as i64) / Numeric::from_integer(k as i64 + 1);
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_expand() {
+    use symexpr_rc::{add, sub, mul, div, pow, neg, exp, apply};
+
+    let x = Expr::from_key(0).clone_to_heap();
+    let y = Expr::from_key(1).clone_to_heap();
+    let z = Expr::from_key(2).clone_to_heap();
+    let two = Expr::from_integer(2).clone_to_heap();
+    let three = Expr::from_integer(3).clone_to_heap();
+    let print = |expr: &Rc<Expr>| expr.format_with(&|key| ["x", "y", "z"][key].to_string());
+
+    assert_eq!(print(&expand(&mul(&x, &add(&y, &z))).unwrap()), "x*y + x*z");
+    assert_eq!(print(&expand(&mul(&add(&x, &y), &sub(&x, &y))).unwrap()), "x^2 - y^2");
+    assert_eq!(print(&expand(&neg(&mul(&x, &add(&y, &two)))).unwrap()), "-x*y - 2*x");
+    // (x + y)^3 and (x + y + 1)^2 by the multinomial theorem
+    assert_eq!(print(&expand(&pow(&add(&x, &y), &three)).unwrap()), "x^3 + y^3 + 3*x*y^2 + 3*y*x^2");
+    let trinomial = add(&add(&x, &y), &Expr::from_integer(1).clone_to_heap());
+    assert_eq!(print(&expand(&pow(&trinomial, &two)).unwrap()), "1 + x^2 + y^2 + 2*x + 2*x*y + 2*y");
+    assert_eq!(print(&expand(&pow(&add(&mul(&two, &x), &y), &two)).unwrap()), "y^2 + 4*x*y + 4*x^2");
+    // Quotients, exp of sums and nested functions
+    assert_eq!(print(&expand(&div(&add(&x, &y), &z)).unwrap()), "x/z + y/z");
+    assert_eq!(print(&expand(&exp(&add(&x, &y))).unwrap()), "exp(x)*exp(y)");
+    assert_eq!(print(&expand(&apply(UnaryFunction::Sin, &mul(&x, &add(&y, &z)))).unwrap()), "sin(x*y + x*z)");
+    // Powers with other exponents are kept
+    let power = pow(&add(&x, &y), &z);
+    assert_eq!(expand(&power).unwrap(), power);
+    // Unless there are too many terms
+    let large = Expr::from_integer(1 << 20).clone_to_heap();
+    assert_eq!(expand(&pow(&add(&x, &y), &large)), Err(NumericError::Overflow));
+    assert_eq!(expand(&pow(&x, &large)).unwrap(), pow(&x, &large));
+}
+
+#[test]
+fn test_collect() {
+    use symexpr_rc::{add, sub, mul, pow, apply};
+
+    let x = Expr::from_key(0).clone_to_heap();
+    let y = Expr::from_key(1).clone_to_heap();
+    let z = Expr::from_key(2).clone_to_heap();
+    let two = Expr::from_integer(2).clone_to_heap();
+    let print = |expr: &Rc<Expr>| expr.format_with(&|key| ["x", "y", "z"][key].to_string());
+
+    // x y + x z - x^2 + 2 + z by the powers of x
+    let sum = add(&add(&mul(&x, &y), &mul(&x, &z)), &sub(&add(&two, &z), &pow(&x, &two)));
+    let collected = collect(&sum, 0);
+    assert_eq!(print(&collected), "2 + z + x*(y + z) - x^2");
+    assert_eq!(expand(&collected).unwrap(), sum);
+    // By y instead, and other factors depending on x
+    let sines = add(&mul(&y, &apply(UnaryFunction::Sin, &x)), &mul(&z, &apply(UnaryFunction::Sin, &x)));
+    assert_eq!(print(&collect(&sines, 0)), "sin(x)*(y + z)");
+    assert_eq!(collect(&sines, 1), sines);
+    assert_eq!(collect(&x, 0), x);
+}
@@ -0,0 +1,213 @@
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use numeric::Numeric;
+use symexpr_rc::{Expr, UnaryFunction, BinaryFunction, NaryFunction, Builder, Operation};
+
+// Index of an interned expression. Two ids of the same pool are equal
+// exactly when their expressions are structurally equal.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct ExprId(usize);
+
+// A node with its children replaced by their ids, so looking up a node
+// never compares whole subtrees
+#[derive(PartialEq, Eq, Hash)]
+enum NodeKey {
+    Numeric(Numeric),
+    IndepVar(usize),
+    Unary(UnaryFunction, ExprId),
+    Binary(BinaryFunction, ExprId, ExprId),
+    Nary(NaryFunction, Vec<ExprId>)
+}
+
+// Hash-consing arena of expressions. Every node is stored once and shared
+// by all expressions containing it, and the derivatives and cleanups of
+// each node are remembered, so repeated differentiation does not grow the
+// pool exponentially.
+pub struct ExprPool {
+    exprs: Vec<Rc<Expr>>,
+    ids: HashMap<NodeKey, ExprId>,
+    // Pooled nodes by address, the pool keeps them alive
+    addresses: HashMap<*const Expr, ExprId>,
+    results: HashMap<(Operation, ExprId), ExprId>
+}
+
+impl ExprPool {
+    pub fn new() -> ExprPool {
+        ExprPool {
+            exprs: Vec::new(),
+            ids: HashMap::new(),
+            addresses: HashMap::new(),
+            results: HashMap::new()
+        }
+    }
+
+    // Number of distinct nodes
+    pub fn len(&self) -> usize {
+        self.exprs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exprs.is_empty()
+    }
+
+    pub fn get(&self, id: ExprId) -> Rc<Expr> {
+        Rc::clone(&self.exprs[id.0])
+    }
+
+    // Id of expr and all its subexpressions, adding the missing ones
+    pub fn intern(&mut self, expr: &Rc<Expr>) -> ExprId {
+        if let Some(id) = self.addresses.get(&Rc::as_ptr(expr)) {
+            return *id
+        }
+        let key = match expr.deref() {
+            Expr::Numeric(numeric) => NodeKey::Numeric(numeric.clone()),
+            Expr::IndepVar(key) => NodeKey::IndepVar(*key),
+            Expr::Unary(unary_expr) => {
+                let argument = self.intern(unary_expr.argument());
+                NodeKey::Unary(unary_expr.function().clone(), argument)
+            },
+            Expr::Binary(binary_expr) => {
+                let lhs = self.intern(binary_expr.lhs());
+                let rhs = self.intern(binary_expr.rhs());
+                NodeKey::Binary(binary_expr.function().clone(), lhs, rhs)
+            },
+            Expr::Nary(nary_expr) => {
+                let operands = nary_expr.operands().iter().map(|operand| self.intern(operand)).collect();
+                NodeKey::Nary(nary_expr.function().clone(), operands)
+            }
+        };
+        if let Some(id) = self.ids.get(&key) {
+            return *id
+        }
+        // Rebuilt over the pooled children, expr may be a foreign tree
+        let node = match key {
+            NodeKey::Numeric(ref numeric) => Expr::from_numeric(numeric.clone()),
+            NodeKey::IndepVar(key) => Expr::from_key(key),
+            NodeKey::Unary(ref function, argument) => {
+                Expr::unary_from_heap(&self.exprs[argument.0], function.clone())
+            },
+            NodeKey::Binary(ref function, lhs, rhs) => {
+                Expr::binary_from_heap(&self.exprs[lhs.0], &self.exprs[rhs.0], function.clone())
+            },
+            NodeKey::Nary(ref function, ref operands) => {
+                let operands: Vec<Rc<Expr>> = operands.iter().map(|operand| self.get(*operand)).collect();
+                Expr::nary_from_heap(&operands, function.clone())
+            }
+        }.move_to_heap();
+        let id = ExprId(self.exprs.len());
+        self.addresses.insert(Rc::as_ptr(&node), id);
+        self.exprs.push(node);
+        self.ids.insert(key, id);
+        return id
+    }
+
+    // Derivative with respect to the variable key, cleaned up
+    pub fn derivative(&mut self, id: ExprId, key: usize) -> ExprId {
+        let expr = self.get(id);
+        let result = Expr::derivative_with(&expr, key, self);
+        return self.intern(&result)
+    }
+
+    pub fn cleaned(&mut self, id: ExprId) -> ExprId {
+        let expr = self.get(id);
+        let result = Expr::cleaned_with(&expr, self);
+        return self.intern(&result)
+    }
+}
+
+impl Default for ExprPool {
+    fn default() -> ExprPool {
+        ExprPool::new()
+    }
+}
+
+impl Builder for ExprPool {
+    fn build(&mut self, expr: Expr) -> Rc<Expr> {
+        let id = self.intern(&expr.move_to_heap());
+        return self.get(id)
+    }
+
+    fn recall(&self, operation: Operation, expr: &Rc<Expr>) -> Option<Rc<Expr>> {
+        let id = self.addresses.get(&Rc::as_ptr(expr))?;
+        let result = self.results.get(&(operation, *id))?;
+        Some(self.get(*result))
+    }
+
+    fn remember(&mut self, operation: Operation, expr: &Rc<Expr>, result: &Rc<Expr>) {
+        let id = self.intern(expr);
+        let result = self.intern(result);
+        self.results.insert((operation, id), result);
+    }
+}
+
+#[cfg(test)]
+fn tree_size(expr: &Expr) -> usize {
+    match expr {
+        Expr::Unary(unary_expr) => 1 + tree_size(unary_expr.argument()),
+        Expr::Binary(binary_expr) => 1 + tree_size(binary_expr.lhs()) + tree_size(binary_expr.rhs()),
+        Expr::Nary(nary_expr) => 1 + nary_expr.operands().iter().map(|operand| tree_size(operand)).sum::<usize>(),
+        _ => 1
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_interning() {
+    use symexpr_rc::{add, mul, apply};
+
+    let mut pool = ExprPool::new();
+    let x = Expr::from_key(0).move_to_heap();
+    let two = Expr::from_integer(2).move_to_heap();
+    let first = add(&mul(&two, &x), &apply(UnaryFunction::Sin, &x));
+    let second = add(&mul(&Expr::from_integer(2).move_to_heap(), &x),
+                     &apply(UnaryFunction::Sin, &Expr::from_key(0).move_to_heap()));
+    let first_id = pool.intern(&first);
+    assert_eq!(pool.intern(&second), first_id);
+    assert_eq!(pool.len(), 5);
+    assert_eq!(pool.get(first_id), first);
+
+    // Shared subexpressions are stored once
+    let square = mul(&first, &first);
+    pool.intern(&square);
+    assert_eq!(pool.len(), 6);
+    let other = pool.intern(&mul(&two, &Expr::from_key(1).move_to_heap()));
+    assert!(other != first_id);
+}
+
+#[cfg(test)]
+#[test]
+fn test_memoized_derivatives() {
+    use symexpr_rc::{mul, exp, apply};
+
+    let mut pool = ExprPool::new();
+    let x = Expr::from_key(0).move_to_heap();
+    let id = pool.intern(&mul(&x, &exp(&apply(UnaryFunction::Sin, &x))));
+    let mut derivative = id;
+    let mut total_size = 0;
+    for _ in 0..8 {
+        derivative = pool.derivative(derivative, 0);
+        total_size += tree_size(&pool.get(derivative));
+    }
+    // All eight derivatives share their nodes
+    let size = pool.len();
+    assert!(size < total_size);
+
+    // Asking again reuses the remembered results
+    let mut again = id;
+    for _ in 0..8 {
+        again = pool.derivative(again, 0);
+    }
+    assert_eq!(again, derivative);
+    assert_eq!(pool.len(), size);
+
+    // Same result as differentiating the tree without a pool
+    let mut expected = pool.get(id);
+    for _ in 0..8 {
+        expected = Expr::derivative(&expected, 0);
+    }
+    assert_eq!(pool.get(derivative), expected);
+}
@@ -0,0 +1,475 @@
+#![allow(dead_code)]
+
+use std::f64::consts::{PI, FRAC_PI_2};
+use std::fmt;
+use std::ops::{Add, Sub, Mul, Neg};
+
+use numeric::{Numeric, NumericError, RealType};
+
+// Upper bounds of pi / 2 and pi, the float constants round down
+const FRAC_PI_2_UP: RealType = 1.5707963267948968;
+const PI_UP: RealType = 3.1415926535897936;
+
+// Closed interval [lo, hi] of reals. Every operation rounds its lower bound
+// down and its upper bound up, so the result always encloses the exact
+// result for every point of the operands. Unbounded ends are infinite.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Interval {
+    lo: RealType,
+    hi: RealType
+}
+
+fn down(value: RealType) -> RealType {
+    if value.is_finite() { value.next_down() } else { value }
+}
+
+fn up(value: RealType) -> RealType {
+    if value.is_finite() { value.next_up() } else { value }
+}
+
+// Moves a rounded result one step down or up only if the exact result
+// lies below or above it, given the sign of the rounding error
+fn down_if(value: RealType, error: RealType) -> RealType {
+    if error < 0. { down(value) } else { value }
+}
+
+fn up_if(value: RealType, error: RealType) -> RealType {
+    if error > 0. { up(value) } else { value }
+}
+
+// Exact error of a rounded sum (TwoSum)
+fn sum_error(lhs: RealType, rhs: RealType, sum: RealType) -> RealType {
+    let rhs_part = sum - lhs;
+    (lhs - (sum - rhs_part)) + (rhs - rhs_part)
+}
+
+// Exact error of a rounded product, using a fused multiply-add
+fn product_error(lhs: RealType, rhs: RealType, product: RealType) -> RealType {
+    lhs.mul_add(rhs, -product)
+}
+
+// Sign of the error of a rounded quotient, from the exact remainder
+fn quotient_error(lhs: RealType, rhs: RealType, quotient: RealType) -> RealType {
+    (-quotient).mul_add(rhs, lhs) * rhs.signum()
+}
+
+fn add_down(lhs: RealType, rhs: RealType) -> RealType {
+    let sum = lhs + rhs;
+    if !sum.is_finite() { return sum }
+    down_if(sum, sum_error(lhs, rhs, sum))
+}
+
+fn add_up(lhs: RealType, rhs: RealType) -> RealType {
+    let sum = lhs + rhs;
+    if !sum.is_finite() { return sum }
+    up_if(sum, sum_error(lhs, rhs, sum))
+}
+
+// Product of two bounds where zero times infinity is zero, since a zero
+// bound is attained exactly
+fn mul_bounds(lhs: RealType, rhs: RealType) -> (RealType, RealType) {
+    if lhs == 0. || rhs == 0. {
+        return (0., 0.)
+    }
+    let product = lhs * rhs;
+    if !product.is_finite() {
+        return (product, product)
+    }
+    let error = product_error(lhs, rhs, product);
+    (down_if(product, error), up_if(product, error))
+}
+
+fn div_bounds(lhs: RealType, rhs: RealType) -> (RealType, RealType) {
+    let quotient = lhs / rhs;
+    if !quotient.is_finite() || rhs.is_infinite() {
+        return (down(quotient), up(quotient))
+    }
+    let error = quotient_error(lhs, rhs, quotient);
+    (down_if(quotient, error), up_if(quotient, error))
+}
+
+// Results of the standard library functions are within one ulp of the
+// exact value, so they are widened by two steps
+fn library_down(value: RealType) -> RealType {
+    down(down(value))
+}
+
+fn library_up(value: RealType) -> RealType {
+    up(up(value))
+}
+
+// True if offset + k * period lies in [lo, hi] for some integer k. Errs on
+// the side of true near the ends, which only widens the enclosure.
+fn contains_multiple(lo: RealType, hi: RealType, period: RealType, offset: RealType) -> bool {
+    let first = (lo - offset) / period;
+    let last = (hi - offset) / period;
+    let slack = 1e-12 * first.abs().max(last.abs()).max(1.);
+    (first - slack).ceil() <= (last + slack).floor()
+}
+
+impl Interval {
+    // Panics if lo > hi or either bound is NaN
+    pub fn new(lo: RealType, hi: RealType) -> Interval {
+        assert!(lo <= hi, "invalid interval [{}, {}]", lo, hi);
+        Interval { lo, hi }
+    }
+
+    pub fn point(value: RealType) -> Interval {
+        Interval::new(value, value)
+    }
+
+    pub fn entire() -> Interval {
+        Interval { lo: RealType::NEG_INFINITY, hi: RealType::INFINITY }
+    }
+
+    // Smallest interval of floats enclosing a real numeric value
+    pub fn from_numeric(value: &Numeric) -> Result<Interval, NumericError> {
+        if value.is_complex() {
+            return Err(NumericError::Domain("interval of a complex number"))
+        }
+        if value.is_nan() {
+            return Err(NumericError::Domain("interval of a value that is not a number"))
+        }
+        let real = value.to_real();
+        // Conversion is exact for integers that fit in the mantissa
+        match value {
+            Numeric::Integer(integer) if integer.unsigned_abs() <= 1 << 53 => Ok(Interval::point(real)),
+            Numeric::Real(_) => Ok(Interval::point(real)),
+            _ => Ok(Interval::new(down(real), up(real)))
+        }
+    }
+
+    // Hull of candidate (lower, upper) bounds
+    fn from_bounds(bounds: &[(RealType, RealType)]) -> Interval {
+        let lo = bounds.iter().map(|bound| bound.0).fold(RealType::INFINITY, RealType::min);
+        let hi = bounds.iter().map(|bound| bound.1).fold(RealType::NEG_INFINITY, RealType::max);
+        Interval::new(lo, hi)
+    }
+
+    pub fn lo(&self) -> RealType {
+        self.lo
+    }
+
+    pub fn hi(&self) -> RealType {
+        self.hi
+    }
+
+    pub fn width(&self) -> RealType {
+        up(self.hi - self.lo)
+    }
+
+    pub fn midpoint(&self) -> RealType {
+        if self.lo == -self.hi {
+            return 0.
+        }
+        self.lo / 2. + self.hi / 2.
+    }
+
+    pub fn contains(&self, value: RealType) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+
+    pub fn contains_zero(&self) -> bool {
+        self.contains(0.)
+    }
+
+    // Smallest interval containing both intervals
+    pub fn hull(&self, other: &Interval) -> Interval {
+        Interval::new(self.lo.min(other.lo), self.hi.max(other.hi))
+    }
+
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        let lo = self.lo.max(other.lo);
+        let hi = self.hi.min(other.hi);
+        if lo <= hi { Some(Interval::new(lo, hi)) } else { None }
+    }
+
+    // Dividing by an interval that contains zero, but is not zero itself,
+    // gives the entire real line
+    pub fn checked_div(self, other: Interval) -> Result<Interval, NumericError> {
+        if other.lo == 0. && other.hi == 0. {
+            return Err(NumericError::DivisionByZero)
+        }
+        if other.contains_zero() {
+            return Ok(Interval::entire())
+        }
+        let quotients = [div_bounds(self.lo, other.lo), div_bounds(self.lo, other.hi),
+                         div_bounds(self.hi, other.lo), div_bounds(self.hi, other.hi)];
+        Ok(Interval::from_bounds(&quotients))
+    }
+
+    // Floored remainder. For a point divisor and a dividend inside a single
+    // period the result is a translate of the dividend, otherwise it is
+    // bounded by the divisor.
+    pub fn checked_rem(self, other: Interval) -> Result<Interval, NumericError> {
+        if other.lo == 0. && other.hi == 0. {
+            return Err(NumericError::DivisionByZero)
+        }
+        if other.contains_zero() {
+            return Ok(Interval::new(other.lo, other.hi))
+        }
+        if other.lo == other.hi && self.lo.is_finite() && self.hi.is_finite() {
+            let period = (self.lo / other.lo).floor();
+            if period == (self.hi / other.lo).floor() && (period * other.lo).abs() < 2f64.powi(53) {
+                let shift = Interval::point(period) * other;
+                let result = self - shift;
+                if let Some(result) = result.intersection(&Interval::new(other.lo.min(0.), other.lo.max(0.))) {
+                    return Ok(result)
+                }
+            }
+        }
+        Ok(Interval::new(other.lo.min(0.), other.hi.max(0.)))
+    }
+
+    pub fn abs(&self) -> Interval {
+        if self.lo >= 0. {
+            *self
+        } else if self.hi <= 0. {
+            -*self
+        } else {
+            Interval::new(0., (-self.lo).max(self.hi))
+        }
+    }
+
+    pub fn checked_pow(&self, pow: i64) -> Result<Interval, NumericError> {
+        if pow < 0 {
+            return Interval::point(1.).checked_div(self.checked_pow(-pow)?)
+        }
+        if pow == 0 {
+            return Ok(Interval::point(1.))
+        }
+        let exponent = pow.min(i32::MAX as i64) as i32;
+        // Even powers are decreasing on the negative half line
+        let base = if pow % 2 == 0 { self.abs() } else { *self };
+        let power = |value: RealType, round: fn(RealType) -> RealType| {
+            if value == 0. { 0. } else { round(value.powi(exponent)) }
+        };
+        Ok(Interval::new(power(base.lo, library_down), power(base.hi, library_up)))
+    }
+
+    // Integer point exponents use checked_pow, any other exponent
+    // exp(y ln x) over the positive part of the base
+    pub fn checked_pow_interval(&self, exponent: Interval) -> Result<Interval, NumericError> {
+        if exponent.lo == exponent.hi && exponent.lo.fract() == 0. && exponent.lo.abs() < 2f64.powi(31) {
+            return self.checked_pow(exponent.lo as i64)
+        }
+        Ok((self.ln()? * exponent).exp())
+    }
+
+    pub fn sqrt(&self) -> Result<Interval, NumericError> {
+        if self.hi < 0. {
+            return Err(NumericError::Domain("square root of a negative interval"))
+        }
+        let root = |value: RealType| {
+            let root = value.sqrt();
+            (root, if root.is_finite() { (-root).mul_add(root, value) } else { 0. })
+        };
+        let (lo, lo_error) = root(self.lo.max(0.));
+        let (hi, hi_error) = root(self.hi);
+        Ok(Interval::new(down_if(lo, lo_error), up_if(hi, hi_error)))
+    }
+
+    pub fn exp(&self) -> Interval {
+        Interval::new(library_down(self.lo.exp()).max(0.), library_up(self.hi.exp()))
+    }
+
+    pub fn ln(&self) -> Result<Interval, NumericError> {
+        if self.hi <= 0. {
+            return Err(NumericError::Domain("logarithm of a non-positive interval"))
+        }
+        let lo = if self.lo <= 0. { RealType::NEG_INFINITY } else { library_down(self.lo.ln()) };
+        Ok(Interval::new(lo, library_up(self.hi.ln())))
+    }
+
+    // Bounds of a periodic function with maxima at offset + 2k pi and
+    // minima at offset + (2k + 1) pi
+    fn periodic(&self, function: fn(RealType) -> RealType, offset: RealType) -> Interval {
+        if self.width() >= 2. * PI {
+            return Interval::new(-1., 1.)
+        }
+        let at_lo = function(self.lo);
+        let at_hi = function(self.hi);
+        let hi = if contains_multiple(self.lo, self.hi, 2. * PI, offset) {
+            1.
+        } else {
+            library_up(at_lo.max(at_hi)).min(1.)
+        };
+        let lo = if contains_multiple(self.lo, self.hi, 2. * PI, offset + PI) {
+            -1.
+        } else {
+            library_down(at_lo.min(at_hi)).max(-1.)
+        };
+        Interval::new(lo, hi)
+    }
+
+    // Interval functions that are monotonic on their domain
+    fn increasing(&self, function: fn(RealType) -> RealType) -> Interval {
+        Interval::new(library_down(function(self.lo)), library_up(function(self.hi)))
+    }
+
+    fn clamp(&self, lo: RealType, hi: RealType) -> Interval {
+        Interval::new(self.lo.max(lo), self.hi.min(hi))
+    }
+
+    pub fn tan(&self) -> Result<Interval, NumericError> {
+        self.sin().checked_div(self.cos())
+    }
+
+    pub fn asin(&self) -> Result<Interval, NumericError> {
+        match self.intersection(&Interval::new(-1., 1.)) {
+            Some(domain) => Ok(domain.increasing(RealType::asin).clamp(-FRAC_PI_2_UP, FRAC_PI_2_UP)),
+            None => Err(NumericError::Domain("asin of an interval outside [-1, 1]"))
+        }
+    }
+
+    pub fn acos(&self) -> Result<Interval, NumericError> {
+        match self.intersection(&Interval::new(-1., 1.)) {
+            // Decreasing on its domain
+            Some(domain) => {
+                let result = Interval::new(library_down(domain.hi.acos()), library_up(domain.lo.acos()));
+                Ok(result.clamp(0., PI_UP))
+            },
+            None => Err(NumericError::Domain("acos of an interval outside [-1, 1]"))
+        }
+    }
+
+    pub fn atan(&self) -> Interval {
+        self.increasing(RealType::atan).clamp(-FRAC_PI_2_UP, FRAC_PI_2_UP)
+    }
+
+    pub fn sinh(&self) -> Interval {
+        self.increasing(RealType::sinh)
+    }
+
+    // Even, with its minimum 1 at zero
+    pub fn cosh(&self) -> Interval {
+        let magnitude = self.abs();
+        Interval::new(library_down(magnitude.lo.cosh()).max(1.), library_up(magnitude.hi.cosh()))
+    }
+
+    pub fn tanh(&self) -> Interval {
+        self.increasing(RealType::tanh).clamp(-1., 1.)
+    }
+
+    pub fn sin(&self) -> Interval {
+        self.periodic(RealType::sin, FRAC_PI_2)
+    }
+
+    pub fn cos(&self) -> Interval {
+        self.periodic(RealType::cos, 0.)
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}, {}]", self.lo, self.hi)
+    }
+}
+
+impl Neg for Interval {
+    type Output = Interval;
+
+    fn neg(self) -> Interval {
+        Interval::new(-self.hi, -self.lo)
+    }
+}
+
+impl Add for Interval {
+    type Output = Interval;
+
+    fn add(self, other: Interval) -> Interval {
+        Interval::new(add_down(self.lo, other.lo), add_up(self.hi, other.hi))
+    }
+}
+
+impl Sub for Interval {
+    type Output = Interval;
+
+    fn sub(self, other: Interval) -> Interval {
+        self + (-other)
+    }
+}
+
+impl Mul for Interval {
+    type Output = Interval;
+
+    fn mul(self, other: Interval) -> Interval {
+        let products = [mul_bounds(self.lo, other.lo), mul_bounds(self.lo, other.hi),
+                        mul_bounds(self.hi, other.lo), mul_bounds(self.hi, other.hi)];
+        Interval::from_bounds(&products)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_interval_arithmetic_encloses_result() {
+    let a = Interval::new(1., 2.);
+    let b = Interval::new(-3., 0.5);
+    let tenth = Interval::from_numeric(&Numeric::from_rational(::rational::Rational::new(1, 10))).unwrap();
+
+    assert!((a + b).contains(-2.) && (a + b).contains(2.5));
+    assert!((a - b).contains(0.5) && (a - b).contains(5.));
+    assert!((a * b).contains(-6.) && (a * b).contains(1.));
+    assert!(!(a * b).contains(1.01));
+    assert!(tenth.contains(0.1) && tenth.lo() < tenth.hi());
+    assert!((tenth + tenth + tenth).contains(0.30000000000000004) && (tenth + tenth + tenth).contains(0.3));
+    assert_eq!(-a, Interval::new(-2., -1.));
+    // Exact results are not widened
+    assert_eq!(a + Interval::new(3., 4.), Interval::new(4., 6.));
+    assert_eq!(a * b, Interval::new(-6., 1.));
+    assert_eq!(Interval::point(0.) * Interval::entire(), Interval::point(0.));
+    assert_eq!(Interval::from_numeric(&Numeric::from_integer(3)), Ok(Interval::point(3.)));
+    assert!(Interval::from_numeric(&Numeric::from_complex(::complex::Complex::i())).is_err());
+}
+
+#[test]
+fn test_interval_division() {
+    let a = Interval::new(1., 2.);
+
+    assert!(a.checked_div(Interval::new(4., 8.)).unwrap().contains(0.125));
+    assert!(a.checked_div(Interval::new(4., 8.)).unwrap().contains(0.5));
+    assert_eq!(a.checked_div(Interval::new(-1., 1.)), Ok(Interval::entire()));
+    assert_eq!(a.checked_div(Interval::point(0.)), Err(NumericError::DivisionByZero));
+    assert!(Interval::point(1.).checked_div(Interval::point(3.)).unwrap().contains(1. / 3.));
+    assert_eq!(Interval::new(7., 8.).checked_rem(Interval::point(3.)), Ok(Interval::new(1., 2.)));
+    assert_eq!(Interval::new(-1., 1.).checked_rem(Interval::point(3.)), Ok(Interval::new(0., 3.)));
+    assert_eq!(Interval::new(-1., 1.).checked_rem(Interval::new(-2., -1.)), Ok(Interval::new(-2., 0.)));
+    assert_eq!(a.checked_rem(Interval::point(0.)), Err(NumericError::DivisionByZero));
+}
+
+#[test]
+fn test_interval_functions() {
+    let a = Interval::new(-2., 1.);
+
+    assert_eq!(a.abs(), Interval::new(0., 2.));
+    assert!(a.checked_pow(2).unwrap().contains(0.) && a.checked_pow(2).unwrap().contains(4.));
+    assert!(a.checked_pow(2).unwrap().lo() == 0.);
+    assert!(a.checked_pow(3).unwrap().contains(-8.));
+    assert_eq!(a.checked_pow(-1), Ok(Interval::entire()));
+    assert_eq!(a.checked_pow_interval(Interval::point(2.)), a.checked_pow(2));
+    let root = Interval::new(4., 9.).checked_pow_interval(Interval::point(0.5)).unwrap();
+    assert!(root.contains(2.) && root.contains(3.) && root.width() < 1.1);
+    assert!(a.checked_pow_interval(Interval::new(0.5, 1.)).is_ok());
+    assert!(Interval::new(-2., -1.).checked_pow_interval(Interval::point(0.5)).is_err());
+    assert!(a.exp().contains((-2f64).exp()) && a.exp().contains(1f64.exp()));
+    assert!(Interval::new(1., 4.).sqrt().unwrap().contains(2.));
+    assert!(Interval::new(-1., 4.).sqrt().unwrap().contains(0.));
+    assert!(Interval::new(-2., -1.).sqrt().is_err());
+    assert_eq!(Interval::new(-1., 1.).ln().unwrap().lo(), RealType::NEG_INFINITY);
+    assert!(Interval::point(0.).ln().is_err());
+
+    // Extrema inside the interval are attained
+    assert_eq!(Interval::new(0., 2.).sin().hi(), 1.);
+    assert_eq!(Interval::new(3., 4.).cos().lo(), -1.);
+    assert_eq!(Interval::new(0., 10.).cos(), Interval::new(-1., 1.));
+    let tan = Interval::new(0., 1.).tan().unwrap();
+    assert!(tan.contains(0.) && tan.contains(1f64.tan()));
+    assert_eq!(Interval::new(1., 2.).tan(), Ok(Interval::entire()));
+    assert!(Interval::new(-2., 0.5).asin().unwrap().contains(-FRAC_PI_2));
+    assert!(Interval::new(-2., -1.5).asin().is_err());
+    assert!(Interval::new(0., 1.).acos().unwrap().contains(FRAC_PI_2) && Interval::new(0., 1.).acos().unwrap().contains(0.));
+    assert!(Interval::new(-1., 2.).cosh().contains(1.) && Interval::new(-1., 2.).cosh().contains(2f64.cosh()));
+    assert!(Interval::entire().tanh() == Interval::new(-1., 1.));
+    let sin = Interval::new(0.1, 0.2).sin();
+    assert!(sin.contains(0.1f64.sin()) && sin.contains(0.2f64.sin()) && sin.hi() < 0.2);
+}
@@ -1,12 +1,67 @@
-use numeric::{Numeric, IntegerType};
-use std::io;
+#![allow(dead_code)]
+
+use bigfloat;
+use bigfloat::BigFloat;
+use bigint::BigInt;
+use complex::Complex;
+use numeric::{Numeric, NumericError, RealType};
+use symexpr_rc::UnaryFunction;
 use std::char;
 use std::fmt;
 
-#[derive(Clone, PartialEq, PartialOrd)]
+#[derive(Clone, PartialEq)]
 pub enum Token {
     Term(TermToken),
-    Operator(Operator)
+    Operator(Operator),
+    // Name of a called function, its arguments follow in parentheses
+    Function(String)
+}
+
+// Byte offsets of a token in the input, end exclusive
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    // The smallest span covering both
+    pub fn to(&self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    // The input line with carets under the spanned characters, an empty
+    // span gets a single caret
+    pub fn underline(&self, input: &str) -> String {
+        let input = input.trim_end();
+        let start = self.start.min(input.len());
+        let end = self.end.min(input.len()).max(start);
+        let column = input[..start].chars().count();
+        let width = input[start..end].chars().count().max(1);
+        format!("{}\n{}{}", input, " ".repeat(column), "^".repeat(width))
+    }
+}
+
+// A character that starts no token, or the first character of a number
+// literal that is out of range, with numeric set to the reason
+#[derive(Clone, PartialEq, Debug)]
+pub struct LexError {
+    pub character: char,
+    pub span: Span,
+    pub numeric: Option<NumericError>
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.numeric {
+            Some(ref error) => write!(f, "{} in number literal", error),
+            None => write!(f, "unexpected character {:?}", self.character)
+        }
+    }
 }
 
 impl fmt::Debug for Token {
@@ -18,15 +73,8 @@ impl fmt::Debug for Token {
                     TermToken::VariableKey(key) => write!(f, "{:?}", *key)
                 }
             },
-            Token::Operator(op) => {
-                match op.op {
-                    OperatorType::Add => write!(f, "+"),
-                    OperatorType::Mul => write!(f, "*"),
-                    OperatorType::LeftP => write!(f, "("),
-                    OperatorType::RightP => write!(f, ")"),
-                    OperatorType::Assignment => write!(f, "=")
-                }
-            }
+            Token::Operator(op) => write!(f, "{}", op.op.symbol()),
+            Token::Function(name) => write!(f, "{}()", name)
         }
     }
 }
@@ -70,14 +118,28 @@ impl Token {
         }
     }
 
-    fn is_left_parenthesis(&self) -> bool {
+    pub fn is_left_parenthesis(&self) -> bool {
         match self {
             Token::Operator(op) => *op == LeftP,
             _ => return false
         }
     }
 
-    fn is_right_parenthesis(&self) -> bool {
+    pub fn is_function(&self) -> bool {
+        match self {
+            Token::Function(_name) => return true,
+            _ => return false
+        }
+    }
+
+    pub fn is_comma(&self) -> bool {
+        match self {
+            Token::Operator(op) => *op == Comma,
+            _ => return false
+        }
+    }
+
+    pub fn is_right_parenthesis(&self) -> bool {
         match self {
             Token::Operator(op) => *op == RightP,
             _ => return false
@@ -92,207 +154,245 @@ impl Token {
     }
 }
 
-#[derive(Clone, PartialEq, Debug, PartialOrd)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct Operator {
-    precedence: i64,
     pub op: OperatorType
 }
 
-const LeftP: Operator = Operator{precedence: 1, op: OperatorType::LeftP};
-const RightP: Operator = Operator{precedence: 1, op: OperatorType::RightP};
-const Add: Operator = Operator{precedence: 2, op: OperatorType::Add};
-const Mul: Operator = Operator{precedence: 4, op: OperatorType::Mul};
-const Assignment: Operator = Operator{precedence: 4, op: OperatorType::Assignment};
-
-#[derive(Clone, PartialEq, Debug, PartialOrd)]
+const LeftP: Operator = Operator{op: OperatorType::LeftP};
+const RightP: Operator = Operator{op: OperatorType::RightP};
+const Comma: Operator = Operator{op: OperatorType::Comma};
+const Add: Operator = Operator{op: OperatorType::Add};
+const Sub: Operator = Operator{op: OperatorType::Sub};
+const Mul: Operator = Operator{op: OperatorType::Mul};
+const Div: Operator = Operator{op: OperatorType::Div};
+const Mod: Operator = Operator{op: OperatorType::Mod};
+const Neg: Operator = Operator{op: OperatorType::Neg};
+const Pow: Operator = Operator{op: OperatorType::Pow};
+const Assignment: Operator = Operator{op: OperatorType::Assignment};
+
+#[derive(Clone, PartialEq, Debug)]
 pub enum TermToken {
     Number(Numeric),
     VariableKey(String)
 }
 
-#[derive(Clone, PartialEq, Debug, PartialOrd)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum OperatorType {
     LeftP,
     RightP,
+    Comma,
     Add,
+    Sub,
     Mul,
+    Div,
+    Mod,
+    Neg,
+    Pow,
     Assignment
 }
 
-pub type Tokens = Vec<Token>;
-
-fn tokenize_number(slice: &str) -> Token {
-    use self::Token::{Term, Operator};
-    use self::TermToken::{Number, VariableKey};
-
-    for ch in slice.chars() {
+impl OperatorType {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            OperatorType::Add => "+",
+            OperatorType::Sub => "-",
+            OperatorType::Mul => "*",
+            OperatorType::Div => "/",
+            OperatorType::Mod => "%",
+            OperatorType::Pow => "^",
+            OperatorType::Neg => "-",
+            OperatorType::LeftP => "(",
+            OperatorType::RightP => ")",
+            OperatorType::Comma => ",",
+            OperatorType::Assignment => "="
+        }
     }
-        return Operator(Add)
 }
 
-fn cocatenate_numeric_tokens(tokens: &[Token]) -> Token {
-    let mut result = Numeric::from_integer(0);
-    for (i, token) in tokens.iter().enumerate() {
-        match token {
-            Token::Term(term) => {
-                match term {
-                    TermToken::Number(numeric) => result = Numeric::from_integer(10) * result + *numeric,
-                    _ => break
-                }
-            }
-            _ => break
+pub type Tokens = Vec<Token>;
+pub type SpannedTokens = Vec<(Token, Span)>;
+
+// Length of the number literal at the start of chars: digits with an
+// optional fraction and an optional exponent, such as 12, 1.25, .5 or 6.02e23
+fn length_of_number_literal(chars: &[char]) -> usize {
+    let digits_from = |start: usize| {
+        chars[start..].iter().take_while(|ch| ch.is_ascii_digit()).count()
+    };
+    let mut length = digits_from(0);
+    if length < chars.len() && chars[length] == '.' {
+        length += 1 + digits_from(length + 1);
+    }
+    // An e that is not followed by an exponent starts an identifier
+    if length < chars.len() && (chars[length] == 'e' || chars[length] == 'E') {
+        let sign = if length + 1 < chars.len() && (chars[length + 1] == '+' || chars[length + 1] == '-') { 1 } else { 0 };
+        let exponent_digits = if length + 1 + sign <= chars.len() { digits_from(length + 1 + sign) } else { 0 };
+        if exponent_digits > 0 {
+            length += 1 + sign + exponent_digits;
         }
     }
-
-    return Token::Term(TermToken::Number(result));
+    length
 }
 
-fn get_length_of_numeric_sequence(tokens: &[Token]) -> usize {
-    for (i, tok) in tokens.iter().enumerate() {
-        if !tok.is_numeric() {
-            return i
+// Integer literals are exact, whatever their size. Literals with a
+// fraction or an exponent are reals, or big floats while a working
+// precision is set. A trailing i makes the literal imaginary.
+// Overflow for a float literal too large for a machine float, 1e400
+fn tokenize_number(slice: &str, imaginary: bool) -> Result<Token, NumericError> {
+    let value = if slice.contains(['.', 'e', 'E']) {
+        match (bigfloat::precision(), slice.parse::<BigFloat>()) {
+            (Some(_), Ok(float)) => Numeric::from_big_float(float),
+            _ => {
+                let real = slice.parse::<RealType>().unwrap_or(RealType::NAN);
+                if real.is_infinite() {
+                    return Err(NumericError::Overflow)
+                }
+                Numeric::from_real(real)
+            }
         }
+    } else {
+        Numeric::from_big_integer(slice.parse::<BigInt>().unwrap_or_else(|_| BigInt::zero()))
+    };
+    if imaginary {
+        return Ok(Token::Term(TermToken::Number(Numeric::from_complex(Complex::new(0., value.to_real())))))
     }
-    return tokens.len()
+    Ok(Token::Term(TermToken::Number(value)))
 }
 
-pub fn tokenize_string(string: &String) -> Tokens {
-    use self::Token::{Term, Operator};
-    use self::TermToken::{Number, VariableKey};
-
-    let mut tokens: Vec<Token> = Vec::new();
-    let mut result: Vec<Token> = Vec::new();
-    let mut temp_number = Numeric::from_integer(0);
-    for (i, ch) in string.chars().enumerate() {
-        match ch {
-            ' ' => continue,
-            '(' => tokens.push(Operator(LeftP)),
-            ')' => tokens.push(Operator(RightP)),
-            '+' => tokens.push(Operator(Add)),
-            '*' => tokens.push(Operator(Mul)),
-            '=' => tokens.push(Operator(Assignment)),
-            '0'...'9' => tokens.push(Term(TermToken::Number(Numeric::from_integer(ch.to_digit(10).unwrap() as IntegerType)))),
-            'A'...'z' => tokens.push(Term(TermToken::VariableKey(ch.to_string()))),
-            _ => continue
-        }
-    }
+// Identifiers start with a letter, Greek letters included, or an
+// underscore, and continue with letters, digits and underscores
+fn is_identifier_start(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_'
+}
 
-    let mut i = 0;
-    while i < tokens.len() {
-        if tokens[i].is_numeric() {
-            let index_of_next_non_numeric = i + get_length_of_numeric_sequence(&tokens[i..]);
-            result.push(cocatenate_numeric_tokens(&tokens[i..]));
-            i = index_of_next_non_numeric;
-            if i < tokens.len() && tokens[i].is_left_parenthesis() {
-                result.push(Operator(Mul));
-            }
-        } else if tokens[i].is_variable() {
-            result.push(tokens[i].clone());
-            if i < tokens.len() - 1 && tokens[i + 1].is_left_parenthesis() {
-                result.push(Operator(Mul));
-            }    
-            i += 1;
-        } else {
-            result.push(tokens[i].clone());
-            i += 1;
-        }
-    }
-    return result;
+fn is_identifier_continue(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_'
 }
 
-pub fn infix_to_postfix(tokens: &Tokens) -> Tokens {
-    let mut postfix: Vec<Token> = Vec::new();
-    let mut opstack: Vec<Token> = Vec::new();
-    // For each token
-    for (i, token) in tokens.iter().enumerate() {
-        // If a number or variable
-        if token.is_term() {
-            postfix.push(token.clone());
-        } 
-        // If operator or parenthesis
-        else if token.is_operator() {
-            // If left parenthesis
-            if token.is_left_parenthesis() {
-                opstack.push(token.clone());
-            } 
-            // If right parenthesis
-            else if token.is_right_parenthesis() {
-                // Pop top of opstack if not empty
-                while let Some(op_from_stack) = opstack.pop() {
-                    // Pop operators onto output until a left parenthesis
-                    if op_from_stack.is_left_parenthesis() {
-                        break;
-                    } else {
-                        postfix.push(op_from_stack.clone());
-                    }
-                }
-            } 
-            // Else
-            else {
-                // Pop top of opstack if not empty
-                while let Some(op_from_stack) = opstack.pop() {
-                    // If top operator has higher precedence than current token
-                    if op_from_stack.is_left_parenthesis() {
-                        opstack.push(op_from_stack);
-                        break;
-                    }
-                    
-                    if op_from_stack > *token {
-                        /// Push top operator to output
-                        postfix.push(op_from_stack.clone());
-                    } else {
-                        opstack.push(op_from_stack);
-                        break;
-                    }
-                }
-                // Push token to output
-                opstack.push(token.clone());
-            }
-        }
+// Keywords are operators, any other identifier names a variable
+fn identifier_token(identifier: String) -> Token {
+    match identifier.as_str() {
+        "mod" => Token::Operator(Mod),
+        _ => Token::Term(TermToken::VariableKey(identifier))
     }
+}
 
-    while let Some(remaining_op) = opstack.pop() {
-        postfix.push(remaining_op.clone());
+// A minus is a prefix minus where an operand is expected, at the start of
+// the input, after an opening parenthesis or after another operator
+fn starts_operand(previous: Option<&Token>) -> bool {
+    match previous {
+        Some(token) => token.is_operator() && !token.is_right_parenthesis(),
+        None => true
     }
-
-    return postfix;
 }
 
-pub fn string_to_postfix(string: &String) -> Tokens {
-    infix_to_postfix(&tokenize_string(string))
+// Tokenizes with only the built-in functions recognized as calls
+pub fn tokenize_string(string: &String) -> Result<Tokens, LexError> {
+    tokenize_string_with(string, &|name: &str| UnaryFunction::from_name(name).is_some())
 }
 
-#[cfg(test)]
-#[test]
-fn test_operator_precedence() {
-    use self::Token::{Term, Operator};
-    assert!(LeftP < Add);
-    assert!(Operator(LeftP) < Operator(Add));
-    assert!(Operator(Add) < Operator(Mul));
-    assert!(!Operator(LeftP).is_numeric());
+// An identifier directly followed by a parenthesis is a function call if
+// is_call accepts its name, and is multiplied with the parenthesis otherwise
+pub fn tokenize_string_with(string: &String, is_call: &dyn Fn(&str) -> bool) -> Result<Tokens, LexError> {
+    let tokens = tokenize_spanned_with(string, is_call)?;
+    Ok(tokens.into_iter().map(|(token, _span)| token).collect())
 }
 
-#[cfg(test)]
-#[test]
-fn test_concatenation() {
+// Tokenizes keeping the byte offsets of every token, an implicit
+// multiplication gets an empty span right after its left operand.
+// Whitespace and semicolons are skipped, any other stray character is an
+// error.
+pub fn tokenize_spanned_with(string: &String, is_call: &dyn Fn(&str) -> bool) -> Result<SpannedTokens, LexError> {
     use self::Token::{Term, Operator};
-    use self::TermToken::{Number, VariableKey};
-
-    let tokenized: Vec<Token> = vec![
-        Operator(Add),
-        Term(TermToken::Number(Numeric::from_integer(2))), 
-        Term(TermToken::Number(Numeric::from_integer(3))), 
-        Term(TermToken::Number(Numeric::from_integer(5))), 
-        Operator(Add), 
-        Term(VariableKey("x".to_string()))
-    ];
+    use self::TermToken::VariableKey;
+
+    let mut tokens: SpannedTokens = Vec::new();
+    let mut result: SpannedTokens = Vec::new();
+    let chars: Vec<char> = string.chars().collect();
+    // Byte offset of every char, and of the end of the input
+    let offsets: Vec<usize> = string.char_indices().map(|(offset, _ch)| offset)
+        .chain(Some(string.len())).collect();
+    let mut position = 0;
+    while position < chars.len() {
+        let ch = chars[position];
+        let start = position;
+        position += 1;
+        let span = Span::new(offsets[start], offsets[position]);
+        let token = match ch {
+            ';' => continue,
+            _ if ch.is_whitespace() => continue,
+            '(' => Operator(LeftP),
+            ')' => Operator(RightP),
+            ',' => Operator(Comma),
+            '+' => Operator(Add),
+            '-' => Operator(Sub),
+            '*' => Operator(Mul),
+            '/' => Operator(Div),
+            '^' => Operator(Pow),
+            '%' => Operator(Mod),
+            '=' => Operator(Assignment),
+            '0'..='9' | '.' => {
+                let length = length_of_number_literal(&chars[start..]);
+                if length == 1 && ch == '.' {
+                    return Err(LexError { character: ch, span, numeric: None })
+                }
+                position = start + length;
+                // 2i is imaginary, but 2in is the number 2 followed by in
+                let imaginary = position < chars.len() && chars[position] == 'i' &&
+                    (position + 1 == chars.len() || !is_identifier_continue(chars[position + 1]));
+                if imaginary {
+                    position += 1;
+                }
+                let literal: String = chars[start..start + length].iter().collect();
+                match tokenize_number(&literal, imaginary) {
+                    Ok(token) => token,
+                    Err(error) => {
+                        let span = Span::new(offsets[start], offsets[position]);
+                        return Err(LexError { character: ch, span, numeric: Some(error) })
+                    }
+                }
+            },
+            _ if is_identifier_start(ch) => {
+                while position < chars.len() && is_identifier_continue(chars[position]) {
+                    position += 1;
+                }
+                identifier_token(chars[start..position].iter().collect())
+            },
+            _ => return Err(LexError { character: ch, span, numeric: None })
+        };
+        tokens.push((token, Span::new(offsets[start], offsets[position])));
+    }
 
-    let index_of_next_non_numeric = 1 + get_length_of_numeric_sequence(&tokenized[1..]);
-    assert_eq!(index_of_next_non_numeric, 4);
-    let result = cocatenate_numeric_tokens(&tokenized[1..]);
-    assert_eq!(result, Term(TermToken::Number(Numeric::from_integer(235))));
+    for i in 0..tokens.len() {
+        let (ref token, span) = tokens[i];
+        let next = tokens.get(i + 1);
+        let followed_by_parenthesis = next.is_some_and(|next| next.0.is_left_parenthesis());
+        let implicit_product = Operator(Mul);
+        if token.is_numeric() {
+            result.push(tokens[i].clone());
+            // 2(x + 1) and 2x are products
+            if followed_by_parenthesis || next.is_some_and(|next| next.0.is_variable()) {
+                result.push((implicit_product, Span::new(span.end, span.end)));
+            }
+        } else if *token == Operator(Sub) && starts_operand(result.last().map(|last| &last.0)) {
+            result.push((Operator(Neg), span));
+        } else if let Term(VariableKey(ref name)) = *token {
+            if followed_by_parenthesis && is_call(name) {
+                result.push((Token::Function(name.clone()), span));
+            } else {
+                result.push(tokens[i].clone());
+                if followed_by_parenthesis {
+                    result.push((implicit_product, Span::new(span.end, span.end)));
+                }
+            }
+        } else {
+            result.push(tokens[i].clone());
+        }
+    }
+    return Ok(result);
 }
 
+#[cfg(test)]
 #[test]
 fn test_tokenizer() {
     use self::Token::{Term, Operator};
@@ -305,8 +405,8 @@ fn test_tokenizer() {
         Term(TermToken::Number(Numeric::from_integer(2)))
     ];
 
-    assert_eq!(tokenize_string(&expr1_variation1), expr1_tokenized);
-    assert_eq!(tokenize_string(&expr1_variation2), expr1_tokenized);
+    assert_eq!(tokenize_string(&expr1_variation1).unwrap(), expr1_tokenized);
+    assert_eq!(tokenize_string(&expr1_variation2).unwrap(), expr1_tokenized);
 
     // Check that integers and variables and operators are processed
     let expr2_variation1 = String::from("20 + x;");
@@ -317,8 +417,8 @@ fn test_tokenizer() {
         Term(VariableKey("x".to_string()))
     ];
 
-    assert_eq!(tokenize_string(&expr2_variation1), expr2_tokenized);
-    assert_eq!(tokenize_string(&expr2_variation2), expr2_tokenized);
+    assert_eq!(tokenize_string(&expr2_variation1).unwrap(), expr2_tokenized);
+    assert_eq!(tokenize_string(&expr2_variation2).unwrap(), expr2_tokenized);
 
     // Check that numbers followed by left parenthesis has multiplication added explicitly
     let expr3_variation1 = String::from("32 * (x + y);");
@@ -335,9 +435,9 @@ fn test_tokenizer() {
         Operator(RightP)
     ];
 
-    assert_eq!(tokenize_string(&expr3_variation1), expr3_tokenized);
-    assert_eq!(tokenize_string(&expr3_variation2), expr3_tokenized);
-    assert_eq!(tokenize_string(&expr3_variation3), expr3_tokenized);
+    assert_eq!(tokenize_string(&expr3_variation1).unwrap(), expr3_tokenized);
+    assert_eq!(tokenize_string(&expr3_variation2).unwrap(), expr3_tokenized);
+    assert_eq!(tokenize_string(&expr3_variation3).unwrap(), expr3_tokenized);
 
     // Check that variables followed by left parenthesis has multipication added explicitly
     let expr4_variation1 = String::from("x *( 1+x)");
@@ -352,8 +452,8 @@ fn test_tokenizer() {
         Operator(RightP)
     ];
 
-    assert_eq!(tokenize_string(&expr4_variation1), expr4_tokenized);
-    assert_eq!(tokenize_string(&expr4_variation2), expr4_tokenized);
+    assert_eq!(tokenize_string(&expr4_variation1).unwrap(), expr4_tokenized);
+    assert_eq!(tokenize_string(&expr4_variation2).unwrap(), expr4_tokenized);
 
     let expr4 = String::from("2 + 3(1 +x)");
     let expr4_tokenized: Vec<Token> = vec![
@@ -368,85 +468,79 @@ fn test_tokenizer() {
         Operator(RightP)
     ];
 
-    assert_eq!(tokenize_string(&expr4), expr4_tokenized);
+    assert_eq!(tokenize_string(&expr4).unwrap(), expr4_tokenized);
 }
 
 #[test]
-fn test_infix_to_postfix() {
+fn test_identifiers() {
     use self::Token::{Term, Operator};
-    use self::TermToken::{Number, VariableKey};
-
-    // 2
-    let expr1: Vec<Token> = vec![
-        Term(TermToken::Number(Numeric::from_integer(2)))
-    ];
-
-    let expr1_postfix: Vec<Token> = vec![
-        Term(TermToken::Number(Numeric::from_integer(2)))
-    ];
-    
-    assert_eq!(infix_to_postfix(&expr1), expr1_postfix);
-
-    // 20 + x
-    let expr2: Vec<Token> = vec![
-        Term(TermToken::Number(Numeric::from_integer(20))), 
-        Operator(Add), 
-        Term(VariableKey("x".to_string()))
-    ];
-
-    let expr2_postfix: Vec<Token> = vec![
-        Term(TermToken::Number(Numeric::from_integer(20))), 
-        Term(VariableKey("x".to_string())),
-        Operator(Add)
-    ];
-
-    assert_eq!(infix_to_postfix(&expr2), expr2_postfix);
-
-    // a((123) + x)
-
-    let expr3: Vec<Token> = vec![
-        Term(VariableKey("a".to_string())),
-        Operator(Mul),
-        Operator(LeftP),
-        Operator(LeftP),
-        Term(TermToken::Number(Numeric::from_integer(123))), 
-        Operator(RightP),
-        Operator(Add),
-        Term(VariableKey("x".to_string())),
-        Operator(RightP)
-    ];
+    use self::TermToken::VariableKey;
+
+    let variable = |name: &str| Term(VariableKey(name.to_string()));
+
+    assert_eq!(tokenize_string(&String::from("xy + x_1")).unwrap(), vec![variable("xy"), Operator(Add), variable("x_1")]);
+    assert_eq!(tokenize_string(&String::from("_tmp*θ")).unwrap(), vec![variable("_tmp"), Operator(Mul), variable("θ")]);
+    assert_eq!(tokenize_string(&String::from("αβ2")).unwrap(), vec![variable("αβ2")]);
+    assert_eq!(tokenize_string(&String::from("sin(x)")).unwrap(),
+               vec![Token::Function("sin".to_string()), Operator(LeftP), variable("x"), Operator(RightP)]);
+    assert_eq!(tokenize_string(&String::from("[x]")), Err(LexError { character: '[', span: Span::new(0, 1), numeric: None }));
+    assert_eq!(tokenize_string(&String::from("x . 2")), Err(LexError { character: '.', span: Span::new(2, 3), numeric: None }));
+    // Float literals beyond the range of a machine float
+    let overflow = tokenize_string(&String::from("2 * 1e400")).unwrap_err();
+    assert_eq!(overflow, LexError { character: '1', span: Span::new(4, 9), numeric: Some(NumericError::Overflow) });
+    assert_eq!(overflow.to_string(), "numeric overflow in number literal");
+    assert_eq!(tokenize_string(&String::from("a mod n")).unwrap(), vec![variable("a"), Operator(Mod), variable("n")]);
+    assert_eq!(tokenize_string(&String::from("modulus")).unwrap(), vec![variable("modulus")]);
+}
 
-    let expr3_postfix: Vec<Token> = vec![
-        Term(VariableKey("a".to_string())),
-        Term(TermToken::Number(Numeric::from_integer(123))), 
-        Term(VariableKey("x".to_string())),
-        Operator(Add),
-        Operator(Mul)
-    ];
+#[test]
+fn test_number_literals() {
+    use self::Token::{Term, Operator};
 
-    assert_eq!(infix_to_postfix(&expr3), expr3_postfix);
+    let number = |value: Numeric| Term(TermToken::Number(value));
+
+    assert_eq!(tokenize_string(&String::from("1.25")).unwrap(), vec![number(Numeric::from_real(1.25))]);
+    assert_eq!(tokenize_string(&String::from(".5 + 2.")).unwrap(),
+               vec![number(Numeric::from_real(0.5)), Operator(Add), number(Numeric::from_real(2.))]);
+    assert_eq!(tokenize_string(&String::from("6.02e23")).unwrap(), vec![number(Numeric::from_real(6.02e23))]);
+    assert_eq!(tokenize_string(&String::from("1E-3*2e+2")).unwrap(),
+               vec![number(Numeric::from_real(1e-3)), Operator(Mul), number(Numeric::from_real(2e2))]);
+    assert_eq!(tokenize_string(&String::from("2e")).unwrap(),
+               vec![number(Numeric::from_integer(2)), Operator(Mul), Term(TermToken::VariableKey("e".to_string()))]);
+    assert_eq!(tokenize_string(&String::from("123456789012345678901234567890")).unwrap(),
+               vec![number(Numeric::from_big_integer("123456789012345678901234567890".parse().unwrap()))]);
+    assert_eq!(tokenize_string(&String::from("2i + 0.5i")).unwrap(),
+               vec![number(Numeric::from_complex(Complex::new(0., 2.))), Operator(Add),
+                    number(Numeric::from_complex(Complex::new(0., 0.5)))]);
+    assert_eq!(tokenize_string(&String::from("2in")).unwrap(),
+               vec![number(Numeric::from_integer(2)), Operator(Mul), Term(TermToken::VariableKey("in".to_string()))]);
+
+    bigfloat::set_precision(Some(30));
+    assert_eq!(tokenize_string(&String::from("0.1")).unwrap(), vec![number(Numeric::from_big_float("0.1".parse().unwrap()))]);
+    bigfloat::set_precision(None);
+}
 
-    let expr4_tokenized: Vec<Token> = vec![
-        Term(TermToken::Number(Numeric::from_integer(2))), 
-        Operator(Add),
-        Term(TermToken::Number(Numeric::from_integer(3))), 
-        Operator(Mul),
-        Operator(LeftP),
-        Term(TermToken::Number(Numeric::from_integer(1))),
-        Operator(Add),
-        Term(VariableKey("x".to_string())),
-        Operator(RightP)
-    ];
+#[test]
+fn test_negation_and_function_tokens() {
+    use self::Token::{Term, Operator};
 
-    let expr4_postfix: Vec<Token> = vec![
-        Term(TermToken::Number(Numeric::from_integer(2))), 
-        Term(TermToken::Number(Numeric::from_integer(3))), 
-        Term(TermToken::Number(Numeric::from_integer(1))),
-        Term(VariableKey("x".to_string())),
-        Operator(Add),
-        Operator(Mul),
-        Operator(Add)
-    ];
+    assert_eq!(tokenize_string(&String::from("-(x) - 1")).unwrap()[0], Operator(Neg));
+    assert_eq!(tokenize_string(&String::from("-(x) - 1")).unwrap()[4], Operator(Sub));
+    assert_eq!(tokenize_string(&String::from("2^-x")).unwrap()[2], Operator(Neg));
+    assert_eq!(tokenize_string_with(&String::from("f(x) + y(x)"), &|name: &str| name == "f").unwrap(),
+               vec![Token::Function("f".to_string()), Operator(LeftP), Term(TermToken::VariableKey("x".to_string())), Operator(RightP),
+                    Operator(Add), Term(TermToken::VariableKey("y".to_string())), Operator(Mul),
+                    Operator(LeftP), Term(TermToken::VariableKey("x".to_string())), Operator(RightP)]);
+}
 
-    assert_eq!(infix_to_postfix(&expr4_tokenized), expr4_postfix);
-}
\ No newline at end of file
+#[test]
+fn test_token_spans() {
+    let spans = |string: &str| {
+        tokenize_spanned_with(&String::from(string), &|_name: &str| false).unwrap().into_iter()
+            .map(|(_token, span)| (span.start, span.end)).collect::<Vec<(usize, usize)>>()
+    };
+
+    assert_eq!(spans("12 + x_1"), vec![(0, 2), (3, 4), (5, 8)]);
+    assert_eq!(spans("2.5e3(θ)"), vec![(0, 5), (5, 5), (5, 6), (6, 8), (8, 9)]);
+    assert_eq!(spans("-2i"), vec![(0, 1), (1, 3)]);
+}
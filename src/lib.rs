@@ -0,0 +1,37 @@
+// Rusty CAS as a library. The sym binary is a REPL over Engine, other
+// crates use the engine, the expressions and the numeric tower through
+// the items re-exported here, the modules themselves are private.
+mod numeric;
+mod complex;
+mod rational;
+mod bigint;
+mod bigfloat;
+mod interval;
+mod numtheory;
+mod symexpr_rc;
+mod exprpool;
+mod pattern;
+mod simplify;
+mod expand;
+mod polynomial;
+mod lexer;
+mod parser;
+mod symengine;
+
+pub use numeric::{Numeric, NumericError};
+pub use complex::Complex;
+pub use rational::Rational;
+pub use bigfloat::BigFloat;
+pub use interval::Interval;
+pub use numtheory::{gcd, lcm, extended_gcd, mod_inverse, mod_pow, is_prime, factorize, totient};
+pub use symexpr_rc::{Expr, UnaryFunction, BinaryFunction, NaryFunction};
+pub use symexpr_rc::{neg, exp, apply, add, sub, mul, sum, product, div, pow, modulo};
+pub use exprpool::{ExprPool, ExprId};
+pub use pattern::{Pattern, RewriteRule, Bindings};
+pub use simplify::{Simplifier, Rule};
+pub use expand::{expand, collect};
+pub use polynomial::{Polynomial, Monomial};
+pub use lexer::{Token, TermToken, Operator, OperatorType, Tokens, SpannedTokens, LexError, Span};
+pub use lexer::{tokenize_string, tokenize_string_with, tokenize_spanned_with};
+pub use parser::{Ast, AstKind, ParseError, parse_string};
+pub use symengine::{Engine, EngineOutput, EngineError};
@@ -1,19 +1,101 @@
 #![allow(dead_code)]
 
+use std::cmp::Ordering;
+use std::error::Error;
 use std::fmt;
+use std::hash::{Hash, Hasher};
 
-use std::ops::{Add, Sub, Mul, Div, Neg};
+use std::ops::{Add, Sub, Mul, Div, Rem, Neg};
 
-pub type ComplexType = f64;
+use bigfloat;
+use bigfloat::BigFloat;
+use bigint::BigInt;
+use complex::Complex;
+use rational::Rational;
+
+pub type ComplexType = Complex;
 pub type RealType = f64;
 pub type IntegerType = i64;
+pub type BigIntegerType = BigInt;
+pub type RationalType = Rational;
+pub type BigFloatType = BigFloat;
 
 // Numeric type
-#[derive(Clone, Copy, PartialEq, PartialOrd)]
+// Integers that fit in an IntegerType are always stored as Integer,
+// BigInteger is only used for values outside of that range.
+#[derive(Clone)]
 pub enum Numeric {
     Real(RealType),
     Complex(ComplexType),
-    Integer(IntegerType)
+    Integer(IntegerType),
+    BigInteger(BigIntegerType),
+    Rational(RationalType),
+    BigFloat(BigFloatType)
+}
+
+// Upper bound on the size of exact results, so that something like
+// 10^(10^10) reports an overflow instead of exhausting memory
+const MAX_EXACT_BITS: u64 = 1 << 24;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum NumericError {
+    DivisionByZero,
+    Overflow,
+    Domain(&'static str)
+}
+
+impl fmt::Display for NumericError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NumericError::DivisionByZero => write!(f, "division by zero"),
+            NumericError::Overflow => write!(f, "numeric overflow"),
+            NumericError::Domain(reason) => write!(f, "domain error: {}", reason)
+        }
+    }
+}
+
+impl Error for NumericError {}
+
+// Promotion lattice of the numeric tower. Binary operations promote both
+// operands to the higher of their two levels before operating.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum NumericLevel {
+    Integer,
+    Rational,
+    Real,
+    BigFloat,
+    Complex
+}
+
+// Operands of a binary operation, promoted to their common level
+enum Operands {
+    Integer(IntegerType, IntegerType),
+    BigInteger(BigIntegerType, BigIntegerType),
+    Rational(RationalType, RationalType),
+    Real(RealType, RealType),
+    BigFloat(BigFloatType, BigFloatType),
+    Complex(ComplexType, ComplexType)
+}
+
+fn promote(lhs: &Numeric, rhs: &Numeric) -> Operands {
+    match lhs.level().max(rhs.level()) {
+        NumericLevel::Complex => Operands::Complex(lhs.to_complex(), rhs.to_complex()),
+        // Infinities and NaN have no big float representation
+        NumericLevel::BigFloat if !lhs.is_finite() || !rhs.is_finite() => {
+            Operands::Real(lhs.to_real(), rhs.to_real())
+        },
+        NumericLevel::BigFloat => Operands::BigFloat(lhs.to_big_float(), rhs.to_big_float()),
+        NumericLevel::Real => Operands::Real(lhs.to_real(), rhs.to_real()),
+        NumericLevel::Rational => Operands::Rational(lhs.to_rational(), rhs.to_rational()),
+        NumericLevel::Integer => {
+            match (lhs, rhs) {
+                (Numeric::Integer(lhs_integer), Numeric::Integer(rhs_integer)) => {
+                    Operands::Integer(*lhs_integer, *rhs_integer)
+                },
+                _ => Operands::BigInteger(lhs.to_big_integer(), rhs.to_big_integer())
+            }
+        }
+    }
 }
 
 impl fmt::Debug for Numeric {
@@ -21,7 +103,10 @@ impl fmt::Debug for Numeric {
         match self {
             Numeric::Real(real) => write!(f, "{}", *real),
             Numeric::Complex(complex) => write!(f, "{}", *complex),
-            Numeric::Integer(integer) => write!(f, "{}", *integer)
+            Numeric::Integer(integer) => write!(f, "{}", *integer),
+            Numeric::BigInteger(integer) => write!(f, "{}", integer),
+            Numeric::Rational(rational) => write!(f, "{}", rational),
+            Numeric::BigFloat(float) => write!(f, "{}", float)
         }
     }
 }
@@ -31,7 +116,10 @@ impl fmt::Display for Numeric {
         match self {
             Numeric::Real(real) => write!(f, "{}", *real),
             Numeric::Complex(complex) => write!(f, "{}", *complex),
-            Numeric::Integer(integer) => write!(f, "{}", *integer)
+            Numeric::Integer(integer) => write!(f, "{}", *integer),
+            Numeric::BigInteger(integer) => write!(f, "{}", integer),
+            Numeric::Rational(rational) => write!(f, "{}", rational),
+            Numeric::BigFloat(float) => write!(f, "{}", float)
         }
     }
 }
@@ -57,44 +145,572 @@ impl Numeric {
         Numeric::Integer(integer)
     }
 
-    pub fn to_real(self) -> RealType {
+    pub fn from_big_integer(integer: BigIntegerType) -> Numeric {
+        match integer.to_i64() {
+            Some(small) => Numeric::Integer(small),
+            None => Numeric::BigInteger(integer)
+        }
+    }
+
+    // Rationals with unit denominator are stored as integers
+    pub fn from_rational(rational: RationalType) -> Numeric {
+        if rational.is_integer() {
+            Numeric::from_big_integer(rational.numer().clone())
+        } else {
+            Numeric::Rational(rational)
+        }
+    }
+
+    pub fn from_big_float(float: BigFloatType) -> Numeric {
+        Numeric::BigFloat(float)
+    }
+
+    pub fn level(&self) -> NumericLevel {
         match self {
-            Numeric::Real(real) => return real,
-            Numeric::Complex(complex) => return complex as RealType,
-            Numeric::Integer(integer) => return integer as RealType 
+            Numeric::Integer(_) | Numeric::BigInteger(_) => return NumericLevel::Integer,
+            Numeric::Rational(_) => return NumericLevel::Rational,
+            Numeric::Real(_) => return NumericLevel::Real,
+            Numeric::BigFloat(_) => return NumericLevel::BigFloat,
+            Numeric::Complex(_) => return NumericLevel::Complex
+        }
+    }
+
+    pub fn to_real(&self) -> RealType {
+        match self {
+            Numeric::Real(real) => return *real,
+            Numeric::Complex(complex) => return complex.re,
+            Numeric::Integer(integer) => return *integer as RealType,
+            Numeric::BigInteger(integer) => return integer.to_f64(),
+            Numeric::Rational(rational) => return rational.to_real(),
+            Numeric::BigFloat(float) => return float.to_f64()
+        }
+    }
+
+    pub fn to_complex(&self) -> ComplexType {
+        match self {
+            Numeric::Complex(complex) => return *complex,
+            _ => return Complex::from_real(self.to_real())
+        }
+    }
+
+    // Rounded to the working precision, only meaningful for finite values
+    fn to_big_float(&self) -> BigFloatType {
+        match self {
+            Numeric::Integer(integer) => return BigFloat::from_big_integer(&BigInt::from(*integer)),
+            Numeric::BigInteger(integer) => return BigFloat::from_big_integer(integer),
+            Numeric::Rational(rational) => return BigFloat::from_rational(rational),
+            Numeric::BigFloat(float) => return float.clone(),
+            _ => return BigFloat::from_f64(self.to_real()).unwrap_or_else(BigFloat::zero)
+        }
+    }
+
+    // Only meaningful for integers, reals are truncated
+    fn to_big_integer(&self) -> BigIntegerType {
+        match self {
+            Numeric::Integer(integer) => return BigInt::from(*integer),
+            Numeric::BigInteger(integer) => return integer.clone(),
+            _ => return BigInt::from_f64(self.to_real())
+        }
+    }
+
+    // The integer value of an Integer or BigInteger, None for other types
+    pub fn to_big_integer_exact(&self) -> Option<BigIntegerType> {
+        match self {
+            Numeric::Integer(_) | Numeric::BigInteger(_) => return Some(self.to_big_integer()),
+            _ => return None
+        }
+    }
+
+    // Only meaningful for integers and rationals
+    fn to_rational(&self) -> RationalType {
+        match self {
+            Numeric::Rational(rational) => return rational.clone(),
+            _ => return Rational::from_big_integer(self.to_big_integer())
+        }
+    }
+
+    pub fn is_complex(&self) -> bool {
+        match self {
+            Numeric::Complex(_complex) => return true,
+            _ => return false
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        match self {
+            Numeric::Integer(_) | Numeric::BigInteger(_) => return true,
+            _ => return false
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        match self {
+            Numeric::Real(real) => return real.is_finite(),
+            Numeric::Complex(complex) => return complex.re.is_finite() && complex.im.is_finite(),
+            _ => return true
+        }
+    }
+
+    pub fn is_nan(&self) -> bool {
+        match self {
+            Numeric::Real(real) => return real.is_nan(),
+            Numeric::Complex(complex) => return complex.re.is_nan() || complex.im.is_nan(),
+            _ => return false
+        }
+    }
+
+    // Size of the largest integer needed to represent an exact value
+    fn exact_bits(&self) -> u64 {
+        match self {
+            Numeric::Integer(integer) => return 64 - integer.unsigned_abs().leading_zeros() as u64,
+            Numeric::BigInteger(integer) => return integer.bits(),
+            Numeric::Rational(rational) => return rational.numer().bits().max(rational.denom().bits()),
+            _ => return 0
         }
     }
 
     pub fn is_zero(&self) -> bool {
         match self {
             Numeric::Real(real) => return *real == 0.,
-            Numeric::Complex(complex) => return *complex == 0.,
-            Numeric::Integer(integer) => return *integer == 0
+            Numeric::Complex(complex) => return *complex == Complex::zero(),
+            Numeric::Integer(integer) => return *integer == 0,
+            Numeric::BigInteger(integer) => return integer.is_zero(),
+            Numeric::Rational(rational) => return rational.is_zero(),
+            Numeric::BigFloat(float) => return float.is_zero()
         }
     }
 
     pub fn is_unity(&self) -> bool {
         match self {
             Numeric::Real(real) => return *real == 1.,
-            Numeric::Complex(complex) => return *complex == 1.,
-            Numeric::Integer(integer) => return *integer == 1
+            Numeric::Complex(complex) => return *complex == Complex::one(),
+            Numeric::Integer(integer) => return *integer == 1,
+            Numeric::BigInteger(integer) => return integer.is_one(),
+            Numeric::Rational(rational) => return rational.is_unity(),
+            Numeric::BigFloat(float) => return float.is_unity()
         }
     }
 
     pub fn pow(&self, pow: IntegerType) -> Numeric {
         match self {
-            Numeric::Real(real) => return Numeric::from_real(real.powi(pow as i32)),
-            Numeric::Complex(complex) => return Numeric::from_complex(complex.powi(pow as i32)),
-            Numeric::Integer(integer) => return Numeric::from_integer(integer.pow(pow as u32))
+            Numeric::Real(real) => return Numeric::from_real(real.powf(pow as RealType)),
+            Numeric::Complex(complex) => return Numeric::from_complex(complex.powi(pow)),
+            // 0^-1 is the float infinity, checked_pow reports it
+            _ if pow < 0 && self.is_zero() => return self.powf(pow as RealType),
+            // Exact powers past u32::MAX only fit in memory for 0, 1 and -1,
+            // which only depend on the sign and parity of the exponent
+            _ if pow.unsigned_abs() > u32::MAX as u64 => {
+                if self.exact_bits() > 1 {
+                    return self.powf(pow as RealType)
+                }
+                let reduced = 2 + (pow % 2).abs();
+                return self.pow(if pow < 0 { -reduced } else { reduced })
+            },
+            Numeric::Rational(rational) => return Numeric::from_rational(rational.pow(pow)),
+            Numeric::BigFloat(float) => return Numeric::from_big_float(float.powi(pow)),
+            _ if pow < 0 => return Numeric::from_rational(self.to_rational().pow(pow)),
+            Numeric::Integer(integer) => {
+                match integer.checked_pow(pow as u32) {
+                    Some(result) => return Numeric::from_integer(result),
+                    None => return Numeric::from_big_integer(BigInt::from(*integer).pow(pow as u32))
+                }
+            },
+            Numeric::BigInteger(integer) => return Numeric::from_big_integer(integer.pow(pow as u32))
+        }
+    }
+
+    // Raises self to a numeric power. Integer exponents and square roots
+    // stay exact, any other exponent promotes both operands to at least a real.
+    pub fn pow_numeric(&self, exponent: &Numeric) -> Numeric {
+        match exponent {
+            Numeric::Integer(pow) => return self.pow(*pow),
+            Numeric::Rational(pow) if *pow.denom() == BigInt::from(2) => {
+                if let Some(numerator) = pow.numer().to_i64() {
+                    return self.sqrt().pow(numerator)
+                }
+            },
+            _ => {}
+        }
+        match promote(self, exponent) {
+            Operands::Complex(base, pow) => return Numeric::from_complex(base.powc(pow)),
+            Operands::BigFloat(base, pow) if !base.is_negative() && !base.is_zero() => {
+                return Numeric::from_big_float((pow * base.ln()).exp())
+            },
+            _ => return self.powf(exponent.to_real())
+        }
+    }
+
+    // Raises self to a real power, negative bases with fractional
+    // exponents give the principal complex value
+    pub fn powf(&self, exponent: RealType) -> Numeric {
+        match self {
+            Numeric::Complex(complex) => return Numeric::from_complex(complex.powc(Complex::from_real(exponent))),
+            _ => {
+                let base = self.to_real();
+                if base < 0. && exponent.fract() != 0. {
+                    return Numeric::from_complex(Complex::from_real(base).powc(Complex::from_real(exponent)))
+                }
+                return Numeric::from_real(base.powf(exponent))
+            }
+        }
+    }
+
+    // Numeric comparison after promotion to the common level, None if the
+    // values are unordered (NaN, or complex numbers off the real axis)
+    pub fn compare(&self, other: &Numeric) -> Option<Ordering> {
+        match promote(self, other) {
+            Operands::Integer(lhs, rhs) => return Some(lhs.cmp(&rhs)),
+            Operands::BigInteger(lhs, rhs) => return Some(lhs.cmp(&rhs)),
+            Operands::Rational(lhs, rhs) => return Some(lhs.cmp(&rhs)),
+            Operands::Real(lhs, rhs) => return lhs.partial_cmp(&rhs),
+            Operands::BigFloat(lhs, rhs) => return Some(lhs.cmp(&rhs)),
+            Operands::Complex(lhs, rhs) => return lhs.partial_cmp(&rhs)
+        }
+    }
+
+    // Total order for sorting, by level and then by value. Floats use
+    // their total order with -0.0 taken as 0.0, as in equality.
+    pub fn total_cmp(&self, other: &Numeric) -> Ordering {
+        let float_cmp = |lhs: RealType, rhs: RealType| (lhs + 0.0).total_cmp(&(rhs + 0.0));
+        match (self, other) {
+            (Numeric::Real(lhs), Numeric::Real(rhs)) => return float_cmp(*lhs, *rhs),
+            (Numeric::Complex(lhs), Numeric::Complex(rhs)) => {
+                return float_cmp(lhs.re, rhs.re).then(float_cmp(lhs.im, rhs.im))
+            },
+            _ if self.level() != other.level() => return self.level().cmp(&other.level()),
+            _ => return self.compare(other).unwrap_or(Ordering::Equal)
         }
     }
 
     pub fn exp(&self) -> Numeric {
+        if let Some(argument) = self.big_float_exp_argument() {
+            return Numeric::from_big_float(argument.exp())
+        }
         match self {
-            Numeric::Real(real) => return Numeric::from_real(real.exp()),
             Numeric::Complex(complex) => return Numeric::from_complex(complex.exp()),
-            Numeric::Integer(integer) => return Numeric::from_real((*integer as RealType).exp())
+            _ => return Numeric::from_real(self.to_real().exp())
+        }
+    }
+
+    pub fn checked_add(self, other: Numeric) -> Result<Numeric, NumericError> {
+        let finite = self.is_finite() && other.is_finite();
+        check_result(self + other, finite)
+    }
+
+    pub fn checked_sub(self, other: Numeric) -> Result<Numeric, NumericError> {
+        let finite = self.is_finite() && other.is_finite();
+        check_result(self - other, finite)
+    }
+
+    pub fn checked_mul(self, other: Numeric) -> Result<Numeric, NumericError> {
+        let finite = self.is_finite() && other.is_finite();
+        check_result(self * other, finite)
+    }
+
+    pub fn checked_div(self, other: Numeric) -> Result<Numeric, NumericError> {
+        if other.is_zero() {
+            return Err(NumericError::DivisionByZero)
+        }
+        let finite = self.is_finite() && other.is_finite();
+        check_result(self / other, finite)
+    }
+
+    pub fn checked_rem(self, other: Numeric) -> Result<Numeric, NumericError> {
+        if other.is_zero() {
+            return Err(NumericError::DivisionByZero)
+        }
+        if self.is_complex() || other.is_complex() {
+            return Err(NumericError::Domain("remainder of a complex number"))
+        }
+        let finite = self.is_finite() && other.is_finite();
+        check_result(self % other, finite)
+    }
+
+    pub fn checked_pow(&self, pow: IntegerType) -> Result<Numeric, NumericError> {
+        if pow < 0 && self.is_zero() {
+            return Err(NumericError::DivisionByZero)
+        }
+        // Powers of 0, 1 and -1 never grow, everything else is bounded
+        let bits = self.exact_bits();
+        if bits > 1 && (bits - 1).saturating_mul(pow.unsigned_abs()) > MAX_EXACT_BITS {
+            return Err(NumericError::Overflow)
+        }
+        check_result(self.pow(pow), self.is_finite())
+    }
+
+    pub fn checked_pow_numeric(&self, exponent: &Numeric) -> Result<Numeric, NumericError> {
+        match exponent {
+            Numeric::Integer(pow) => return self.checked_pow(*pow),
+            Numeric::BigInteger(_) if self.exact_bits() > 1 => {
+                return Err(NumericError::Overflow)
+            },
+            _ => {}
+        }
+        if self.is_zero() && exponent.compare(&Numeric::zero()) == Some(Ordering::Less) {
+            return Err(NumericError::DivisionByZero)
+        }
+        check_result(self.pow_numeric(exponent), self.is_finite() && exponent.is_finite())
+    }
+
+    pub fn checked_exp(&self) -> Result<Numeric, NumericError> {
+        check_result(self.exp(), self.is_finite())
+    }
+
+    // Applies an elementary function such as Numeric::sin, reporting
+    // non-finite results from finite arguments
+    pub fn checked_apply(&self, function: fn(&Numeric) -> Numeric) -> Result<Numeric, NumericError> {
+        check_result(function(self), self.is_finite())
+    }
+
+    pub fn checked_ln(&self) -> Result<Numeric, NumericError> {
+        if self.is_zero() {
+            return Err(NumericError::Domain("logarithm of zero"))
+        }
+        self.checked_apply(Numeric::ln)
+    }
+
+    pub fn checked_log(&self, base: &Numeric) -> Result<Numeric, NumericError> {
+        if base.is_zero() || base.is_unity() {
+            return Err(NumericError::Domain("logarithm to base zero or one"))
+        }
+        if self.is_zero() {
+            return Err(NumericError::Domain("logarithm of zero"))
+        }
+        check_result(self.log(base), self.is_finite() && base.is_finite())
+    }
+
+    pub fn checked_atan2(&self, x: &Numeric) -> Result<Numeric, NumericError> {
+        if self.is_complex() || x.is_complex() {
+            return Err(NumericError::Domain("atan2 of a complex number"))
+        }
+        Ok(self.atan2(x))
+    }
+}
+
+// Square root of a perfect square
+fn exact_sqrt(integer: &BigInt) -> Option<BigInt> {
+    let root = integer.isqrt();
+    if root.clone() * root.clone() == *integer {
+        Some(root)
+    } else {
+        None
+    }
+}
+
+// Elementary functions. Exact arguments give exact results where the
+// result is known to be exact, such as sqrt(4) = 2 and sin(0) = 0. Real
+// arguments outside of the real domain of a function, such as sqrt(-1) or
+// asin(2), give the principal complex value.
+impl Numeric {
+    fn is_exact_zero(&self) -> bool {
+        *self == Numeric::zero()
+    }
+
+    // Arguments evaluated with big floats: big floats themselves, and exact
+    // values while a working precision is set
+    fn big_float_argument(&self) -> Option<BigFloatType> {
+        match self {
+            Numeric::BigFloat(float) => return Some(float.clone()),
+            _ if self.level() <= NumericLevel::Rational && bigfloat::precision().is_some() => {
+                return Some(self.to_big_float())
+            },
+            _ => return None
+        }
+    }
+
+    // A big float argument small enough that e to its power fits the
+    // exponent of a big float
+    fn big_float_exp_argument(&self) -> Option<BigFloatType> {
+        let argument = self.big_float_argument()?;
+        if argument.abs() < BigFloat::from_big_integer(&BigInt::from(1_000_000_000_000)) {
+            return Some(argument)
+        }
+        return None
+    }
+
+    fn real_or_complex(&self, real: fn(RealType) -> RealType, complex: fn(&Complex) -> Complex,
+                       in_real_domain: fn(RealType) -> bool) -> Numeric {
+        match self {
+            Numeric::Complex(argument) => return Numeric::from_complex(complex(argument)),
+            _ => {
+                let argument = self.to_real();
+                if in_real_domain(argument) {
+                    return Numeric::from_real(real(argument))
+                }
+                return Numeric::from_complex(complex(&Complex::from_real(argument)))
+            }
+        }
+    }
+
+    pub fn sqrt(&self) -> Numeric {
+        if self.level() <= NumericLevel::Rational {
+            let rational = self.to_rational();
+            if let (Some(num), Some(den)) = (exact_sqrt(&rational.numer().abs()), exact_sqrt(rational.denom())) {
+                let root = Numeric::from_rational(Rational::from_parts(num, den));
+                if rational.is_negative() {
+                    return Numeric::from_complex(Complex::new(0., root.to_real()))
+                }
+                return root
+            }
         }
+        if let Some(argument) = self.big_float_argument() {
+            if !argument.is_negative() {
+                return Numeric::from_big_float(argument.sqrt())
+            }
+        }
+        self.real_or_complex(RealType::sqrt, Complex::sqrt, |x| x >= 0.)
+    }
+
+    // Natural logarithm, ln(1) = 0 stays exact
+    pub fn ln(&self) -> Numeric {
+        if *self == Numeric::one() {
+            return Numeric::zero()
+        }
+        if let Some(argument) = self.big_float_argument() {
+            if !argument.is_negative() && !argument.is_zero() {
+                return Numeric::from_big_float(argument.ln())
+            }
+        }
+        self.real_or_complex(RealType::ln, Complex::ln, |x| x >= 0.)
+    }
+
+    // Logarithm to an arbitrary base, exact when self is an integer power
+    // of an integer base, such as log(8, 2) = 3
+    pub fn log(&self, base: &Numeric) -> Numeric {
+        if let (Numeric::Integer(value), Numeric::Integer(base_value)) = (self, base) {
+            if *value > 0 && *base_value > 1 {
+                let mut remaining = *value;
+                let mut exponent = 0;
+                while remaining % base_value == 0 {
+                    remaining /= base_value;
+                    exponent += 1;
+                }
+                if remaining == 1 {
+                    return Numeric::from_integer(exponent)
+                }
+            }
+        }
+        let base_ln = base.ln();
+        if base_ln.is_zero() {
+            return Numeric::from_real(RealType::NAN)
+        }
+        self.ln() / base_ln
+    }
+
+    pub fn sin(&self) -> Numeric {
+        if self.is_exact_zero() {
+            return Numeric::zero()
+        }
+        if let Some(argument) = self.big_float_argument() {
+            return Numeric::from_big_float(argument.sin())
+        }
+        self.real_or_complex(RealType::sin, Complex::sin, |_| true)
+    }
+
+    pub fn cos(&self) -> Numeric {
+        if self.is_exact_zero() {
+            return Numeric::one()
+        }
+        if let Some(argument) = self.big_float_argument() {
+            return Numeric::from_big_float(argument.cos())
+        }
+        self.real_or_complex(RealType::cos, Complex::cos, |_| true)
+    }
+
+    pub fn tan(&self) -> Numeric {
+        if self.is_exact_zero() {
+            return Numeric::zero()
+        }
+        if let Some(argument) = self.big_float_argument() {
+            return Numeric::from_big_float(argument.sin() / argument.cos())
+        }
+        self.real_or_complex(RealType::tan, Complex::tan, |_| true)
+    }
+
+    pub fn asin(&self) -> Numeric {
+        if self.is_exact_zero() {
+            return Numeric::zero()
+        }
+        if let Some(argument) = self.big_float_argument() {
+            if argument.abs() <= BigFloat::one() {
+                return Numeric::from_big_float(argument.asin())
+            }
+        }
+        self.real_or_complex(RealType::asin, Complex::asin, |x| (-1. ..=1.).contains(&x))
+    }
+
+    pub fn acos(&self) -> Numeric {
+        if *self == Numeric::one() {
+            return Numeric::zero()
+        }
+        if let Some(argument) = self.big_float_argument() {
+            if argument.abs() <= BigFloat::one() {
+                return Numeric::from_big_float(argument.acos())
+            }
+        }
+        self.real_or_complex(RealType::acos, Complex::acos, |x| (-1. ..=1.).contains(&x))
+    }
+
+    pub fn atan(&self) -> Numeric {
+        if self.is_exact_zero() {
+            return Numeric::zero()
+        }
+        if let Some(argument) = self.big_float_argument() {
+            return Numeric::from_big_float(argument.atan())
+        }
+        self.real_or_complex(RealType::atan, Complex::atan, |_| true)
+    }
+
+    // Angle of the point (x, self), only defined for real arguments
+    pub fn atan2(&self, x: &Numeric) -> Numeric {
+        if self.is_complex() || x.is_complex() {
+            return Numeric::from_real(RealType::NAN)
+        }
+        if self.is_exact_zero() && x.compare(&Numeric::zero()) == Some(Ordering::Greater) {
+            return Numeric::zero()
+        }
+        Numeric::from_real(self.to_real().atan2(x.to_real()))
+    }
+
+    pub fn sinh(&self) -> Numeric {
+        if self.is_exact_zero() {
+            return Numeric::zero()
+        }
+        if let Some(argument) = self.big_float_exp_argument() {
+            return Numeric::from_big_float(argument.sinh())
+        }
+        self.real_or_complex(RealType::sinh, Complex::sinh, |_| true)
+    }
+
+    pub fn cosh(&self) -> Numeric {
+        if self.is_exact_zero() {
+            return Numeric::one()
+        }
+        if let Some(argument) = self.big_float_exp_argument() {
+            return Numeric::from_big_float(argument.cosh())
+        }
+        self.real_or_complex(RealType::cosh, Complex::cosh, |_| true)
+    }
+
+    pub fn tanh(&self) -> Numeric {
+        if self.is_exact_zero() {
+            return Numeric::zero()
+        }
+        if let Some(argument) = self.big_float_argument() {
+            return Numeric::from_big_float(argument.tanh())
+        }
+        self.real_or_complex(RealType::tanh, Complex::tanh, |_| true)
+    }
+}
+
+// A non-finite result from finite operands is reported as an error
+fn check_result(result: Numeric, finite_operands: bool) -> Result<Numeric, NumericError> {
+    if !finite_operands || result.is_finite() {
+        Ok(result)
+    } else if result.is_nan() {
+        Err(NumericError::Domain("result is not a number"))
+    } else {
+        Err(NumericError::Overflow)
     }
 }
 
@@ -105,7 +721,78 @@ impl Neg for Numeric {
         match self {
             Numeric::Real(real) => return Numeric::from_real(-real),
             Numeric::Complex(complex) => return Numeric::from_complex(-complex),
-            Numeric::Integer(integer) => return Numeric::from_integer(-integer)
+            Numeric::Integer(integer) => {
+                match integer.checked_neg() {
+                    Some(result) => return Numeric::from_integer(result),
+                    None => return Numeric::from_big_integer(-BigInt::from(integer))
+                }
+            },
+            Numeric::BigInteger(integer) => return Numeric::from_big_integer(-integer),
+            Numeric::Rational(rational) => return Numeric::from_rational(-rational),
+            Numeric::BigFloat(float) => return Numeric::from_big_float(-float)
+        }
+    }
+}
+
+// Floats compare and hash by their bits with -0.0 folded into 0.0 and
+// every NaN alike, so that equality is reflexive for hash map keys
+fn float_bits(value: RealType) -> u64 {
+    if value == 0.0 {
+        return (0.0 as RealType).to_bits()
+    }
+    if value.is_nan() {
+        return RealType::NAN.to_bits()
+    }
+    value.to_bits()
+}
+
+fn hash_float<H: Hasher>(value: RealType, state: &mut H) {
+    float_bits(value).hash(state);
+}
+
+// Structural equality, numerically equal values of different types differ
+impl PartialEq for Numeric {
+    fn eq(&self, other: &Numeric) -> bool {
+        match (self, other) {
+            (Numeric::Real(lhs), Numeric::Real(rhs)) => return float_bits(*lhs) == float_bits(*rhs),
+            (Numeric::Complex(lhs), Numeric::Complex(rhs)) => {
+                return float_bits(lhs.re) == float_bits(rhs.re) && float_bits(lhs.im) == float_bits(rhs.im)
+            },
+            (Numeric::Integer(lhs), Numeric::Integer(rhs)) => return lhs == rhs,
+            (Numeric::BigInteger(lhs), Numeric::BigInteger(rhs)) => return lhs == rhs,
+            (Numeric::Rational(lhs), Numeric::Rational(rhs)) => return lhs == rhs,
+            (Numeric::BigFloat(lhs), Numeric::BigFloat(rhs)) => return lhs == rhs,
+            _ => return false
+        }
+    }
+}
+
+impl Hash for Numeric {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.level().hash(state);
+        match self {
+            Numeric::Real(value) => hash_float(*value, state),
+            Numeric::Complex(value) => {
+                hash_float(value.re, state);
+                hash_float(value.im, state);
+            },
+            Numeric::Integer(value) => value.hash(state),
+            Numeric::BigInteger(value) => value.hash(state),
+            Numeric::Rational(value) => value.hash(state),
+            Numeric::BigFloat(value) => value.hash(state)
+        }
+    }
+}
+
+impl Eq for Numeric {}
+
+// Ordering follows the numeric value, but numerically equal values of
+// different types are unordered to stay consistent with equality
+impl PartialOrd for Numeric {
+    fn partial_cmp(&self, other: &Numeric) -> Option<Ordering> {
+        match self.compare(other) {
+            Some(Ordering::Equal) if self != other => None,
+            ordering => ordering
         }
     }
 }
@@ -114,31 +801,18 @@ impl Add for Numeric {
     type Output = Numeric;
 
     fn add(self, other: Numeric) -> Numeric {
-        match self {
-            Numeric::Real(lhs_real) => {
-                let rhs = match other {
-                    Numeric::Real(rhs_real) => rhs_real,
-                    Numeric::Complex(rhs_complex) => rhs_complex as RealType,
-                    Numeric::Integer(rhs_integer) => rhs_integer as RealType
-                };
-                return Numeric::from_real(lhs_real + rhs)
-            },
-            Numeric::Complex(lhs_complex) => {
-                let rhs = match other {
-                    Numeric::Real(rhs_real) => rhs_real as ComplexType,
-                    Numeric::Complex(rhs_complex) => rhs_complex,
-                    Numeric::Integer(rhs_integer) => rhs_integer as ComplexType
-                };
-                return Numeric::from_complex(lhs_complex + rhs)
-            },
-            Numeric::Integer(lhs_integer) => {
-                let rhs = match other {
-                    Numeric::Real(rhs_real) => rhs_real as IntegerType,
-                    Numeric::Complex(rhs_complex) => rhs_complex as IntegerType,
-                    Numeric::Integer(rhs_integer) => rhs_integer
-                };
-                return Numeric::from_integer(lhs_integer + rhs)
-            }
+        match promote(&self, &other) {
+            Operands::Integer(lhs, rhs) => {
+                match lhs.checked_add(rhs) {
+                    Some(result) => return Numeric::from_integer(result),
+                    None => return Numeric::from_big_integer(BigInt::from(lhs) + BigInt::from(rhs))
+                }
+            },
+            Operands::BigInteger(lhs, rhs) => return Numeric::from_big_integer(lhs + rhs),
+            Operands::Rational(lhs, rhs) => return Numeric::from_rational(lhs + rhs),
+            Operands::Real(lhs, rhs) => return Numeric::from_real(lhs + rhs),
+            Operands::BigFloat(lhs, rhs) => return Numeric::from_big_float(lhs + rhs),
+            Operands::Complex(lhs, rhs) => return Numeric::from_complex(lhs + rhs)
         }
     }
 }
@@ -147,31 +821,18 @@ impl Sub for Numeric {
     type Output = Numeric;
 
     fn sub(self, other: Numeric) -> Numeric {
-        match self {
-            Numeric::Real(lhs_real) => {
-                let rhs = match other {
-                    Numeric::Real(rhs_real) => rhs_real,
-                    Numeric::Complex(rhs_complex) => rhs_complex as RealType,
-                    Numeric::Integer(rhs_integer) => rhs_integer as RealType
-                };
-                return Numeric::from_real(lhs_real - rhs)
-            },
-            Numeric::Complex(lhs_complex) => {
-                let rhs = match other {
-                    Numeric::Real(rhs_real) => rhs_real as ComplexType,
-                    Numeric::Complex(rhs_complex) => rhs_complex,
-                    Numeric::Integer(rhs_integer) => rhs_integer as ComplexType
-                };
-                return Numeric::from_complex(lhs_complex - rhs)
-            },
-            Numeric::Integer(lhs_integer) => {
-                let rhs = match other {
-                    Numeric::Real(rhs_real) => rhs_real as IntegerType,
-                    Numeric::Complex(rhs_complex) => rhs_complex as IntegerType,
-                    Numeric::Integer(rhs_integer) => rhs_integer
-                };
-                return Numeric::from_integer(lhs_integer - rhs)
-            }
+        match promote(&self, &other) {
+            Operands::Integer(lhs, rhs) => {
+                match lhs.checked_sub(rhs) {
+                    Some(result) => return Numeric::from_integer(result),
+                    None => return Numeric::from_big_integer(BigInt::from(lhs) - BigInt::from(rhs))
+                }
+            },
+            Operands::BigInteger(lhs, rhs) => return Numeric::from_big_integer(lhs - rhs),
+            Operands::Rational(lhs, rhs) => return Numeric::from_rational(lhs - rhs),
+            Operands::Real(lhs, rhs) => return Numeric::from_real(lhs - rhs),
+            Operands::BigFloat(lhs, rhs) => return Numeric::from_big_float(lhs - rhs),
+            Operands::Complex(lhs, rhs) => return Numeric::from_complex(lhs - rhs)
         }
     }
 }
@@ -180,31 +841,18 @@ impl Mul for Numeric {
     type Output = Numeric;
 
     fn mul(self, other: Numeric) -> Numeric {
-        match self {
-            Numeric::Real(lhs_real) => {
-                let rhs = match other {
-                    Numeric::Real(rhs_real) => rhs_real,
-                    Numeric::Complex(rhs_complex) => rhs_complex as RealType,
-                    Numeric::Integer(rhs_integer) => rhs_integer as RealType
-                };
-                return Numeric::from_real(lhs_real * rhs)
-            },
-            Numeric::Complex(lhs_complex) => {
-                let rhs = match other {
-                    Numeric::Real(rhs_real) => rhs_real as ComplexType,
-                    Numeric::Complex(rhs_complex) => rhs_complex,
-                    Numeric::Integer(rhs_integer) => rhs_integer as ComplexType
-                };
-                return Numeric::from_complex(lhs_complex * rhs)
-            },
-            Numeric::Integer(lhs_integer) => {
-                let rhs = match other {
-                    Numeric::Real(rhs_real) => rhs_real as IntegerType,
-                    Numeric::Complex(rhs_complex) => rhs_complex as IntegerType,
-                    Numeric::Integer(rhs_integer) => rhs_integer
-                };
-                return Numeric::from_integer(lhs_integer * rhs)
-            }
+        match promote(&self, &other) {
+            Operands::Integer(lhs, rhs) => {
+                match lhs.checked_mul(rhs) {
+                    Some(result) => return Numeric::from_integer(result),
+                    None => return Numeric::from_big_integer(BigInt::from(lhs) * BigInt::from(rhs))
+                }
+            },
+            Operands::BigInteger(lhs, rhs) => return Numeric::from_big_integer(lhs * rhs),
+            Operands::Rational(lhs, rhs) => return Numeric::from_rational(lhs * rhs),
+            Operands::Real(lhs, rhs) => return Numeric::from_real(lhs * rhs),
+            Operands::BigFloat(lhs, rhs) => return Numeric::from_big_float(lhs * rhs),
+            Operands::Complex(lhs, rhs) => return Numeric::from_complex(lhs * rhs)
         }
     }
 }
@@ -213,31 +861,62 @@ impl Div for Numeric {
     type Output = Numeric;
 
     fn div(self, other: Numeric) -> Numeric {
-        match self {
-            Numeric::Real(lhs_real) => {
-                let rhs = match other {
-                    Numeric::Real(rhs_real) => rhs_real,
-                    Numeric::Complex(rhs_complex) => rhs_complex as RealType,
-                    Numeric::Integer(rhs_integer) => rhs_integer as RealType
-                };
-                return Numeric::from_real(lhs_real / rhs)
-            },
-            Numeric::Complex(lhs_complex) => {
-                let rhs = match other {
-                    Numeric::Real(rhs_real) => rhs_real as ComplexType,
-                    Numeric::Complex(rhs_complex) => rhs_complex,
-                    Numeric::Integer(rhs_integer) => rhs_integer as ComplexType
-                };
-                return Numeric::from_complex(lhs_complex / rhs)
-            },
-            Numeric::Integer(lhs_integer) => {
-                let rhs = match other {
-                    Numeric::Real(rhs_real) => rhs_real as IntegerType,
-                    Numeric::Complex(rhs_complex) => rhs_complex as IntegerType,
-                    Numeric::Integer(rhs_integer) => rhs_integer
-                };
-                return Numeric::from_integer(lhs_integer / rhs)
-            }
+        // Division by zero is the float one, inf or NaN, checked_div reports it
+        if other.is_zero() && !self.is_complex() && !other.is_complex() {
+            return Numeric::from_real(self.to_real() / other.to_real())
+        }
+        match promote(&self, &other) {
+            Operands::Integer(lhs, rhs) => {
+                match (lhs.checked_rem(rhs), lhs.checked_div(rhs)) {
+                    (Some(0), Some(result)) => return Numeric::from_integer(result),
+                    _ => return Numeric::from_rational(Rational::from_parts(BigInt::from(lhs), BigInt::from(rhs)))
+                }
+            },
+            Operands::BigInteger(lhs, rhs) => return Numeric::from_rational(Rational::from_parts(lhs, rhs)),
+            Operands::Rational(lhs, rhs) => return Numeric::from_rational(lhs / rhs),
+            Operands::Real(lhs, rhs) => return Numeric::from_real(lhs / rhs),
+            Operands::BigFloat(lhs, rhs) => return Numeric::from_big_float(lhs / rhs),
+            Operands::Complex(lhs, rhs) => return Numeric::from_complex(lhs / rhs)
+        }
+    }
+}
+
+// Floored remainder, with the sign of the divisor, a - b floor(a / b)
+impl Rem for Numeric {
+    type Output = Numeric;
+
+    fn rem(self, other: Numeric) -> Numeric {
+        // As for floats, checked_rem reports it
+        if other.is_zero() {
+            return Numeric::from_real(RealType::NAN)
+        }
+        match promote(&self, &other) {
+            Operands::Integer(lhs, rhs) => {
+                match lhs.checked_rem(rhs) {
+                    Some(remainder) if remainder != 0 && (remainder < 0) != (rhs < 0) => {
+                        return Numeric::from_integer(remainder + rhs)
+                    },
+                    Some(remainder) => return Numeric::from_integer(remainder),
+                    // Only i64::MIN % -1 overflows
+                    None => return Numeric::zero()
+                }
+            },
+            Operands::BigInteger(lhs, rhs) => return Numeric::from_big_integer(lhs.div_mod_floor(&rhs).1),
+            Operands::Rational(lhs, rhs) => {
+                let quotient = lhs.clone() / rhs.clone();
+                let floor = quotient.numer().div_mod_floor(quotient.denom()).0;
+                return Numeric::from_rational(lhs - rhs * Rational::from_big_integer(floor))
+            },
+            Operands::Real(lhs, rhs) => return Numeric::from_real(lhs - rhs * (lhs / rhs).floor()),
+            Operands::BigFloat(lhs, rhs) => {
+                let quotient = lhs.clone() / rhs.clone();
+                let mut floor = BigFloat::from_big_integer(&quotient.round_to_integer());
+                if floor > quotient {
+                    floor = floor - BigFloat::one();
+                }
+                return Numeric::from_big_float(lhs - rhs * floor)
+            },
+            Operands::Complex(_, _) => return Numeric::from_real(RealType::NAN)
         }
     }
 }
@@ -249,4 +928,247 @@ fn test_basic_operations() {
     let b = Numeric::from_integer(1);
 
     assert_eq!(a + b, Numeric::from_real(2.));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_complex_operations() {
+    let i = Numeric::from_complex(Complex::i());
+    let two = Numeric::from_integer(2);
+    let half = Numeric::from_real(0.5);
+
+    assert_eq!(i.clone() * i.clone(), Numeric::from_complex(Complex::from_real(-1.)));
+    assert_eq!(two.clone() + i.clone(), Numeric::from_complex(Complex::new(2., 1.)));
+    assert_eq!(i.clone() + two.clone(), Numeric::from_complex(Complex::new(2., 1.)));
+    assert_eq!(half * i.clone(), Numeric::from_complex(Complex::new(0., 0.5)));
+    assert_eq!(two / i.clone(), Numeric::from_complex(Complex::new(0., -2.)));
+    assert_eq!(-i.clone(), Numeric::from_complex(Complex::new(0., -1.)));
+    assert_eq!(i.pow(2), Numeric::from_complex(Complex::from_real(-1.)));
+    assert!(!i.is_zero());
+    assert!(i.pow(4).is_unity());
+
+    let euler = (i * Numeric::from_real(::std::f64::consts::PI)).exp();
+    assert!((euler.to_complex() - Complex::from_real(-1.)).abs() < 1e-12);
+}
+
+#[test]
+fn test_rational_operations() {
+    let one = Numeric::from_integer(1);
+    let three = Numeric::from_integer(3);
+    let third = one.clone() / three.clone();
+
+    assert_eq!(third, Numeric::Rational(Rational::new(1, 3)));
+    assert_eq!(format!("{}", third), "1/3");
+    assert_eq!(third.clone() * three.clone(), one);
+    assert_eq!(Numeric::from_integer(6) / Numeric::from_integer(3), Numeric::from_integer(2));
+    assert_eq!(third.clone() + third.clone() + third.clone(), one);
+    assert_eq!(three.clone() - third.clone(), Numeric::from_rational(Rational::new(8, 3)));
+    assert_eq!(third.clone() + Numeric::from_real(0.5), Numeric::from_real(1. / 3. + 0.5));
+    assert_eq!(three.pow(-2), Numeric::from_rational(Rational::new(1, 9)));
+    assert_eq!(third.pow(2), Numeric::from_rational(Rational::new(1, 9)));
+    assert_eq!(-third, Numeric::from_rational(Rational::new(-1, 3)));
+}
+
+#[test]
+fn test_integer_promotion_on_overflow() {
+    let max = Numeric::from_integer(IntegerType::MAX);
+    let one = Numeric::one();
+    let two = Numeric::from_integer(2);
+
+    let beyond = max.clone() + one.clone();
+    assert_eq!(format!("{}", beyond), "9223372036854775808");
+    assert_eq!(beyond - one.clone(), max);
+    assert_eq!(format!("{}", two.pow(70)), "1180591620717411303424");
+    assert_eq!(two.pow(70) / two.pow(68), Numeric::from_integer(4));
+    assert_eq!(-Numeric::from_integer(IntegerType::MIN), max + one);
+
+    let factorial = (1..31).fold(Numeric::one(), |acc, n| acc * Numeric::from_integer(n));
+    assert_eq!(format!("{}", factorial), "265252859812191058636308480000000");
+    assert_eq!(format!("{}", Numeric::one() / factorial), "1/265252859812191058636308480000000");
+
+    // Exponents past u32::MAX are not truncated, and division by zero gives
+    // a float instead of panicking
+    let huge = (1 << 32) + 1;
+    assert_eq!(two.pow(huge), Numeric::from_real(RealType::INFINITY));
+    assert_eq!(Numeric::from_rational(Rational::new(1, 2)).pow(huge), Numeric::from_real(0.));
+    assert_eq!(Numeric::zero().pow(1 << 32), Numeric::zero());
+    assert_eq!(Numeric::from_integer(-1).pow(huge), Numeric::from_integer(-1));
+    assert_eq!(Numeric::from_integer(-1).pow(-huge - 1), Numeric::one());
+    assert_eq!(Numeric::zero().pow(-1), Numeric::from_real(RealType::INFINITY));
+    assert_eq!(Numeric::one() / Numeric::zero(), Numeric::from_real(RealType::INFINITY));
+    assert!((Numeric::zero() / Numeric::zero()).to_real().is_nan());
+    assert!((Numeric::from_integer(2).pow(70) % Numeric::zero()).to_real().is_nan());
+}
+
+#[test]
+fn test_promotion_is_commutative() {
+    let one = Numeric::from_integer(1);
+    let half = Numeric::from_real(0.5);
+    let third = Numeric::from_rational(Rational::new(1, 3));
+    let i = Numeric::from_complex(Complex::i());
+
+    assert_eq!(one.clone() + half.clone(), Numeric::from_real(1.5));
+    assert_eq!(half.clone() + one.clone(), Numeric::from_real(1.5));
+    assert_eq!(one.clone() - half.clone(), Numeric::from_real(0.5));
+    assert_eq!(one.clone() * half.clone(), half.clone() * one.clone());
+    assert_eq!(one.clone() / half.clone(), Numeric::from_real(2.));
+    assert_eq!(third.clone() + half.clone(), half.clone() + third.clone());
+    assert_eq!(third.clone() * i.clone(), i.clone() * third.clone());
+    assert_eq!((third.clone() + one.clone()).level(), NumericLevel::Rational);
+    assert_eq!((third * half.clone()).level(), NumericLevel::Real);
+    assert_eq!((i + one.clone()).level(), NumericLevel::Complex);
+
+    assert_eq!(Numeric::from_integer(4).pow_numeric(&half), Numeric::from_real(2.));
+    assert_eq!(Numeric::from_integer(2).pow_numeric(&Numeric::from_integer(-1)),
+               Numeric::from_rational(Rational::new(1, 2)));
+}
+
+#[test]
+fn test_comparison() {
+    let one = Numeric::from_integer(1);
+    let half = Numeric::from_real(0.5);
+    let third = Numeric::from_rational(Rational::new(1, 3));
+    let huge = Numeric::from_integer(2).pow(80);
+
+    assert_eq!(one.compare(&Numeric::from_real(1.)), Some(Ordering::Equal));
+    assert_eq!(half.compare(&third), Some(Ordering::Greater));
+    assert_eq!(third.compare(&Numeric::zero()), Some(Ordering::Greater));
+    assert_eq!(huge.compare(&one), Some(Ordering::Greater));
+    assert_eq!(Numeric::from_complex(Complex::i()).compare(&one), None);
+    assert!(third < half);
+    assert!(Numeric::from_real(-100.) < one);
+    assert!(one < huge);
+    assert_eq!(one.partial_cmp(&Numeric::from_real(1.)), None);
+}
+
+#[test]
+fn test_checked_operations() {
+    let zero = Numeric::zero();
+    let one = Numeric::one();
+    let two = Numeric::from_integer(2);
+
+    assert_eq!(one.clone().checked_div(zero.clone()), Err(NumericError::DivisionByZero));
+    assert_eq!(one.clone().checked_div(Numeric::from_real(0.)), Err(NumericError::DivisionByZero));
+    assert_eq!(one.clone().checked_div(two.clone()), Ok(Numeric::from_rational(Rational::new(1, 2))));
+    assert_eq!(zero.checked_pow(-1), Err(NumericError::DivisionByZero));
+    assert_eq!(two.checked_pow(-2), Ok(Numeric::from_rational(Rational::new(1, 4))));
+    assert_eq!(two.checked_pow(IntegerType::MAX), Err(NumericError::Overflow));
+    assert_eq!(one.checked_pow(IntegerType::MAX), Ok(one.clone()));
+    assert_eq!(Numeric::from_integer(-1).checked_pow(IntegerType::MAX), Ok(Numeric::from_integer(-1)));
+    assert_eq!(Numeric::from_real(1e308).checked_mul(two.clone()), Err(NumericError::Overflow));
+    assert_eq!(Numeric::from_integer(1000).checked_exp(), Err(NumericError::Overflow));
+    assert_eq!(Numeric::from_real(-8.).checked_pow_numeric(&Numeric::from_real(0.5)).map(|root| root.is_complex()),
+               Ok(true));
+    assert_eq!(two.checked_pow_numeric(&two.pow(70)), Err(NumericError::Overflow));
+    assert_eq!(Numeric::from_real(RealType::INFINITY).checked_add(one), Ok(Numeric::from_real(RealType::INFINITY)));
+}
+
+#[cfg(test)]
+fn assert_close(lhs: &Numeric, rhs: &Numeric) {
+    assert!((lhs.to_complex() - rhs.to_complex()).abs() < 1e-12, "{} != {}", lhs, rhs);
+}
+
+#[test]
+fn test_exact_elementary_functions() {
+    let zero = Numeric::zero();
+    let one = Numeric::one();
+
+    assert_eq!(Numeric::from_integer(4).sqrt(), Numeric::from_integer(2));
+    assert_eq!(Numeric::from_rational(Rational::new(9, 4)).sqrt(), Numeric::from_rational(Rational::new(3, 2)));
+    assert_eq!(Numeric::from_integer(2).pow(100).sqrt(), Numeric::from_integer(2).pow(50));
+    assert_eq!(Numeric::from_integer(-1).sqrt(), Numeric::from_complex(Complex::i()));
+    assert_eq!(Numeric::from_integer(-4).sqrt(), Numeric::from_complex(Complex::new(0., 2.)));
+    assert_eq!(Numeric::from_integer(2).sqrt(), Numeric::from_real(2f64.sqrt()));
+    assert_eq!(Numeric::from_integer(9).pow_numeric(&Numeric::from_rational(Rational::new(3, 2))),
+               Numeric::from_integer(27));
+    assert_eq!(one.ln(), zero);
+    assert_eq!(Numeric::from_integer(8).log(&Numeric::from_integer(2)), Numeric::from_integer(3));
+    assert_eq!(zero.sin(), zero);
+    assert_eq!(zero.cos(), one);
+    assert_eq!(zero.tan(), zero);
+    assert_eq!(zero.asin(), zero);
+    assert_eq!(one.acos(), zero);
+    assert_eq!(zero.atan(), zero);
+    assert_eq!(zero.atan2(&one), zero);
+    assert_eq!(zero.sinh(), zero);
+    assert_eq!(zero.cosh(), one);
+    assert_eq!(zero.tanh(), zero);
+}
+
+#[test]
+fn test_real_and_complex_elementary_functions() {
+    let half = Numeric::from_real(0.5);
+    let two = Numeric::from_integer(2);
+    let i = Numeric::from_complex(Complex::i());
+    let pi = ::std::f64::consts::PI;
+
+    assert_eq!(half.sin(), Numeric::from_real(0.5f64.sin()));
+    assert_eq!(half.atan(), Numeric::from_real(0.5f64.atan()));
+    assert_eq!(two.tanh(), Numeric::from_real(2f64.tanh()));
+    assert_eq!(Numeric::from_integer(100).log(&Numeric::from_integer(10)), Numeric::from_integer(2));
+    assert_close(&Numeric::from_integer(1000).log(&Numeric::from_real(10.)), &Numeric::from_real(3.));
+    assert_close(&Numeric::from_integer(-1).ln(), &Numeric::from_complex(Complex::new(0., pi)));
+    assert!(two.asin().is_complex());
+    assert_close(&two.asin().sin(), &two);
+    assert_close(&two.acos().cos(), &two);
+    assert_close(&i.sin(), &Numeric::from_complex(Complex::new(0., 1f64.sinh())));
+    assert_close(&i.cosh(), &Numeric::from_real(1f64.cos()));
+    assert_close(&Numeric::one().atan2(&Numeric::from_integer(-1)), &Numeric::from_real(3. * pi / 4.));
+    assert!(i.atan2(&Numeric::one()).is_nan());
+    assert_close(&Numeric::from_integer(-8).powf(1. / 3.), &Numeric::from_complex(Complex::new(1., 3f64.sqrt())));
+    assert_eq!(Numeric::from_integer(2).powf(0.5), Numeric::from_real(2f64.sqrt()));
+
+    assert_eq!(Numeric::zero().checked_ln(), Err(NumericError::Domain("logarithm of zero")));
+    assert_eq!(two.checked_log(&Numeric::one()), Err(NumericError::Domain("logarithm to base zero or one")));
+    assert_eq!(Numeric::from_integer(1000).checked_apply(Numeric::cosh), Err(NumericError::Overflow));
+    assert_eq!(i.checked_atan2(&two), Err(NumericError::Domain("atan2 of a complex number")));
+}
+
+#[test]
+fn test_big_float_operations() {
+    let third = Numeric::from_rational(Rational::new(1, 3));
+    let float = |string: &str| Numeric::from_big_float(string.parse().unwrap());
+
+    assert_eq!((third.clone() + float("1")).level(), NumericLevel::BigFloat);
+    assert_eq!(third.clone() * float("3"), float("0.99999999999999999999999999999999999999999999999999"));
+    assert_eq!(Numeric::from_real(0.5) + float("0.25"), float("0.75"));
+    assert_eq!((float("1") + Numeric::from_complex(Complex::i())).level(), NumericLevel::Complex);
+    assert_eq!(float("2").pow(10), float("1024"));
+    assert_eq!(third.compare(&float("0.3")), Some(Ordering::Greater));
+    assert_eq!(float("1").checked_div(float("0")), Err(NumericError::DivisionByZero));
+    assert_eq!(float("2").sqrt(), float("1.4142135623730950488016887242096980785696718753769"));
+    assert!(float("-2").sqrt().is_complex());
+    assert_eq!(float("4").pow_numeric(&float("0.5")), float("2"));
+
+    // Exact arguments only use big floats while a precision is set
+    assert_eq!(Numeric::from_integer(2).sqrt().level(), NumericLevel::Real);
+    bigfloat::set_precision(Some(30));
+    assert_eq!(Numeric::from_integer(2).sqrt(), float("1.41421356237309504880168872421"));
+    assert_eq!(Numeric::one().exp(), float("2.71828182845904523536028747135"));
+    assert_eq!(Numeric::from_integer(10).ln(), float("2.30258509299404568401799145468"));
+    assert_eq!(Numeric::from_integer(4).sqrt(), Numeric::from_integer(2));
+    assert_eq!(Numeric::zero().sin(), Numeric::zero());
+    assert_eq!(Numeric::from_real(0.5).sin(), Numeric::from_real(0.5f64.sin()));
+    assert_eq!(Numeric::one().atan(), float("0.78539816339744830961566084582"));
+    assert_eq!(Numeric::from_rational(Rational::new(1, 4)).asin(), float("0.252680255142078653485657436994"));
+    assert_eq!(Numeric::from_rational(Rational::new(1, 2)).acos(), float("1.04719755119659774615421446109"));
+    assert_eq!(Numeric::one().sinh(), float("1.1752011936438014568823818506"));
+    assert_eq!(Numeric::one().cosh(), float("1.54308063481524377847790562076"));
+    assert_eq!(Numeric::one().tanh(), float("0.761594155955764888119458282605"));
+    assert!(Numeric::from_integer(2).asin().is_complex());
+    bigfloat::set_precision(None);
+}
+
+#[test]
+fn test_floored_remainder() {
+    let int = Numeric::from_integer;
+
+    assert_eq!(int(7) % int(3), int(1));
+    assert_eq!(int(-7) % int(3), int(2));
+    assert_eq!(int(7) % int(-3), int(-2));
+    assert_eq!(int(IntegerType::MIN) % int(-1), int(0));
+    assert_eq!(int(2).pow(100) % int(7), int(2));
+    assert_eq!(Numeric::from_rational(Rational::new(7, 2)) % int(2), Numeric::from_rational(Rational::new(3, 2)));
+    assert_eq!(Numeric::from_real(-0.5) % int(2), Numeric::from_real(1.5));
+    assert_eq!(int(1).checked_rem(int(0)), Err(NumericError::DivisionByZero));
+    assert!(Numeric::from_complex(Complex::i()).checked_rem(int(2)).is_err());
+}
@@ -0,0 +1,301 @@
+#![allow(dead_code)]
+
+use bigint::BigInt;
+use numeric::{Numeric, NumericError};
+
+// Number theory on the integers of the numeric tower. Arguments that are
+// not integers give a domain error, results are normalized Numerics.
+
+fn integer_argument(value: &Numeric) -> Result<BigInt, NumericError> {
+    match value.to_big_integer_exact() {
+        Some(integer) => Ok(integer),
+        None => Err(NumericError::Domain("number theory expects integer arguments"))
+    }
+}
+
+fn modulus_argument(value: &Numeric) -> Result<BigInt, NumericError> {
+    let modulus = integer_argument(value)?;
+    if modulus.is_zero() {
+        return Err(NumericError::DivisionByZero)
+    }
+    if modulus.is_negative() {
+        return Err(NumericError::Domain("modulus must be positive"))
+    }
+    Ok(modulus)
+}
+
+// Non-negative remainder of integer division by a positive modulus
+fn reduce(value: &BigInt, modulus: &BigInt) -> BigInt {
+    value.div_mod_floor(modulus).1
+}
+
+// Returns (g, x, y) with g = gcd(a, b) = a x + b y and g non-negative
+fn big_extended_gcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut old_r, mut r) = (a.clone(), b.clone());
+    let (mut old_x, mut x) = (BigInt::one(), BigInt::zero());
+    let (mut old_y, mut y) = (BigInt::zero(), BigInt::one());
+    while !r.is_zero() {
+        let (quotient, remainder) = old_r.divrem(&r);
+        old_r = ::std::mem::replace(&mut r, remainder);
+        let next_x = old_x - quotient.clone() * x.clone();
+        old_x = ::std::mem::replace(&mut x, next_x);
+        let next_y = old_y - quotient * y.clone();
+        old_y = ::std::mem::replace(&mut y, next_y);
+    }
+    if old_r.is_negative() {
+        return (-old_r, -old_x, -old_y)
+    }
+    (old_r, old_x, old_y)
+}
+
+fn big_mod_pow(base: &BigInt, exponent: &BigInt, modulus: &BigInt) -> BigInt {
+    let mut result = reduce(&BigInt::one(), modulus);
+    let mut base = reduce(base, modulus);
+    let mut exponent = exponent.clone();
+    while !exponent.is_zero() {
+        if !exponent.is_even() {
+            result = reduce(&(result * base.clone()), modulus);
+        }
+        exponent = exponent.shr(1);
+        if !exponent.is_zero() {
+            base = reduce(&(base.clone() * base), modulus);
+        }
+    }
+    result
+}
+
+const SMALL_PRIMES: [i64; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+// Miller-Rabin with the first thirteen primes as witnesses, which is
+// deterministic below 3.3 * 10^24 and a strong probable prime test above
+fn big_is_prime(n: &BigInt) -> bool {
+    if *n < BigInt::from(2) {
+        return false
+    }
+    for prime in SMALL_PRIMES.iter() {
+        let prime = BigInt::from(*prime);
+        if *n == prime {
+            return true
+        }
+        if reduce(n, &prime).is_zero() {
+            return false
+        }
+    }
+    let one = BigInt::one();
+    let n_minus_one = n.clone() - one.clone();
+    let mut odd_part = n_minus_one.clone();
+    let mut twos = 0;
+    while odd_part.is_even() {
+        odd_part = odd_part.shr(1);
+        twos += 1;
+    }
+    'witness: for witness in SMALL_PRIMES.iter() {
+        let mut x = big_mod_pow(&BigInt::from(*witness), &odd_part, n);
+        if x == one || x == n_minus_one {
+            continue;
+        }
+        for _ in 1..twos {
+            x = reduce(&(x.clone() * x), n);
+            if x == n_minus_one {
+                continue 'witness;
+            }
+        }
+        return false
+    }
+    true
+}
+
+// Pollard's rho with Floyd cycle detection, returns a non-trivial factor
+// of an odd composite n
+fn pollard_rho(n: &BigInt) -> BigInt {
+    let one = BigInt::one();
+    let mut increment = BigInt::one();
+    loop {
+        let step = |x: &BigInt| reduce(&(x.clone() * x.clone() + increment.clone()), n);
+        let mut tortoise = BigInt::from(2);
+        let mut hare = BigInt::from(2);
+        let mut divisor = one.clone();
+        while divisor == one {
+            tortoise = step(&tortoise);
+            hare = step(&step(&hare));
+            divisor = (tortoise.clone() - hare.clone()).gcd(n);
+        }
+        if divisor != *n {
+            return divisor
+        }
+        increment = increment + one.clone();
+    }
+}
+
+// Prime factors of n > 1 with repetition, in no particular order
+fn collect_factors(n: BigInt, factors: &mut Vec<BigInt>) {
+    if n.is_one() {
+        return
+    }
+    if big_is_prime(&n) {
+        factors.push(n);
+        return
+    }
+    let divisor = pollard_rho(&n);
+    let cofactor = n / divisor.clone();
+    collect_factors(divisor, factors);
+    collect_factors(cofactor, factors);
+}
+
+// Trial division by small numbers, then Pollard's rho for what remains
+fn big_factorize(n: &BigInt) -> Vec<(BigInt, u32)> {
+    let mut remaining = n.abs();
+    let mut factors: Vec<BigInt> = Vec::new();
+    let mut divisor = BigInt::from(2);
+    let limit = BigInt::from(1000);
+    while divisor <= limit && divisor.clone() * divisor.clone() <= remaining {
+        loop {
+            let (quotient, remainder) = remaining.divrem(&divisor);
+            if !remainder.is_zero() {
+                break;
+            }
+            factors.push(divisor.clone());
+            remaining = quotient;
+        }
+        divisor = divisor + BigInt::one();
+    }
+    collect_factors(remaining, &mut factors);
+    factors.sort();
+
+    let mut result: Vec<(BigInt, u32)> = Vec::new();
+    for factor in factors {
+        match result.last_mut() {
+            Some(last) if last.0 == factor => last.1 += 1,
+            _ => result.push((factor, 1))
+        }
+    }
+    result
+}
+
+pub fn gcd(a: &Numeric, b: &Numeric) -> Result<Numeric, NumericError> {
+    Ok(Numeric::from_big_integer(integer_argument(a)?.gcd(&integer_argument(b)?)))
+}
+
+// Non-negative least common multiple, lcm(a, 0) = 0
+pub fn lcm(a: &Numeric, b: &Numeric) -> Result<Numeric, NumericError> {
+    let (a, b) = (integer_argument(a)?, integer_argument(b)?);
+    if a.is_zero() || b.is_zero() {
+        return Ok(Numeric::zero())
+    }
+    let divisor = a.gcd(&b);
+    Ok(Numeric::from_big_integer((a / divisor * b).abs()))
+}
+
+// Bezout coefficients, returns (g, x, y) with g = gcd(a, b) = a x + b y
+pub fn extended_gcd(a: &Numeric, b: &Numeric) -> Result<(Numeric, Numeric, Numeric), NumericError> {
+    let (g, x, y) = big_extended_gcd(&integer_argument(a)?, &integer_argument(b)?);
+    Ok((Numeric::from_big_integer(g), Numeric::from_big_integer(x), Numeric::from_big_integer(y)))
+}
+
+// Inverse of a modulo n in [0, n)
+pub fn mod_inverse(a: &Numeric, n: &Numeric) -> Result<Numeric, NumericError> {
+    let modulus = modulus_argument(n)?;
+    let (g, x, _) = big_extended_gcd(&integer_argument(a)?, &modulus);
+    if !g.is_one() {
+        return Err(NumericError::Domain("no modular inverse, arguments are not coprime"))
+    }
+    Ok(Numeric::from_big_integer(reduce(&x, &modulus)))
+}
+
+// base^exponent mod n in [0, n), negative exponents use the modular inverse
+pub fn mod_pow(base: &Numeric, exponent: &Numeric, n: &Numeric) -> Result<Numeric, NumericError> {
+    let modulus = modulus_argument(n)?;
+    let exponent = integer_argument(exponent)?;
+    let base = if exponent.is_negative() {
+        integer_argument(&mod_inverse(base, n)?)?
+    } else {
+        integer_argument(base)?
+    };
+    Ok(Numeric::from_big_integer(big_mod_pow(&base, &exponent.abs(), &modulus)))
+}
+
+pub fn is_prime(n: &Numeric) -> Result<bool, NumericError> {
+    Ok(big_is_prime(&integer_argument(n)?))
+}
+
+// Prime factorization as (prime, multiplicity) pairs in increasing order.
+// Negative numbers get a leading (-1, 1) factor.
+pub fn factorize(n: &Numeric) -> Result<Vec<(Numeric, u32)>, NumericError> {
+    let n = integer_argument(n)?;
+    if n.is_zero() {
+        return Err(NumericError::Domain("factorization of zero"))
+    }
+    let mut factors: Vec<(Numeric, u32)> = Vec::new();
+    if n.is_negative() {
+        factors.push((Numeric::from_integer(-1), 1));
+    }
+    for (prime, multiplicity) in big_factorize(&n) {
+        factors.push((Numeric::from_big_integer(prime), multiplicity));
+    }
+    Ok(factors)
+}
+
+// Euler's totient, the count of integers in [1, n] coprime to n
+pub fn totient(n: &Numeric) -> Result<Numeric, NumericError> {
+    let value = integer_argument(n)?;
+    if value.is_zero() || value.is_negative() {
+        return Err(NumericError::Domain("totient of a non-positive integer"))
+    }
+    let mut result = value.clone();
+    for (prime, _) in big_factorize(&value) {
+        result = result / prime.clone() * (prime - BigInt::one());
+    }
+    Ok(Numeric::from_big_integer(result))
+}
+
+#[cfg(test)]
+fn int(value: i64) -> Numeric {
+    Numeric::from_integer(value)
+}
+
+#[test]
+fn test_gcd_lcm_and_inverses() {
+    assert_eq!(gcd(&int(12), &int(-18)), Ok(int(6)));
+    assert_eq!(gcd(&int(0), &int(0)), Ok(int(0)));
+    assert_eq!(lcm(&int(4), &int(-6)), Ok(int(12)));
+    assert_eq!(lcm(&int(4), &int(0)), Ok(int(0)));
+    assert_eq!(extended_gcd(&int(240), &int(46)), Ok((int(2), int(-9), int(47))));
+    assert_eq!(mod_inverse(&int(3), &int(11)), Ok(int(4)));
+    assert_eq!(mod_inverse(&int(-3), &int(11)), Ok(int(7)));
+    assert!(mod_inverse(&int(6), &int(9)).is_err());
+    assert_eq!(mod_inverse(&int(3), &int(0)), Err(NumericError::DivisionByZero));
+    assert!(gcd(&Numeric::from_real(1.5), &int(3)).is_err());
+}
+
+#[test]
+fn test_modular_exponentiation() {
+    assert_eq!(mod_pow(&int(4), &int(13), &int(497)), Ok(int(445)));
+    assert_eq!(mod_pow(&int(3), &int(-1), &int(11)), Ok(int(4)));
+    assert_eq!(mod_pow(&int(5), &int(0), &int(1)), Ok(int(0)));
+    assert_eq!(mod_pow(&int(-2), &int(3), &int(5)), Ok(int(2)));
+    // Fermat's little theorem for the Mersenne prime 2^127 - 1
+    let mersenne = int(2).pow(127) - int(1);
+    assert_eq!(mod_pow(&int(3), &(mersenne.clone() - int(1)), &mersenne), Ok(int(1)));
+}
+
+#[test]
+fn test_primality_and_factorization() {
+    let primes: Vec<i64> = (0..60).filter(|n| is_prime(&int(*n)).unwrap()).collect();
+    assert_eq!(primes, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59]);
+    assert_eq!(is_prime(&int(561)), Ok(false));
+    assert_eq!(is_prime(&(int(2).pow(127) - int(1))), Ok(true));
+    assert_eq!(is_prime(&(int(2).pow(128) + int(1))), Ok(false));
+
+    assert_eq!(factorize(&int(360)), Ok(vec![(int(2), 3), (int(3), 2), (int(5), 1)]));
+    assert_eq!(factorize(&int(-7)), Ok(vec![(int(-1), 1), (int(7), 1)]));
+    assert_eq!(factorize(&int(1)), Ok(vec![]));
+    assert!(factorize(&int(0)).is_err());
+    // Product of two primes beyond the reach of trial division
+    assert_eq!(factorize(&int(1000003 * 998244353)), Ok(vec![(int(1000003), 1), (int(998244353), 1)]));
+    assert_eq!(factorize(&(int(2).pow(64) + int(1))), Ok(vec![(int(274177), 1), (int(67280421310721), 1)]));
+
+    assert_eq!(totient(&int(1)), Ok(int(1)));
+    assert_eq!(totient(&int(36)), Ok(int(12)));
+    assert_eq!(totient(&int(97)), Ok(int(96)));
+    assert!(totient(&int(0)).is_err());
+}
@@ -0,0 +1,347 @@
+use lexer;
+use lexer::{Token, TermToken, OperatorType, Span, SpannedTokens};
+use numeric::Numeric;
+use symexpr_rc::UnaryFunction;
+use std::fmt;
+
+#[derive(Clone, PartialEq)]
+pub enum AstKind {
+    Number(Numeric),
+    Variable(String),
+    Neg(Box<Ast>),
+    Binary(OperatorType, Box<Ast>, Box<Ast>),
+    Call(String, Vec<Ast>),
+    // name = value, only at the top level
+    Assignment(String, Box<Ast>),
+    // f(x, y) = body, only at the top level
+    Definition(String, Vec<String>, Box<Ast>)
+}
+
+// Every node keeps the span of the input it was parsed from
+#[derive(Clone, PartialEq)]
+pub struct Ast {
+    pub kind: AstKind,
+    pub span: Span
+}
+
+impl Ast {
+    fn new(kind: AstKind, span: Span) -> Ast {
+        Ast { kind, span }
+    }
+}
+
+// Prints the tree as an s-expression, (+ x (* 2 y))
+impl fmt::Debug for Ast {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            AstKind::Number(ref number) => write!(f, "{:?}", number),
+            AstKind::Variable(ref name) => write!(f, "{}", name),
+            AstKind::Neg(ref argument) => write!(f, "(neg {:?})", argument),
+            AstKind::Binary(op, ref lhs, ref rhs) => {
+                write!(f, "({} {:?} {:?})", op.symbol(), lhs, rhs)
+            },
+            AstKind::Call(ref name, ref arguments) => {
+                write!(f, "({}", name)?;
+                for argument in arguments.iter() {
+                    write!(f, " {:?}", argument)?;
+                }
+                write!(f, ")")
+            },
+            AstKind::Assignment(ref name, ref value) => write!(f, "(= {} {:?})", name, value),
+            AstKind::Definition(ref name, ref parameters, ref body) => {
+                write!(f, "(define ({} {}) {:?})", name, parameters.join(" "), body)
+            }
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ParseError {
+    pub message: String,
+    pub span: Span
+}
+
+impl ParseError {
+    pub fn new(message: String, span: Span) -> ParseError {
+        ParseError { message, span }
+    }
+
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// Binding powers of the infix operators, a left power above the right one
+// makes the operator right associative
+fn infix_binding_power(op: OperatorType) -> Option<(u8, u8)> {
+    match op {
+        OperatorType::Add | OperatorType::Sub => Some((1, 2)),
+        OperatorType::Mul | OperatorType::Div | OperatorType::Mod => Some((3, 4)),
+        OperatorType::Pow => Some((7, 6)),
+        _ => None
+    }
+}
+
+// Prefix minus binds tighter than * but looser than ^, so -x^2 = -(x^2)
+const NEG_BINDING_POWER: u8 = 5;
+
+// Deepest nesting of parentheses, prefix minuses and powers parsed, deeper
+// input is an error rather than a stack overflow
+const MAX_DEPTH: usize = 256;
+
+struct Parser<'a> {
+    tokens: &'a SpannedTokens,
+    position: usize,
+    // Empty span at the end of the input
+    end: Span,
+    // Number of expressions being parsed
+    depth: usize
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a (Token, Span)> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&'a (Token, Span)> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn next_is(&self, op: OperatorType) -> bool {
+        match self.peek() {
+            Some((Token::Operator(operator), _)) => operator.op == op,
+            _ => false
+        }
+    }
+
+    fn statement(&mut self) -> Result<Ast, ParseError> {
+        let target = self.expression(0)?;
+        if !self.next_is(OperatorType::Assignment) {
+            return Ok(target)
+        }
+        self.next();
+        let value = self.expression(0)?;
+        let span = target.span.to(value.span);
+        match target.kind {
+            AstKind::Variable(name) => return Ok(Ast::new(AstKind::Assignment(name, Box::new(value)), span)),
+            AstKind::Call(name, arguments) => {
+                let mut parameters: Vec<String> = Vec::new();
+                for argument in arguments.into_iter() {
+                    match argument.kind {
+                        AstKind::Variable(parameter) => parameters.push(parameter),
+                        _ => return Err(ParseError::new("expected a parameter name".to_string(), argument.span))
+                    }
+                }
+                return Ok(Ast::new(AstKind::Definition(name, parameters, Box::new(value)), span))
+            },
+            _ => return Err(ParseError::new("cannot assign to this expression".to_string(), target.span))
+        }
+    }
+
+    fn expression(&mut self, min_binding_power: u8) -> Result<Ast, ParseError> {
+        if self.depth == MAX_DEPTH {
+            let span = match self.peek() {
+                Some((_token, span)) => *span,
+                None => self.end
+            };
+            return Err(ParseError::new("expression nested too deeply".to_string(), span))
+        }
+        self.depth += 1;
+        let expression = self.infix_expression(min_binding_power);
+        self.depth -= 1;
+        return expression
+    }
+
+    fn infix_expression(&mut self, min_binding_power: u8) -> Result<Ast, ParseError> {
+        let mut lhs = self.operand()?;
+        while let Some((Token::Operator(operator), span)) = self.peek() {
+            let (op, span) = (operator.op, *span);
+            let (left, right) = match infix_binding_power(op) {
+                Some(powers) => powers,
+                None => break
+            };
+            if left < min_binding_power {
+                break;
+            }
+            self.next();
+            let rhs = match self.expression(right) {
+                Ok(rhs) => rhs,
+                Err(ref error) if error.span == self.end => {
+                    return Err(ParseError::new(format!("missing right operand of {}", op.symbol()), span))
+                },
+                Err(error) => return Err(error)
+            };
+            let span = lhs.span.to(rhs.span);
+            lhs = Ast::new(AstKind::Binary(op, Box::new(lhs), Box::new(rhs)), span);
+        }
+        return Ok(lhs)
+    }
+
+    fn operand(&mut self) -> Result<Ast, ParseError> {
+        let (token, span) = match self.next() {
+            Some((token, span)) => (token, *span),
+            None => return Err(ParseError::new("expected an operand".to_string(), self.end))
+        };
+        match token {
+            Token::Term(TermToken::Number(number)) => return Ok(Ast::new(AstKind::Number(number.clone()), span)),
+            Token::Term(TermToken::VariableKey(name)) => return Ok(Ast::new(AstKind::Variable(name.clone()), span)),
+            Token::Operator(operator) if operator.op == OperatorType::Neg => {
+                let argument = self.expression(NEG_BINDING_POWER)?;
+                let span = span.to(argument.span);
+                return Ok(Ast::new(AstKind::Neg(Box::new(argument)), span))
+            },
+            Token::Operator(operator) if operator.op == OperatorType::LeftP => {
+                let inner = self.expression(0)?;
+                let close = self.closing_parenthesis(span)?;
+                return Ok(Ast::new(inner.kind, span.to(close)))
+            },
+            Token::Function(name) => {
+                let open = match self.next() {
+                    Some((token, open)) if token.is_left_parenthesis() => *open,
+                    _ => return Err(ParseError::new(format!("expected ( after {}", name), span))
+                };
+                let mut arguments: Vec<Ast> = Vec::new();
+                if !self.next_is(OperatorType::RightP) {
+                    loop {
+                        arguments.push(self.expression(0)?);
+                        if !self.next_is(OperatorType::Comma) {
+                            break;
+                        }
+                        self.next();
+                    }
+                }
+                let close = self.closing_parenthesis(open)?;
+                return Ok(Ast::new(AstKind::Call(name.clone(), arguments), span.to(close)))
+            },
+            _ => return Err(ParseError::new(format!("expected an operand before {:?}", token), span))
+        }
+    }
+
+    fn closing_parenthesis(&mut self, open: Span) -> Result<Span, ParseError> {
+        match self.peek() {
+            Some((token, close)) if token.is_right_parenthesis() => {
+                self.next();
+                return Ok(*close)
+            },
+            Some((_token, span)) => return Err(ParseError::new("expected )".to_string(), *span)),
+            None => return Err(ParseError::new("unclosed parenthesis".to_string(), open))
+        }
+    }
+}
+
+// Parses a statement: an expression, an assignment x = value or a
+// function definition f(x, y) = body
+pub fn parse_tokens(tokens: &SpannedTokens, input: &String) -> Result<Ast, ParseError> {
+    let mut parser = Parser { tokens, position: 0, end: Span::new(input.len(), input.len()), depth: 0 };
+    if parser.peek().is_none() {
+        return Err(ParseError::new("empty input".to_string(), parser.end))
+    }
+    let ast = parser.statement()?;
+    match parser.peek() {
+        None => return Ok(ast),
+        Some((token, span)) if token.is_right_parenthesis() => {
+            return Err(ParseError::new("unmatched )".to_string(), *span))
+        },
+        Some((token, span)) if token.is_operator() => {
+            return Err(ParseError::new(format!("unexpected {:?}", token), *span))
+        },
+        Some((_token, span)) => return Err(ParseError::new("expected an operator".to_string(), *span))
+    }
+}
+
+// An identifier directly followed by a parenthesis is a call if is_call
+// accepts its name
+pub fn parse_string_with(input: &String, is_call: &dyn Fn(&str) -> bool) -> Result<Ast, ParseError> {
+    match lexer::tokenize_spanned_with(input, is_call) {
+        Ok(tokens) => return parse_tokens(&tokens, input),
+        Err(error) => return Err(ParseError::new(error.to_string(), error.span))
+    }
+}
+
+// Parses with only the built-in functions recognized as calls
+pub fn parse_string(input: &String) -> Result<Ast, ParseError> {
+    parse_string_with(input, &|name: &str| UnaryFunction::from_name(name).is_some())
+}
+
+#[cfg(test)]
+fn sexpr(input: &str) -> String {
+    match parse_string(&String::from(input)) {
+        Ok(ast) => format!("{:?}", ast),
+        Err(error) => panic!("{} in {}", error, input)
+    }
+}
+
+#[cfg(test)]
+fn error_in(input: &str) -> (String, usize, usize) {
+    match parse_string(&String::from(input)) {
+        Ok(ast) => panic!("{} parsed as {:?}", input, ast),
+        Err(error) => (error.message, error.span.start, error.span.end)
+    }
+}
+
+#[test]
+fn test_precedence_and_associativity() {
+    assert_eq!(sexpr("2"), "2");
+    assert_eq!(sexpr("20 + x"), "(+ 20 x)");
+    assert_eq!(sexpr("a((123) + x)"), "(* a (+ 123 x))");
+    assert_eq!(sexpr("2 + 3(1 + x)"), "(+ 2 (* 3 (+ 1 x)))");
+    assert_eq!(sexpr("2 * 7 % 4"), "(% (* 2 7) 4)");
+    assert_eq!(sexpr("x - 1 - y"), "(- (- x 1) y)");
+    assert_eq!(sexpr("x / 2 * y"), "(* (/ x 2) y)");
+    assert_eq!(sexpr("2^3^2"), "(^ 2 (^ 3 2))");
+    assert_eq!(sexpr("-x^2"), "(neg (^ x 2))");
+    assert_eq!(sexpr("-x * y"), "(* (neg x) y)");
+    assert_eq!(sexpr("2^-x"), "(^ 2 (neg x))");
+    assert_eq!(sexpr("a * (-b - -c)"), "(* a (- (neg b) (neg c)))");
+}
+
+#[test]
+fn test_calls_and_statements() {
+    let parse_with = |input: &str, names: &[&str]| {
+        format!("{:?}", parse_string_with(&String::from(input), &|name: &str| names.contains(&name)).unwrap())
+    };
+
+    assert_eq!(sexpr("sin(x)"), "(sin x)");
+    assert_eq!(sexpr("2 exp(-x^2)"), "(* 2 (exp (neg (^ x 2))))");
+    assert_eq!(sexpr("-sin(x + 1) * 2"), "(* (neg (sin (+ x 1))) 2)");
+    assert_eq!(sexpr("cos(sin(x))^2"), "(^ (cos (sin x)) 2)");
+    assert_eq!(sexpr("2x + y(x)"), "(+ (* 2 x) (* y x))");
+    assert_eq!(parse_with("f(x, y + 1, (2))", &["f"]), "(f x (+ y 1) 2)");
+    assert_eq!(parse_with("g() + f(g(1), 2)", &["f", "g"]), "(+ (g) (f (g 1) 2))");
+    assert_eq!(sexpr("y = 1 + 2"), "(= y (+ 1 2))");
+    assert_eq!(parse_with("f(x, y) = x * y", &["f"]), "(define (f x y) (* x y))");
+
+    let ast = parse_string(&String::from("x + sin(y)")).unwrap();
+    assert_eq!((ast.span.start, ast.span.end), (0, 10));
+    match ast.kind {
+        AstKind::Binary(_, _, ref rhs) => assert_eq!((rhs.span.start, rhs.span.end), (4, 10)),
+        _ => panic!("expected a sum")
+    }
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(error_in("+"), ("expected an operand before +".to_string(), 0, 1));
+    assert_eq!(error_in("2 *"), ("missing right operand of *".to_string(), 2, 3));
+    assert_eq!(error_in("(x + 1"), ("unclosed parenthesis".to_string(), 0, 1));
+    assert_eq!(error_in("x + 1)"), ("unmatched )".to_string(), 5, 6));
+    assert_eq!(error_in("1 2"), ("expected an operator".to_string(), 2, 3));
+    assert_eq!(error_in("sin(x, )"), ("expected an operand before )".to_string(), 7, 8));
+    assert_eq!(error_in("2 = 3"), ("cannot assign to this expression".to_string(), 0, 1));
+    assert_eq!(error_in("x = y = 1"), ("unexpected =".to_string(), 6, 7));
+    assert_eq!(error_in("   "), ("empty input".to_string(), 3, 3));
+    // Deep nesting is an error where it gets too deep
+    let nested = "(".repeat(5000) + "x" + &")".repeat(5000);
+    assert_eq!(error_in(&nested), ("expression nested too deeply".to_string(), 256, 257));
+    assert_eq!(error_in(&"-".repeat(5000)).0, "expression nested too deeply");
+    assert_eq!(error_in(&"2^".repeat(5000)).0, "expression nested too deeply");
+    assert_eq!(sexpr(&("(".repeat(200) + "x" + &")".repeat(200))), "x");
+
+    let error = parse_string(&String::from("θ * * 2\n")).unwrap_err();
+    assert_eq!(error.span.underline("θ * * 2\n"), "θ * * 2\n    ^");
+}
@@ -0,0 +1,295 @@
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+use std::rc::Rc;
+
+use symexpr_rc::{Expr, NaryFunction, Builder, HeapBuilder};
+
+// Subexpressions matched by the pattern variables, by variable key
+pub type Bindings = HashMap<usize, Rc<Expr>>;
+
+// An expression whose variables with keys in variables match any
+// subexpression. Sums and products match whatever the order of their
+// operands, and the last pattern variable among the operands takes all
+// the operands left over, so a + sin(b) matches x + y + sin(z) with
+// a = x + y and b = z.
+pub struct Pattern {
+    expr: Rc<Expr>,
+    variables: HashSet<usize>
+}
+
+impl Pattern {
+    pub fn new(expr: &Rc<Expr>, variables: HashSet<usize>) -> Pattern {
+        Pattern { expr: Rc::clone(expr), variables }
+    }
+
+    pub fn expr(&self) -> &Rc<Expr> {
+        &self.expr
+    }
+
+    pub fn is_variable(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::IndepVar(key) => return self.variables.contains(key),
+            _ => return false
+        }
+    }
+
+    // Bindings under which the pattern equals expr, None if it does not match
+    pub fn matches(&self, expr: &Rc<Expr>) -> Option<Bindings> {
+        self.match_node(&self.expr, expr, Bindings::new(), &mut Some)
+    }
+
+    // Bindings under which a sum or product pattern equals some of the
+    // operands of the sum or product expr, with the operands left over
+    pub fn matches_part(&self, expr: &Rc<Expr>) -> Option<(Bindings, Vec<Rc<Expr>>)> {
+        let (function, patterns) = nary_parts(&self.expr)?;
+        let (expr_function, operands) = nary_parts(expr)?;
+        if function != expr_function || patterns.len() >= operands.len() {
+            return None
+        }
+        return self.match_operands(&self.ordered(patterns), operands.clone(), Bindings::new(), &mut |bindings, left| Some((bindings, left)))
+    }
+
+    // Matches pattern against expr consistently with bindings, and hands
+    // the bindings to then. Other ways of matching sums and products are
+    // tried while then fails, the first result it gives is returned.
+    fn match_node<T>(&self, pattern: &Rc<Expr>, expr: &Rc<Expr>, bindings: Bindings,
+                     then: &mut dyn FnMut(Bindings) -> Option<T>) -> Option<T> {
+        if let Expr::IndepVar(key) = pattern.deref() {
+            if self.variables.contains(key) {
+                return then(bind(bindings, *key, expr)?)
+            }
+        }
+        match (pattern.deref(), expr.deref()) {
+            (Expr::Unary(pattern), Expr::Unary(unary_expr)) if pattern.function() == unary_expr.function() => {
+                return self.match_node(pattern.argument(), unary_expr.argument(), bindings, then)
+            },
+            (Expr::Binary(pattern), Expr::Binary(binary_expr)) if pattern.function() == binary_expr.function() => {
+                return self.match_node(pattern.lhs(), binary_expr.lhs(), bindings,
+                                       &mut |bindings| self.match_node(pattern.rhs(), binary_expr.rhs(), bindings, then))
+            },
+            (Expr::Nary(pattern), Expr::Nary(nary_expr)) if pattern.function() == nary_expr.function() => {
+                return self.match_nary(pattern.function(), pattern.operands(), nary_expr.operands(), bindings, then)
+            },
+            _ if pattern == expr => return then(bindings),
+            _ => return None
+        }
+    }
+
+    // Matches all the operands, the last pattern variable takes the sum or
+    // product of the operands the others leave over
+    fn match_nary<T>(&self, function: &NaryFunction, patterns: &[Rc<Expr>], operands: &[Rc<Expr>], bindings: Bindings,
+                     then: &mut dyn FnMut(Bindings) -> Option<T>) -> Option<T> {
+        let mut patterns = self.ordered(patterns);
+        let last = match patterns.last().map(|last| last.deref()) {
+            Some(Expr::IndepVar(key)) if self.variables.contains(key) => Some(*key),
+            _ => None
+        };
+        if last.is_some() {
+            patterns.pop();
+        }
+        // Without a variable for the rest every operand needs a pattern
+        if patterns.len() > operands.len() || (last.is_none() && patterns.len() < operands.len()) {
+            return None
+        }
+        return self.match_operands(&patterns, operands.to_vec(), bindings, &mut |bindings, left| {
+            match last {
+                Some(key) if !left.is_empty() => {
+                    let rest = HeapBuilder::new().nary(function.clone(), left);
+                    return then(bind(bindings, key, &rest)?)
+                },
+                None if left.is_empty() => return then(bindings),
+                _ => return None
+            }
+        })
+    }
+
+    // Matches each pattern with a different operand, trying the operands
+    // in turn, and hands the bindings and the operands left over to then
+    fn match_operands<T>(&self, patterns: &[Rc<Expr>], operands: Vec<Rc<Expr>>, bindings: Bindings,
+                         then: &mut dyn FnMut(Bindings, Vec<Rc<Expr>>) -> Option<T>) -> Option<T> {
+        let (first, rest) = match patterns.split_first() {
+            Some(split) => split,
+            None => return then(bindings, operands)
+        };
+        if patterns.len() > operands.len() {
+            return None
+        }
+        for (i, operand) in operands.iter().enumerate() {
+            if !self.may_match(first, operand) {
+                continue;
+            }
+            let mut left = operands.clone();
+            left.remove(i);
+            let result = self.match_node(first, operand, bindings.clone(),
+                                         &mut |bindings| self.match_operands(rest, left.clone(), bindings, then));
+            if result.is_some() {
+                return result
+            }
+        }
+        return None
+    }
+
+    // Whether the roots of pattern and expr agree, so that pattern can
+    // match expr at all
+    fn may_match(&self, pattern: &Expr, expr: &Expr) -> bool {
+        match (pattern, expr) {
+            _ if self.is_variable(pattern) => return true,
+            (Expr::Unary(pattern), Expr::Unary(unary_expr)) => return pattern.function() == unary_expr.function(),
+            (Expr::Binary(pattern), Expr::Binary(binary_expr)) => return pattern.function() == binary_expr.function(),
+            (Expr::Nary(pattern), Expr::Nary(nary_expr)) => return pattern.function() == nary_expr.function(),
+            _ => return pattern == expr
+        }
+    }
+
+    // Pattern variables last, the other operands narrow down their choices
+    fn ordered(&self, patterns: &[Rc<Expr>]) -> Vec<Rc<Expr>> {
+        let (variables, others): (Vec<Rc<Expr>>, Vec<Rc<Expr>>) = patterns.iter()
+            .cloned()
+            .partition(|pattern| self.is_variable(pattern));
+        return others.into_iter().chain(variables).collect()
+    }
+}
+
+fn bind(mut bindings: Bindings, key: usize, expr: &Rc<Expr>) -> Option<Bindings> {
+    if let Some(bound) = bindings.get(&key) {
+        if bound != expr {
+            return None
+        }
+        return Some(bindings)
+    }
+    bindings.insert(key, Rc::clone(expr));
+    return Some(bindings)
+}
+
+fn nary_parts(expr: &Expr) -> Option<(&NaryFunction, &Vec<Rc<Expr>>)> {
+    match expr {
+        Expr::Nary(nary_expr) => return Some((nary_expr.function(), nary_expr.operands())),
+        _ => return None
+    }
+}
+
+// pattern -> replacement, where the variables of the pattern stand for the
+// subexpressions they matched
+pub struct RewriteRule {
+    pattern: Pattern,
+    replacement: Rc<Expr>
+}
+
+impl RewriteRule {
+    pub fn new(pattern: Pattern, replacement: &Rc<Expr>) -> RewriteRule {
+        RewriteRule { pattern, replacement: Rc::clone(replacement) }
+    }
+
+    pub fn pattern(&self) -> &Pattern {
+        &self.pattern
+    }
+
+    pub fn replacement(&self) -> &Rc<Expr> {
+        &self.replacement
+    }
+
+    // expr rewritten at its root, None if the rule does not apply. A sum
+    // or product with more operands than the pattern has the operands the
+    // pattern matches replaced, ln(a) + ln(b) -> ln(a b) rewrites
+    // ln(x) + ln(y) + z to ln(x y) + z.
+    pub fn apply(&self, expr: &Rc<Expr>) -> Option<Rc<Expr>> {
+        let rewritten = match self.pattern.matches(expr) {
+            Some(bindings) => Expr::substitute(&self.replacement, &bindings),
+            None => {
+                let (bindings, mut rest) = self.pattern.matches_part(expr)?;
+                let (function, _) = nary_parts(expr)?;
+                rest.push(Expr::substitute(&self.replacement, &bindings));
+                HeapBuilder::new().nary(function.clone(), rest)
+            }
+        };
+        let rewritten = Expr::cleaned(&rewritten);
+        if rewritten == *expr {
+            return None
+        }
+        return Some(rewritten)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_matching() {
+    use symexpr_rc::{add, mul, pow, apply};
+    use symexpr_rc::UnaryFunction;
+
+    // a and b are pattern variables, x, y and z are not
+    let a = Expr::from_key(0).clone_to_heap();
+    let b = Expr::from_key(1).clone_to_heap();
+    let x = Expr::from_key(2).clone_to_heap();
+    let y = Expr::from_key(3).clone_to_heap();
+    let z = Expr::from_key(4).clone_to_heap();
+    let two = Expr::from_integer(2).clone_to_heap();
+    let variables: HashSet<usize> = vec![0, 1].into_iter().collect();
+
+    // sin(a)^2 + cos(a)^2 whatever the order of the terms
+    let square = |function, argument: &Rc<Expr>| pow(&apply(function, argument), &two);
+    let identity = Pattern::new(&add(&square(UnaryFunction::Sin, &a), &square(UnaryFunction::Cos, &a)), variables.clone());
+    let xy = mul(&x, &y);
+    let bindings = identity.matches(&add(&square(UnaryFunction::Cos, &xy), &square(UnaryFunction::Sin, &xy))).unwrap();
+    assert_eq!(bindings[&0], xy);
+    assert_eq!(bindings.len(), 1);
+    // A variable matches the same subexpression everywhere
+    assert!(identity.matches(&add(&square(UnaryFunction::Sin, &x), &square(UnaryFunction::Cos, &y))).is_none());
+    // Left over terms only match in part
+    let longer = add(&z, &add(&square(UnaryFunction::Sin, &x), &square(UnaryFunction::Cos, &x)));
+    assert!(identity.matches(&longer).is_none());
+    let (bindings, rest) = identity.matches_part(&longer).unwrap();
+    assert_eq!(bindings[&0], x);
+    assert_eq!(rest, vec![Rc::clone(&z)]);
+
+    // The last variable of a product takes the factors left over
+    let scaled = Pattern::new(&mul(&a, &apply(UnaryFunction::Sin, &b)), variables.clone());
+    let bindings = scaled.matches(&mul(&two, &mul(&x, &apply(UnaryFunction::Sin, &y)))).unwrap();
+    assert_eq!(bindings[&0], mul(&two, &x));
+    assert_eq!(bindings[&1], y);
+    assert!(scaled.matches(&apply(UnaryFunction::Sin, &y)).is_none());
+}
+
+#[test]
+fn test_matching_wide_sums() {
+    use symexpr_rc::{sum, apply};
+    use symexpr_rc::UnaryFunction;
+
+    // Eight pattern variables against twenty terms stop at the first
+    // consistent choice instead of going through every arrangement
+    let variables: HashSet<usize> = (0..8).collect();
+    let pattern = Pattern::new(&sum((0..8).map(|key| Expr::from_key(key).clone_to_heap()).collect()), variables);
+    let terms: Vec<Rc<Expr>> = (8..28).map(|key| apply(UnaryFunction::Sin, &Expr::from_key(key).clone_to_heap())).collect();
+    let bindings = pattern.matches(&sum(terms.clone())).unwrap();
+    assert_eq!(bindings.len(), 8);
+
+    // Operands of another kind are never tried, sin(a) against twenty cosines
+    let variables: HashSet<usize> = vec![0, 1].into_iter().collect();
+    let a = Expr::from_key(0).clone_to_heap();
+    let b = Expr::from_key(1).clone_to_heap();
+    let pattern = Pattern::new(&sum(vec![apply(UnaryFunction::Sin, &a), apply(UnaryFunction::Tan, &a), b]), variables);
+    let cosines: Vec<Rc<Expr>> = terms.iter().map(|term| apply(UnaryFunction::Cos, term)).collect();
+    assert!(pattern.matches(&sum(cosines)).is_none());
+}
+
+#[test]
+fn test_rewrite_rules() {
+    use symexpr_rc::{add, mul, apply};
+    use symexpr_rc::UnaryFunction;
+
+    let a = Expr::from_key(0).clone_to_heap();
+    let b = Expr::from_key(1).clone_to_heap();
+    let x = Expr::from_key(2).clone_to_heap();
+    let y = Expr::from_key(3).clone_to_heap();
+    let z = Expr::from_key(4).clone_to_heap();
+    let variables: HashSet<usize> = vec![0, 1].into_iter().collect();
+    let ln = |argument: &Rc<Expr>| apply(UnaryFunction::Ln, argument);
+
+    // ln(a) + ln(b) -> ln(a b)
+    let rule = RewriteRule::new(Pattern::new(&add(&ln(&a), &ln(&b)), variables), &ln(&mul(&a, &b)));
+    assert_eq!(rule.apply(&add(&ln(&x), &ln(&y))), Some(ln(&mul(&x, &y))));
+    let rewritten = rule.apply(&add(&z, &add(&ln(&x), &ln(&y)))).unwrap();
+    assert_eq!(rewritten, add(&z, &ln(&mul(&x, &y))));
+    assert_eq!(rule.apply(&ln(&x)), None);
+}
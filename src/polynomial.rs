@@ -0,0 +1,589 @@
+#![allow(dead_code)]
+
+use std::collections::BTreeMap;
+use std::mem;
+use std::ops::{Add, Sub, Mul, Neg, Deref};
+use std::rc::Rc;
+
+use numeric::{Numeric, NumericError, NumericLevel};
+use symexpr_rc;
+use symexpr_rc::{Expr, UnaryFunction, BinaryFunction, NaryFunction};
+
+// Exponents of the variables of a polynomial, in the order of its variables
+pub type Monomial = Vec<u32>;
+
+// Largest degree of a polynomial converted from an expression
+pub const MAX_DEGREE: u32 = 1 << 10;
+
+// Sparse polynomial in the variables with the given keys, with exact
+// coefficients. Only the nonzero terms are stored, ordered
+// lexicographically by their monomials, so the last is the leading term.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Polynomial {
+    variables: Vec<usize>,
+    terms: BTreeMap<Monomial, Numeric>
+}
+
+impl Polynomial {
+    pub fn zero(variables: &[usize]) -> Polynomial {
+        Polynomial { variables: variables.to_vec(), terms: BTreeMap::new() }
+    }
+
+    // None if the coefficient is not exact
+    pub fn constant(variables: &[usize], coefficient: Numeric) -> Option<Polynomial> {
+        Polynomial::from_terms(variables, vec![(vec![0; variables.len()], coefficient)])
+    }
+
+    // The variable with key, None if it is not one of the variables
+    pub fn variable(variables: &[usize], key: usize) -> Option<Polynomial> {
+        let position = variables.iter().position(|variable| *variable == key)?;
+        let mut monomial = vec![0; variables.len()];
+        monomial[position] = 1;
+        return Polynomial::from_terms(variables, vec![(monomial, Numeric::one())])
+    }
+
+    // Sum of the terms, None if a coefficient is not exact or a monomial
+    // has the wrong number of exponents
+    pub fn from_terms(variables: &[usize], terms: Vec<(Monomial, Numeric)>) -> Option<Polynomial> {
+        let mut polynomial = Polynomial::zero(variables);
+        for (monomial, coefficient) in terms {
+            if monomial.len() != variables.len() || !is_exact(&coefficient) {
+                return None
+            }
+            polynomial.add_term(monomial, coefficient);
+        }
+        return Some(polynomial)
+    }
+
+    pub fn variables(&self) -> &Vec<usize> {
+        &self.variables
+    }
+
+    pub fn terms(&self) -> &BTreeMap<Monomial, Numeric> {
+        &self.terms
+    }
+
+    // Number of nonzero terms
+    pub fn term_count(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    pub fn coefficient(&self, monomial: &Monomial) -> Numeric {
+        self.terms.get(monomial).cloned().unwrap_or_else(Numeric::zero)
+    }
+
+    // Total degree, None for the zero polynomial
+    pub fn degree(&self) -> Option<u32> {
+        self.terms.keys().map(|monomial| monomial.iter().sum()).max()
+    }
+
+    // Degree in the variable with key, None for the zero polynomial or a
+    // key that is not one of the variables
+    pub fn degree_in(&self, key: usize) -> Option<u32> {
+        let position = self.variables.iter().position(|variable| *variable == key)?;
+        self.terms.keys().map(|monomial| monomial[position]).max()
+    }
+
+    // The term with the lexicographically largest monomial, None for the
+    // zero polynomial
+    pub fn leading_term(&self) -> Option<(&Monomial, &Numeric)> {
+        self.terms.iter().next_back()
+    }
+
+    pub fn scaled(&self, factor: &Numeric) -> Polynomial {
+        let mut result = Polynomial::zero(&self.variables);
+        for (monomial, coefficient) in self.terms.iter() {
+            result.add_term(monomial.clone(), coefficient.clone() * factor.clone());
+        }
+        return result
+    }
+
+    pub fn pow(&self, exponent: u32) -> Polynomial {
+        let mut result = Polynomial::constant(&self.variables, Numeric::one()).unwrap();
+        let mut square = self.clone();
+        let mut exponent = exponent;
+        while exponent > 0 {
+            if exponent % 2 == 1 {
+                result = &result * &square;
+            }
+            exponent /= 2;
+            if exponent > 0 {
+                square = &square * &square;
+            }
+        }
+        return result
+    }
+
+    // The polynomial over variables, which must contain its own
+    pub fn with_variables(&self, variables: &[usize]) -> Polynomial {
+        let positions: Vec<usize> = self.variables.iter()
+            .map(|key| variables.iter().position(|variable| variable == key).expect("missing variable"))
+            .collect();
+        let mut result = Polynomial::zero(variables);
+        for (monomial, coefficient) in self.terms.iter() {
+            let mut exponents = vec![0; variables.len()];
+            for (exponent, position) in monomial.iter().zip(positions.iter()) {
+                exponents[*position] = *exponent;
+            }
+            result.terms.insert(exponents, coefficient.clone());
+        }
+        return result
+    }
+
+    // expr as a polynomial in variables, None if it is not one. Sums,
+    // products, negations, natural powers and quotients by a number of
+    // polynomials are polynomials, as are exact numbers and the variables.
+    // Overflow if its degree would pass MAX_DEGREE.
+    pub fn from_expr(expr: &Expr, variables: &[usize]) -> Result<Option<Polynomial>, NumericError> {
+        match expr {
+            Expr::Numeric(number) => return Ok(Polynomial::constant(variables, number.clone())),
+            Expr::IndepVar(key) => return Ok(Polynomial::variable(variables, *key)),
+            Expr::Unary(unary_expr) if *unary_expr.function() == UnaryFunction::Neg => {
+                return Ok(Polynomial::from_expr(unary_expr.argument(), variables)?.map(|polynomial| -&polynomial))
+            },
+            Expr::Binary(binary_expr) => {
+                let lhs = match Polynomial::from_expr(binary_expr.lhs(), variables)? {
+                    Some(lhs) => lhs,
+                    None => return Ok(None)
+                };
+                match (binary_expr.function(), binary_expr.rhs().deref()) {
+                    (BinaryFunction::Pow, Expr::Numeric(Numeric::Integer(exponent))) if *exponent >= 0 => {
+                        let degree = lhs.degree().unwrap_or(0) as i64;
+                        if degree.saturating_mul(*exponent) > MAX_DEGREE as i64 {
+                            return Err(NumericError::Overflow)
+                        }
+                        return Ok(Some(lhs.pow(*exponent as u32)))
+                    },
+                    (BinaryFunction::Div, Expr::Numeric(divisor)) if is_exact(divisor) && !divisor.is_zero() => {
+                        return Ok(Some(lhs.scaled(&(Numeric::one() / divisor.clone()))))
+                    },
+                    _ => return Ok(None)
+                }
+            },
+            Expr::Nary(nary_expr) => {
+                let mut result: Option<Polynomial> = None;
+                for operand in nary_expr.operands().iter() {
+                    let operand = match Polynomial::from_expr(operand, variables)? {
+                        Some(operand) => operand,
+                        None => return Ok(None)
+                    };
+                    let combined = match (result, nary_expr.function()) {
+                        (None, _) => operand,
+                        (Some(sum), NaryFunction::Add) => &sum + &operand,
+                        (Some(product), NaryFunction::Mul) => {
+                            if product.degree().unwrap_or(0) + operand.degree().unwrap_or(0) > MAX_DEGREE {
+                                return Err(NumericError::Overflow)
+                            }
+                            &product * &operand
+                        }
+                    };
+                    result = Some(combined);
+                }
+                return Ok(result)
+            },
+            _ => return Ok(None)
+        }
+    }
+
+    // Whether expr is a polynomial in variables with exact coefficients
+    pub fn is_polynomial(expr: &Expr, variables: &[usize]) -> bool {
+        Polynomial::from_expr(expr, variables) != Ok(None)
+    }
+
+    // The canonical sum of the terms
+    pub fn to_expr(&self) -> Rc<Expr> {
+        let terms = self.terms.iter().map(|(monomial, coefficient)| {
+            let mut factors = vec![Expr::from_numeric(coefficient.clone()).move_to_heap()];
+            for (key, exponent) in self.variables.iter().zip(monomial.iter()) {
+                let variable = Expr::from_key(*key).move_to_heap();
+                match exponent {
+                    0 => {},
+                    1 => factors.push(variable),
+                    _ => factors.push(symexpr_rc::pow(&variable, &Expr::from_integer(*exponent as i64).move_to_heap()))
+                }
+            }
+            symexpr_rc::product(factors)
+        }).collect();
+        return symexpr_rc::sum(terms)
+    }
+
+    // Derivative with respect to the variable with key, zero if it is not
+    // one of the variables
+    pub fn derivative(&self, key: usize) -> Polynomial {
+        let mut result = Polynomial::zero(&self.variables);
+        let position = match self.variables.iter().position(|variable| *variable == key) {
+            Some(position) => position,
+            None => return result
+        };
+        for (monomial, coefficient) in self.terms.iter() {
+            let exponent = monomial[position];
+            if exponent > 0 {
+                let mut lowered = monomial.clone();
+                lowered[position] -= 1;
+                result.add_term(lowered, coefficient.clone() * Numeric::from_integer(exponent as i64));
+            }
+        }
+        return result
+    }
+
+    // The leading coefficient, zero for the zero polynomial
+    pub fn leading_coefficient(&self) -> Numeric {
+        match self.leading_term() {
+            Some((_, coefficient)) => return coefficient.clone(),
+            None => return Numeric::zero()
+        }
+    }
+
+    // self divided by its leading coefficient
+    pub fn monic(&self) -> Polynomial {
+        if self.is_zero() {
+            return self.clone()
+        }
+        return self.scaled(&(Numeric::one() / self.leading_coefficient()))
+    }
+
+    // The rest are univariate, for polynomials in at most one variable, and
+    // give None for others
+
+    // Quotient and remainder of the long division by divisor, None if the
+    // divisor is zero
+    pub fn div_rem(&self, divisor: &Polynomial) -> Option<(Polynomial, Polynomial)> {
+        let (mut remainder, divisor) = self.univariate(divisor)?;
+        let divisor_degree = divisor.degree()?;
+        let lead = divisor.leading_coefficient();
+        let mut quotient = Polynomial::zero(&divisor.variables);
+        while let Some(degree) = remainder.degree() {
+            if degree < divisor_degree {
+                break
+            }
+            let mut term = Polynomial::zero(&divisor.variables);
+            term.add_term(divisor.power(degree - divisor_degree), remainder.leading_coefficient() / lead.clone());
+            remainder = &remainder - &(&term * &divisor);
+            quotient = &quotient + &term;
+        }
+        return Some((quotient, remainder))
+    }
+
+    // Monic greatest common divisor, zero if both are zero. Computed by the
+    // subresultant remainder sequence, whose coefficients grow much slower
+    // than those of Euclid's algorithm.
+    pub fn gcd(&self, other: &Polynomial) -> Option<Polynomial> {
+        let (mut a, mut b) = self.univariate(other)?;
+        if a.degree() < b.degree() {
+            mem::swap(&mut a, &mut b);
+        }
+        let mut g = Numeric::one();
+        let mut h = Numeric::one();
+        while !b.is_zero() {
+            let delta = a.degree()? - b.degree()?;
+            let remainder = a.pseudo_remainder(&b)?;
+            if remainder.degree() == Some(0) {
+                return Polynomial::constant(&a.variables, Numeric::one())
+            }
+            a = b;
+            b = remainder.scaled(&(Numeric::one() / (g * h.pow(delta as i64))));
+            g = a.leading_coefficient();
+            if delta > 0 {
+                h = g.pow(delta as i64) / h.pow(delta as i64 - 1);
+            }
+        }
+        return Some(a.monic())
+    }
+
+    // Resultant, zero exactly when both have a common root or one is zero
+    pub fn resultant(&self, other: &Polynomial) -> Option<Numeric> {
+        let (a, b) = self.univariate(other)?;
+        let (a_degree, b_degree) = match (a.degree(), b.degree()) {
+            (Some(a_degree), Some(b_degree)) => (a_degree, b_degree),
+            _ => return Some(Numeric::zero())
+        };
+        if a_degree == 0 {
+            return Some(a.leading_coefficient().pow(b_degree as i64))
+        }
+        if b_degree == 0 {
+            return Some(b.leading_coefficient().pow(a_degree as i64))
+        }
+        // res(a, b) = (-1)^(deg a deg b) lc(b)^(deg a - deg r) res(b, r)
+        // for the remainder r of a by b
+        let remainder = a.div_rem(&b)?.1;
+        let remainder_degree = match remainder.degree() {
+            Some(degree) => degree,
+            None => return Some(Numeric::zero())
+        };
+        let mut result = b.leading_coefficient().pow((a_degree - remainder_degree) as i64) * b.resultant(&remainder)?;
+        if a_degree % 2 == 1 && b_degree % 2 == 1 {
+            result = -result;
+        }
+        return Some(result)
+    }
+
+    // (-1)^(n (n - 1) / 2) res(p, p') / lc(p) for p = self of degree n,
+    // None for constants
+    pub fn discriminant(&self) -> Option<Numeric> {
+        let degree = self.degree()?;
+        if degree == 0 || self.variables.len() > 1 {
+            return None
+        }
+        let resultant = self.resultant(&self.univariate_derivative())? / self.leading_coefficient();
+        if (degree as u64 * (degree as u64 - 1) / 2) % 2 == 1 {
+            return Some(-resultant)
+        }
+        return Some(resultant)
+    }
+
+    // Square-free decomposition by Yun's algorithm, self = c a1 a2^2 ...
+    // with a number c and monic, square-free and pairwise coprime ai.
+    // Returns c and the ai of positive degree with their exponents, None
+    // for zero.
+    pub fn square_free(&self) -> Option<(Numeric, Vec<(Polynomial, u32)>)> {
+        if self.is_zero() || self.variables.len() > 1 {
+            return None
+        }
+        let mut factors = Vec::new();
+        let derivative = self.univariate_derivative();
+        let common = self.gcd(&derivative)?;
+        let mut w = self.div_rem(&common)?.0;
+        let mut z = &derivative.div_rem(&common)?.0 - &w.univariate_derivative();
+        let mut exponent = 1;
+        while w.degree() > Some(0) {
+            let factor = w.gcd(&z)?;
+            w = w.div_rem(&factor)?.0;
+            z = &z.div_rem(&factor)?.0 - &w.univariate_derivative();
+            if factor.degree() > Some(0) {
+                factors.push((factor, exponent));
+            }
+            exponent += 1;
+        }
+        return Some((self.leading_coefficient(), factors))
+    }
+
+    // Remainder of lc(other)^(deg self - deg other + 1) self by other, which
+    // has no fractions for integer coefficients
+    fn pseudo_remainder(&self, other: &Polynomial) -> Option<Polynomial> {
+        let delta = self.degree()? - other.degree()?;
+        let scale = other.leading_coefficient().pow(delta as i64 + 1);
+        return Some(self.scaled(&scale).div_rem(other)?.1)
+    }
+
+    fn univariate(&self, other: &Polynomial) -> Option<(Polynomial, Polynomial)> {
+        let (lhs, rhs) = self.aligned(other);
+        if lhs.variables.len() > 1 {
+            return None
+        }
+        return Some((lhs, rhs))
+    }
+
+    fn univariate_derivative(&self) -> Polynomial {
+        match self.variables.first() {
+            Some(key) => return self.derivative(*key),
+            None => return Polynomial::zero(&self.variables)
+        }
+    }
+
+    // The monomial of the variable to the power degree, a constant has no
+    // variable
+    fn power(&self, degree: u32) -> Monomial {
+        if self.variables.is_empty() {
+            return Vec::new()
+        }
+        return vec![degree]
+    }
+
+    fn add_term(&mut self, monomial: Monomial, coefficient: Numeric) {
+        let sum = match self.terms.remove(&monomial) {
+            Some(existing) => existing + coefficient,
+            None => coefficient
+        };
+        if !sum.is_zero() {
+            self.terms.insert(monomial, sum);
+        }
+    }
+
+    // Both polynomials over the variables of self followed by the new
+    // ones of other
+    fn aligned(&self, other: &Polynomial) -> (Polynomial, Polynomial) {
+        if self.variables == other.variables {
+            return (self.clone(), other.clone())
+        }
+        let mut variables = self.variables.clone();
+        variables.extend(other.variables.iter().filter(|key| !self.variables.contains(key)));
+        return (self.with_variables(&variables), other.with_variables(&variables))
+    }
+}
+
+// Exponents add up
+fn monomial_product(left: &Monomial, right: &Monomial) -> Monomial {
+    left.iter().zip(right.iter()).map(|(a, b)| a + b).collect()
+}
+
+// Integers and rationals, floats would make equal polynomials differ
+fn is_exact(number: &Numeric) -> bool {
+    match number.level() {
+        NumericLevel::Integer | NumericLevel::Rational => return true,
+        _ => return false
+    }
+}
+
+impl Add<&Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    fn add(self, other: &Polynomial) -> Polynomial {
+        let (mut result, other) = self.aligned(other);
+        for (monomial, coefficient) in other.terms {
+            result.add_term(monomial, coefficient);
+        }
+        return result
+    }
+}
+
+impl Sub<&Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    fn sub(self, other: &Polynomial) -> Polynomial {
+        let (mut result, other) = self.aligned(other);
+        for (monomial, coefficient) in other.terms {
+            result.add_term(monomial, -coefficient);
+        }
+        return result
+    }
+}
+
+impl Mul<&Polynomial> for &Polynomial {
+    type Output = Polynomial;
+
+    fn mul(self, other: &Polynomial) -> Polynomial {
+        let (lhs, rhs) = self.aligned(other);
+        let mut result = Polynomial::zero(&lhs.variables);
+        for (left, left_coefficient) in lhs.terms.iter() {
+            for (right, right_coefficient) in rhs.terms.iter() {
+                result.add_term(monomial_product(left, right), left_coefficient.clone() * right_coefficient.clone());
+            }
+        }
+        return result
+    }
+}
+
+impl Neg for &Polynomial {
+    type Output = Polynomial;
+
+    fn neg(self) -> Polynomial {
+        self.scaled(&Numeric::from_integer(-1))
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_arithmetic() {
+    let variables = [0, 1];
+    let x = Polynomial::variable(&variables, 0).unwrap();
+    let y = Polynomial::variable(&variables, 1).unwrap();
+    let one = Polynomial::constant(&variables, Numeric::one()).unwrap();
+
+    // (x + y)^2 = x^2 + 2 x y + y^2
+    let square = (&x + &y).pow(2);
+    assert_eq!(square.term_count(), 3);
+    assert_eq!(square.coefficient(&vec![1, 1]), Numeric::from_integer(2));
+    assert_eq!(square, &(&(&x * &x) + &(&x * &y).scaled(&Numeric::from_integer(2))) + &(&y * &y));
+    // (x + 1)(x - 1) = x^2 - 1
+    let difference = &(&x + &one) * &(&x - &one);
+    assert_eq!(difference, &x.pow(2) - &one);
+    assert!((&difference - &difference).is_zero());
+    assert_eq!(Polynomial::constant(&variables, Numeric::from_real(0.5)), None);
+
+    // Degrees and the lexicographically leading term, x before y
+    let mixed = &(&x * &y.pow(3)) + &x.pow(2).scaled(&Numeric::from_integer(5));
+    assert_eq!(mixed.degree(), Some(4));
+    assert_eq!(mixed.degree_in(0), Some(2));
+    assert_eq!(mixed.degree_in(1), Some(3));
+    assert_eq!(mixed.degree_in(7), None);
+    assert_eq!(mixed.leading_term(), Some((&vec![2, 0], &Numeric::from_integer(5))));
+    assert_eq!(Polynomial::zero(&variables).degree(), None);
+
+    // Polynomials in different variables are brought to the same ones
+    let z = Polynomial::variable(&[2], 2).unwrap();
+    let product = &x * &z;
+    assert_eq!(product.variables(), &vec![0, 1, 2]);
+    assert_eq!(product.coefficient(&vec![1, 0, 1]), Numeric::one());
+}
+
+#[test]
+fn test_conversion() {
+    use symexpr_rc::{add, sub, mul, div, pow, neg, apply};
+
+    let x = Expr::from_key(0).clone_to_heap();
+    let y = Expr::from_key(1).clone_to_heap();
+    let two = Expr::from_integer(2).clone_to_heap();
+    let three = Expr::from_integer(3).clone_to_heap();
+    let print = |expr: &Rc<Expr>| expr.format_with(&|key| ["x", "y"][key].to_string());
+
+    // (x + 2)^3 - x y / 2
+    let expr = sub(&pow(&add(&x, &two), &three), &div(&mul(&x, &y), &two));
+    let polynomial = Polynomial::from_expr(&expr, &[0, 1]).unwrap().unwrap();
+    assert_eq!(polynomial.term_count(), 5);
+    assert_eq!(polynomial.coefficient(&vec![1, 1]), Numeric::from_rational(::rational::Rational::new(-1, 2)));
+    assert_eq!(print(&polynomial.to_expr()), "8 + x^3 + 6*x^2 + 12*x - 1/2*x*y");
+    assert_eq!(Polynomial::from_expr(&polynomial.to_expr(), &[0, 1]), Ok(Some(polynomial)));
+    assert!(Polynomial::is_polynomial(&neg(&x), &[0]));
+
+    // Not polynomials in x and y: functions, negative or symbolic powers,
+    // division by a variable, float coefficients and other variables
+    assert!(!Polynomial::is_polynomial(&apply(UnaryFunction::Sin, &x), &[0, 1]));
+    assert!(!Polynomial::is_polynomial(&pow(&x, &Expr::from_integer(-1).clone_to_heap()), &[0, 1]));
+    assert!(!Polynomial::is_polynomial(&pow(&x, &y), &[0, 1]));
+    assert!(!Polynomial::is_polynomial(&div(&two, &x), &[0, 1]));
+    assert!(!Polynomial::is_polynomial(&mul(&Expr::from_real(1.5).clone_to_heap(), &x), &[0, 1]));
+    assert!(!Polynomial::is_polynomial(&add(&x, &y), &[0]));
+    assert!(Polynomial::is_polynomial(&add(&x, &y), &[0, 1]));
+    // Up to MAX_DEGREE
+    let large = Expr::from_integer(4000000000).clone_to_heap();
+    assert_eq!(Polynomial::from_expr(&pow(&add(&x, &y), &large), &[0, 1]), Err(NumericError::Overflow));
+}
+
+#[test]
+fn test_division_and_gcd() {
+    let variables = [0];
+    let x = Polynomial::variable(&variables, 0).unwrap();
+    let number = |value: i64| Polynomial::constant(&variables, Numeric::from_integer(value)).unwrap();
+
+    // x^3 - 2x + 5 = (2x + 1)(1/2 x^2 - 1/4 x - 7/8) + 47/8
+    let dividend = &(&x.pow(3) - &x.scaled(&Numeric::from_integer(2))) + &number(5);
+    let divisor = &x.scaled(&Numeric::from_integer(2)) + &number(1);
+    let (quotient, remainder) = dividend.div_rem(&divisor).unwrap();
+    assert_eq!(remainder, Polynomial::constant(&variables, Numeric::from_rational(::rational::Rational::new(47, 8))).unwrap());
+    assert_eq!(&(&quotient * &divisor) + &remainder, dividend);
+    assert_eq!(dividend.div_rem(&Polynomial::zero(&variables)), None);
+
+    // gcd((x - 1)^2 (x + 2), 3 (x - 1)(x + 5)) = x - 1
+    let first = &(&x - &number(1)).pow(2) * &(&x + &number(2));
+    let second = &(&x - &number(1)).scaled(&Numeric::from_integer(3)) * &(&x + &number(5));
+    assert_eq!(first.gcd(&second), Some(&x - &number(1)));
+    assert_eq!(first.gcd(&(&x + &number(7))), Some(number(1)));
+    assert_eq!(first.gcd(&Polynomial::zero(&variables)), Some(first.monic()));
+    let y = Polynomial::variable(&[1], 1).unwrap();
+    assert_eq!(x.gcd(&y), None);
+}
+
+#[test]
+fn test_square_free_and_discriminant() {
+    let variables = [0];
+    let x = Polynomial::variable(&variables, 0).unwrap();
+    let number = |value: i64| Polynomial::constant(&variables, Numeric::from_integer(value)).unwrap();
+
+    // 2 (x + 1) (x - 2)^3
+    let polynomial = &(&x + &number(1)).scaled(&Numeric::from_integer(2)) * &(&x - &number(2)).pow(3);
+    let (constant, factors) = polynomial.square_free().unwrap();
+    assert_eq!(constant, Numeric::from_integer(2));
+    assert_eq!(factors, vec![(&x + &number(1), 1), (&x - &number(2), 3)]);
+
+    // x^2 - 2 has discriminant 8, x^3 + x + 1 has -4 - 27 = -31
+    assert_eq!((&x.pow(2) - &number(2)).discriminant(), Some(Numeric::from_integer(8)));
+    assert_eq!((&(&x.pow(3) + &x) + &number(1)).discriminant(), Some(Numeric::from_integer(-31)));
+    assert_eq!(polynomial.discriminant(), Some(Numeric::zero()));
+    assert_eq!(number(3).discriminant(), None);
+    // res(x^2 + 1, x - 2) = 5
+    assert_eq!((&x.pow(2) + &number(1)).resultant(&(&x - &number(2))), Some(Numeric::from_integer(5)));
+}
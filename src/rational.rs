@@ -0,0 +1,189 @@
+#![allow(dead_code)]
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Sub, Mul, Div, Neg};
+
+use bigint::BigInt;
+
+pub type RationalPart = BigInt;
+
+// Exact fraction, always kept with a positive denominator and
+// numerator and denominator reduced by their greatest common divisor
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Rational {
+    num: RationalPart,
+    den: RationalPart
+}
+
+impl Rational {
+    pub fn new(num: i64, den: i64) -> Rational {
+        Rational::from_parts(BigInt::from(num), BigInt::from(den))
+    }
+
+    pub fn from_integer(integer: i64) -> Rational {
+        Rational::from_big_integer(BigInt::from(integer))
+    }
+
+    pub fn from_big_integer(integer: RationalPart) -> Rational {
+        Rational { num: integer, den: BigInt::one() }
+    }
+
+    // Normalizes a fraction. Panics if the denominator is zero.
+    pub fn from_parts(num: RationalPart, den: RationalPart) -> Rational {
+        assert!(!den.is_zero(), "attempt to create a rational with a zero denominator");
+        let divisor = num.gcd(&den);
+        let (num, den) = if den.is_negative() { (-num, -den) } else { (num, den) };
+        Rational {
+            num: num / divisor.clone(),
+            den: den / divisor
+        }
+    }
+
+    pub fn numer(&self) -> &RationalPart {
+        &self.num
+    }
+
+    pub fn denom(&self) -> &RationalPart {
+        &self.den
+    }
+
+    pub fn is_integer(&self) -> bool {
+        self.den.is_one()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.num.is_zero()
+    }
+
+    pub fn is_unity(&self) -> bool {
+        self.num.is_one() && self.den.is_one()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.num.is_negative()
+    }
+
+    pub fn to_real(&self) -> f64 {
+        // Scale both parts down so huge fractions do not become inf / inf
+        let excess = self.num.bits().max(self.den.bits()).saturating_sub(1000) as u32;
+        self.num.shr(excess).to_f64() / self.den.shr(excess).to_f64()
+    }
+
+    pub fn recip(&self) -> Rational {
+        Rational::from_parts(self.den.clone(), self.num.clone())
+    }
+
+    // Exponents past u32::MAX saturate keeping their parity, which is exact
+    // for 0, 1 and -1, the only bases whose powers that large fit in memory
+    pub fn pow(&self, pow: i64) -> Rational {
+        let base = if pow < 0 { self.recip() } else { self.clone() };
+        let exponent = if pow.unsigned_abs() <= u32::MAX as u64 {
+            pow.unsigned_abs() as u32
+        } else {
+            u32::MAX - 1 + (pow.unsigned_abs() % 2) as u32
+        };
+        Rational { num: base.num.pow(exponent), den: base.den.pow(exponent) }
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.den.is_one() {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> Ordering {
+        // Denominators are positive, so cross multiplication preserves order
+        (self.num.clone() * other.den.clone()).cmp(&(other.num.clone() * self.den.clone()))
+    }
+}
+
+impl Neg for Rational {
+    type Output = Rational;
+
+    fn neg(self) -> Rational {
+        Rational { num: -self.num, den: self.den }
+    }
+}
+
+impl Add for Rational {
+    type Output = Rational;
+
+    fn add(self, other: Rational) -> Rational {
+        Rational::from_parts(self.num * other.den.clone() + other.num * self.den.clone(),
+                             self.den * other.den)
+    }
+}
+
+impl Sub for Rational {
+    type Output = Rational;
+
+    fn sub(self, other: Rational) -> Rational {
+        self + (-other)
+    }
+}
+
+impl Mul for Rational {
+    type Output = Rational;
+
+    fn mul(self, other: Rational) -> Rational {
+        Rational::from_parts(self.num * other.num, self.den * other.den)
+    }
+}
+
+impl Div for Rational {
+    type Output = Rational;
+
+    fn div(self, other: Rational) -> Rational {
+        Rational::from_parts(self.num * other.den, self.den * other.num)
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_rational_normalization() {
+    assert_eq!(Rational::new(2, 4), Rational::new(1, 2));
+    assert_eq!(Rational::new(1, -3), Rational::new(-1, 3));
+    assert_eq!(Rational::new(-6, -3), Rational::from_integer(2));
+    assert_eq!(Rational::new(0, -5), Rational::from_integer(0));
+    assert_eq!(*Rational::new(3, -9).denom(), BigInt::from(3));
+    assert!(Rational::new(4, 2).is_integer());
+}
+
+#[test]
+fn test_rational_arithmetic() {
+    let third = Rational::new(1, 3);
+    let half = Rational::new(1, 2);
+
+    assert_eq!(third.clone() + half.clone(), Rational::new(5, 6));
+    assert_eq!(third.clone() - half.clone(), Rational::new(-1, 6));
+    assert_eq!(third.clone() * half.clone(), Rational::new(1, 6));
+    assert_eq!(third.clone() / half.clone(), Rational::new(2, 3));
+    assert_eq!(half.pow(3), Rational::new(1, 8));
+    assert_eq!(half.pow(-2), Rational::from_integer(4));
+    assert!(third < half);
+    assert!(-half.clone() < third);
+    assert_eq!(format!("{}", third), "1/3");
+    assert_eq!(format!("{}", -half), "-1/2");
+}
+
+#[test]
+fn test_rational_with_large_parts() {
+    let tiny = Rational::from_parts(BigInt::one(), BigInt::from(2).pow(100));
+    let sum = tiny.clone() + tiny.clone();
+    assert_eq!(sum, Rational::from_parts(BigInt::one(), BigInt::from(2).pow(99)));
+    assert_eq!(tiny.to_real(), 2f64.powi(-100));
+    assert_eq!(format!("{}", tiny.recip()), "1267650600228229401496703205376");
+}
@@ -0,0 +1,427 @@
+#![allow(dead_code)]
+
+use std::collections::HashSet;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use numeric::{Numeric, NumericLevel};
+use symexpr_rc;
+use symexpr_rc::{Expr, UnaryFunction, BinaryFunction, NaryFunction};
+use pattern::RewriteRule;
+
+// Passes over the whole expression, and rewrites of a single node, before
+// giving up on reaching a fixpoint
+const MAX_PASSES: usize = 64;
+
+// The rules of docs/simplification_rules.md. Associativity and the
+// negation rules -(-a) = a and (-a) (-b) = a b hold for every sum and
+// product already.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Rule {
+    // 0 * a = 0, 1 * a = a, a / a = 1, a^1 = a and folding of numbers
+    Identities,
+    // (-a) + (-b) = -(a + b)
+    Negation,
+    // (a / b) (c / d) = (a c) / (b d) and (a / b) / (c / d) = (a d) / (b c)
+    Fractions,
+    // a (b + c) = a b + a c
+    Distribution,
+    // exp(a + b) = exp(a) exp(b)
+    ExpOfSum,
+    // n^(k + a) = n^k n^a for a number n and an integer k. Other powers
+    // of the same base are kept together by products.
+    PowerOfSum,
+    // sin(a)^2 + cos(a)^2 = 1
+    Pythagorean
+}
+
+pub const RULES: [Rule; 7] = [
+    Rule::Identities, Rule::Negation, Rule::Fractions, Rule::Distribution,
+    Rule::ExpOfSum, Rule::PowerOfSum, Rule::Pythagorean
+];
+
+impl Rule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::Identities => "identities",
+            Rule::Negation => "negation",
+            Rule::Fractions => "fractions",
+            Rule::Distribution => "distribution",
+            Rule::ExpOfSum => "exp_of_sum",
+            Rule::PowerOfSum => "power_of_sum",
+            Rule::Pythagorean => "pythagorean"
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Rule> {
+        RULES.iter().cloned().find(|rule| rule.name() == name)
+    }
+
+    // The rewritten node, None if the rule does not apply to it
+    fn apply(&self, expr: &Rc<Expr>) -> Option<Rc<Expr>> {
+        match self {
+            Rule::Identities => {
+                let cleaned = Expr::cleaned(expr);
+                if cleaned == *expr {
+                    return None
+                }
+                return Some(cleaned)
+            },
+            Rule::Negation => return collect_negation(expr),
+            Rule::Fractions => return combine_fractions(expr),
+            Rule::Distribution => return distribute(expr),
+            Rule::ExpOfSum => return split_exp(expr),
+            Rule::PowerOfSum => return split_power(expr),
+            Rule::Pythagorean => return pythagorean(expr)
+        }
+    }
+}
+
+// Applies the enabled rules bottom-up until the expression stops changing,
+// then the rewrite rules in the order they were added
+pub struct Simplifier {
+    disabled: HashSet<Rule>,
+    rewrites: Vec<RewriteRule>
+}
+
+impl Simplifier {
+    // All rules enabled
+    pub fn new() -> Simplifier {
+        Simplifier { disabled: HashSet::new(), rewrites: Vec::new() }
+    }
+
+    pub fn enable(&mut self, rule: Rule) {
+        self.disabled.remove(&rule);
+    }
+
+    pub fn disable(&mut self, rule: Rule) {
+        self.disabled.insert(rule);
+    }
+
+    pub fn is_enabled(&self, rule: Rule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+
+    pub fn add_rewrite(&mut self, rewrite: RewriteRule) {
+        self.rewrites.push(rewrite);
+    }
+
+    pub fn rewrites(&self) -> &Vec<RewriteRule> {
+        &self.rewrites
+    }
+
+    pub fn simplify(&self, expr: &Rc<Expr>) -> Rc<Expr> {
+        let mut current = Rc::clone(expr);
+        for _ in 0..MAX_PASSES {
+            let next = self.pass(&current);
+            if next == current {
+                break
+            }
+            current = next;
+        }
+        return current
+    }
+
+    // One bottom-up pass, the children first and then the node itself
+    fn pass(&self, expr: &Rc<Expr>) -> Rc<Expr> {
+        let mut current = match expr.deref() {
+            Expr::Unary(unary_expr) => {
+                let argument = self.pass(unary_expr.argument());
+                if Rc::ptr_eq(&argument, unary_expr.argument()) {
+                    Rc::clone(expr)
+                } else if *unary_expr.function() == UnaryFunction::Neg {
+                    symexpr_rc::neg(&argument)
+                } else {
+                    symexpr_rc::apply(unary_expr.function().clone(), &argument)
+                }
+            },
+            Expr::Binary(binary_expr) => {
+                let lhs = self.pass(binary_expr.lhs());
+                let rhs = self.pass(binary_expr.rhs());
+                if Rc::ptr_eq(&lhs, binary_expr.lhs()) && Rc::ptr_eq(&rhs, binary_expr.rhs()) {
+                    Rc::clone(expr)
+                } else {
+                    Expr::binary_from_heap(&lhs, &rhs, binary_expr.function().clone()).move_to_heap()
+                }
+            },
+            Expr::Nary(nary_expr) => {
+                let operands: Vec<Rc<Expr>> = nary_expr.operands().iter().map(|operand| self.pass(operand)).collect();
+                if operands.iter().zip(nary_expr.operands()).all(|(new, old)| Rc::ptr_eq(new, old)) {
+                    Rc::clone(expr)
+                } else {
+                    nary(nary_expr.function(), operands)
+                }
+            },
+            _ => Rc::clone(expr)
+        };
+        for _ in 0..MAX_PASSES {
+            let rewritten = RULES.iter()
+                .filter(|rule| self.is_enabled(**rule))
+                .filter_map(|rule| rule.apply(&current))
+                .next()
+                .or_else(|| self.rewrites.iter().filter_map(|rewrite| rewrite.apply(&current)).next());
+            match rewritten {
+                Some(next) => current = next,
+                None => break
+            }
+        }
+        return current
+    }
+}
+
+impl Default for Simplifier {
+    fn default() -> Simplifier {
+        Simplifier::new()
+    }
+}
+
+fn nary(function: &NaryFunction, operands: Vec<Rc<Expr>>) -> Rc<Expr> {
+    match function {
+        NaryFunction::Add => return symexpr_rc::sum(operands),
+        NaryFunction::Mul => return symexpr_rc::product(operands)
+    }
+}
+
+fn operands_of(expr: &Expr, function: NaryFunction) -> Option<&Vec<Rc<Expr>>> {
+    match expr {
+        Expr::Nary(nary_expr) if *nary_expr.function() == function => return Some(nary_expr.operands()),
+        _ => return None
+    }
+}
+
+fn binary_operands(expr: &Expr, function: BinaryFunction) -> Option<(&Rc<Expr>, &Rc<Expr>)> {
+    match expr {
+        Expr::Binary(binary_expr) if *binary_expr.function() == function => {
+            return Some((binary_expr.lhs(), binary_expr.rhs()))
+        },
+        _ => return None
+    }
+}
+
+fn collect_negation(expr: &Rc<Expr>) -> Option<Rc<Expr>> {
+    let terms = operands_of(expr, NaryFunction::Add)?;
+    let positive: Option<Vec<Rc<Expr>>> = terms.iter().map(Expr::without_sign).collect();
+    return Some(symexpr_rc::neg(&symexpr_rc::sum(positive?)))
+}
+
+fn factors_of(expr: &Rc<Expr>) -> Vec<Rc<Expr>> {
+    match operands_of(expr, NaryFunction::Mul) {
+        Some(factors) => return factors.clone(),
+        None => return vec![Rc::clone(expr)]
+    }
+}
+
+// Cancels the factors a quotient has in common, x y / (x z) = y / z. The
+// numerator is multiplied by the inverse of the denominator, so that the
+// product combines their powers, and the negative powers are split off as
+// the new denominator.
+fn cancel(numerator: &Rc<Expr>, denominator: &Rc<Expr>) -> Option<Rc<Expr>> {
+    let minus_one = Expr::from_integer(-1).move_to_heap();
+    let mut factors = factors_of(numerator);
+    for factor in factors_of(denominator) {
+        if let Expr::Numeric(number) = factor.deref() {
+            // Division by zero is left for evaluation to report
+            let inverse = Numeric::one().checked_div(number.clone()).ok()?;
+            factors.push(Expr::from_numeric(inverse).move_to_heap());
+            continue
+        }
+        match binary_operands(&factor, BinaryFunction::Pow) {
+            Some((base, exponent)) => factors.push(symexpr_rc::pow(base, &symexpr_rc::neg(exponent))),
+            None => factors.push(symexpr_rc::pow(&factor, &minus_one))
+        }
+    }
+    let mut numerators = Vec::new();
+    let mut denominators = Vec::new();
+    for factor in factors_of(&symexpr_rc::product(factors)) {
+        let negated = binary_operands(&factor, BinaryFunction::Pow)
+            .and_then(|(base, exponent)| Expr::without_sign(exponent).map(|exponent| (Rc::clone(base), exponent)));
+        match negated {
+            Some((base, exponent)) => denominators.push(symexpr_rc::pow(&base, &exponent)),
+            None => numerators.push(factor)
+        }
+    }
+    let numerator = Expr::cleaned(&symexpr_rc::product(numerators));
+    if denominators.is_empty() {
+        return Some(numerator)
+    }
+    return Some(symexpr_rc::div(&numerator, &Expr::cleaned(&symexpr_rc::product(denominators))))
+}
+
+fn combine_fractions(expr: &Rc<Expr>) -> Option<Rc<Expr>> {
+    if let Some((lhs, rhs)) = binary_operands(expr, BinaryFunction::Div) {
+        // (a / b) / c = a / (b c) and a / (c / d) = (a d) / c
+        if let Some((a, b)) = binary_operands(lhs, BinaryFunction::Div) {
+            return Some(symexpr_rc::div(a, &symexpr_rc::mul(b, rhs)))
+        }
+        if let Some((c, d)) = binary_operands(rhs, BinaryFunction::Div) {
+            return Some(symexpr_rc::div(&symexpr_rc::mul(lhs, d), c))
+        }
+        let cancelled = cancel(lhs, rhs)?;
+        if cancelled == *expr {
+            return None
+        }
+        return Some(cancelled)
+    }
+    let factors = operands_of(expr, NaryFunction::Mul)?;
+    let mut numerators = Vec::new();
+    let mut denominators = Vec::new();
+    for factor in factors {
+        match binary_operands(factor, BinaryFunction::Div) {
+            Some((numerator, denominator)) => {
+                numerators.push(Rc::clone(numerator));
+                denominators.push(Rc::clone(denominator));
+            },
+            None => numerators.push(Rc::clone(factor))
+        }
+    }
+    if denominators.is_empty() {
+        return None
+    }
+    return Some(symexpr_rc::div(&symexpr_rc::product(numerators), &symexpr_rc::product(denominators)))
+}
+
+// Distributes the first sum among the factors of a product
+fn distribute(expr: &Rc<Expr>) -> Option<Rc<Expr>> {
+    let factors = operands_of(expr, NaryFunction::Mul)?;
+    let position = factors.iter().position(|factor| operands_of(factor, NaryFunction::Add).is_some())?;
+    let terms = operands_of(&factors[position], NaryFunction::Add)?;
+    let distributed = terms.iter().map(|term| {
+        let mut product = factors.clone();
+        product[position] = Rc::clone(term);
+        symexpr_rc::product(product)
+    }).collect();
+    return Some(symexpr_rc::sum(distributed))
+}
+
+fn split_exp(expr: &Rc<Expr>) -> Option<Rc<Expr>> {
+    let argument = match expr.deref() {
+        Expr::Unary(unary_expr) if *unary_expr.function() == UnaryFunction::Exp => unary_expr.argument(),
+        _ => return None
+    };
+    let terms = operands_of(argument, NaryFunction::Add)?;
+    return Some(symexpr_rc::product(terms.iter().map(symexpr_rc::exp).collect()))
+}
+
+fn split_power(expr: &Rc<Expr>) -> Option<Rc<Expr>> {
+    let (base, exponent) = binary_operands(expr, BinaryFunction::Pow)?;
+    let terms = operands_of(exponent, NaryFunction::Add)?;
+    let (base_value, constant) = match (base.deref(), terms[0].deref()) {
+        (Expr::Numeric(base_value), Expr::Numeric(constant)) if constant.level() == NumericLevel::Integer => {
+            (base_value, constant)
+        },
+        _ => return None
+    };
+    // Left alone if n^k is too large to write out
+    let power = base_value.clone().checked_pow_numeric(constant).ok()?;
+    let rest = symexpr_rc::sum(terms[1..].to_vec());
+    return Some(symexpr_rc::mul(&Expr::from_numeric(power).move_to_heap(), &symexpr_rc::pow(base, &rest)))
+}
+
+// Coefficient and rest of a term, 3 sin(x)^2 gives 3 and sin(x)^2
+fn split_coefficient(term: &Rc<Expr>) -> (Numeric, Rc<Expr>) {
+    if let Some(positive) = Expr::without_sign(term) {
+        let (coefficient, rest) = split_coefficient(&positive);
+        return (-coefficient, rest)
+    }
+    if let Some(factors) = operands_of(term, NaryFunction::Mul) {
+        if let Expr::Numeric(coefficient) = factors[0].deref() {
+            return (coefficient.clone(), symexpr_rc::product(factors[1..].to_vec()))
+        }
+    }
+    return (Numeric::one(), Rc::clone(term))
+}
+
+// The argument of f(a)^2
+fn squared_argument(expr: &Expr, function: UnaryFunction) -> Option<&Rc<Expr>> {
+    let (base, exponent) = binary_operands(expr, BinaryFunction::Pow)?;
+    if **exponent != Expr::from_integer(2) {
+        return None
+    }
+    match base.deref() {
+        Expr::Unary(unary_expr) if *unary_expr.function() == function => return Some(unary_expr.argument()),
+        _ => return None
+    }
+}
+
+// c sin(a)^2 + c cos(a)^2 = c, for the first such pair of terms
+fn pythagorean(expr: &Rc<Expr>) -> Option<Rc<Expr>> {
+    let terms = operands_of(expr, NaryFunction::Add)?;
+    let split: Vec<(Numeric, Rc<Expr>)> = terms.iter().map(split_coefficient).collect();
+    for (i, (sin_coefficient, sin_squared)) in split.iter().enumerate() {
+        let argument = match squared_argument(sin_squared, UnaryFunction::Sin) {
+            Some(argument) => argument,
+            None => continue
+        };
+        let cosine = split.iter().position(|(coefficient, cos_squared)| {
+            coefficient == sin_coefficient && squared_argument(cos_squared, UnaryFunction::Cos) == Some(argument)
+        });
+        if let Some(j) = cosine {
+            let mut rest: Vec<Rc<Expr>> = terms.iter().enumerate()
+                .filter(|&(position, _)| position != i && position != j)
+                .map(|(_, term)| Rc::clone(term))
+                .collect();
+            rest.push(Expr::from_numeric(sin_coefficient.clone()).move_to_heap());
+            return Some(symexpr_rc::sum(rest))
+        }
+    }
+    return None
+}
+
+#[cfg(test)]
+#[test]
+fn test_rules() {
+    use symexpr_rc::{add, sub, mul, div, pow, neg, exp, apply};
+
+    let x = Expr::from_key(0).clone_to_heap();
+    let y = Expr::from_key(1).clone_to_heap();
+    let z = Expr::from_key(2).clone_to_heap();
+    let two = Expr::from_integer(2).clone_to_heap();
+    let simplifier = Simplifier::new();
+    let print = |expr: &Rc<Expr>| expr.format_with(&|key| ["x", "y", "z"][key].to_string());
+
+    // a (b + c) = a b + a c, through nested products
+    assert_eq!(print(&simplifier.simplify(&mul(&x, &add(&y, &z)))), "x*y + x*z");
+    assert_eq!(print(&simplifier.simplify(&mul(&add(&x, &y), &sub(&x, &y)))), "x^2 - y^2");
+    // (-x) + (-y) = -(x + y)
+    assert_eq!(print(&simplifier.simplify(&sub(&neg(&x), &y))), "-(x + y)");
+    // (x / y) (z / x) = z / y and (x / y) / (z / y) = x / z
+    assert_eq!(print(&simplifier.simplify(&mul(&div(&x, &y), &div(&z, &x)))), "z/y");
+    assert_eq!(print(&simplifier.simplify(&div(&div(&x, &y), &div(&z, &y)))), "x/z");
+    // Common factors cancel, x y / (2 x z) = 1/2 y / z and (x + y) / (x + y)^2 = 1 / (x + y)
+    assert_eq!(print(&simplifier.simplify(&div(&mul(&x, &y), &mul(&two, &mul(&x, &z))))), "1/2*y/z");
+    let sum = add(&x, &y);
+    assert_eq!(print(&simplifier.simplify(&div(&sum, &pow(&sum, &two)))), "1/(x + y)");
+    // exp(x + y) = exp(x) exp(y) and 2^(x + 3) = 8 2^x
+    assert_eq!(print(&simplifier.simplify(&exp(&add(&x, &y)))), "exp(x)*exp(y)");
+    assert_eq!(print(&simplifier.simplify(&pow(&two, &add(&x, &Expr::from_integer(3).clone_to_heap())))), "8*2^x");
+    // 3 sin(x y)^2 + 3 cos(x y)^2 + z = 3 + z
+    let xy = mul(&x, &y);
+    let three = Expr::from_integer(3).clone_to_heap();
+    let identity = add(&mul(&three, &pow(&apply(UnaryFunction::Sin, &xy), &two)),
+                       &add(&z, &mul(&three, &pow(&apply(UnaryFunction::Cos, &xy), &two))));
+    assert_eq!(print(&simplifier.simplify(&identity)), "3 + z");
+    // Unchanged expressions are shared
+    let sine = apply(UnaryFunction::Sin, &x);
+    assert!(Rc::ptr_eq(&simplifier.simplify(&sine), &sine));
+}
+
+#[test]
+fn test_toggling_rules() {
+    use symexpr_rc::{add, mul, exp};
+
+    let x = Expr::from_key(0).clone_to_heap();
+    let y = Expr::from_key(1).clone_to_heap();
+    let mut simplifier = Simplifier::new();
+    let product = mul(&x, &add(&x, &y));
+
+    simplifier.disable(Rule::Distribution);
+    assert!(!simplifier.is_enabled(Rule::Distribution));
+    assert_eq!(simplifier.simplify(&product), product);
+    simplifier.enable(Rule::Distribution);
+    assert!(simplifier.simplify(&product) != product);
+
+    simplifier.disable(Rule::ExpOfSum);
+    assert_eq!(simplifier.simplify(&exp(&add(&x, &y))), exp(&add(&x, &y)));
+    assert_eq!(Rule::from_name("exp_of_sum"), Some(Rule::ExpOfSum));
+    assert_eq!(Rule::from_name("nothing"), None);
+}
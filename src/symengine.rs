@@ -1,15 +1,130 @@
-use std::collections::{HashMap};
-use numeric::{Numeric};
-use symexpr_rc::{Expr, BinaryFunction};
+#![allow(dead_code)]
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use bigfloat;
+use numeric::{Numeric, NumericError};
+use numtheory;
+use symexpr_rc;
+use symexpr_rc::{Expr, UnaryFunction};
+use exprpool::ExprPool;
+use simplify::{Simplifier, Rule};
+use pattern::{Pattern, RewriteRule};
+use expand;
+use polynomial::Polynomial;
+use std::ops::Deref;
 use std::rc::Rc;
 use lexer;
-use lexer::{Token, TermToken, OperatorType};
+use lexer::{LexError, OperatorType, Span};
+use parser;
+use parser::{Ast, AstKind, ParseError};
+
+// Function defined with f(x, y) = body, the parameters are the indices of
+// hidden variables in the body
+struct UserFunction {
+    parameters: Vec<usize>,
+    body: Rc<Expr>
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum EngineOutput {
+    Value(Numeric),
+    // An assignment whose value depends on names without a value
+    Stored(String),
+    // Signature of a defined function
+    Defined(String),
+    Precision(Option<usize>),
+    // Result of a rewriting command such as simplify, printed
+    Expression(String),
+    // A simplification rule switched on or off
+    Toggled(String, bool)
+}
+
+// Commands whose result is shown as an expression rather than evaluated
+const REWRITE_COMMANDS: [&str; 8] = ["simplify", "expand", "collect", "quo", "rem", "gcd", "sqf", "discriminant"];
+
+// Commands on polynomials in one variable
+const POLYNOMIAL_COMMANDS: [&str; 5] = ["quo", "rem", "gcd", "sqf", "discriminant"];
+
+impl fmt::Display for EngineOutput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineOutput::Value(value) => write!(f, "{}", value),
+            EngineOutput::Stored(name) => write!(f, "stored {}", name),
+            EngineOutput::Defined(signature) => write!(f, "defined {}", signature),
+            EngineOutput::Precision(None) => write!(f, "precision: machine floats"),
+            EngineOutput::Precision(Some(digits)) => write!(f, "precision: {} digits", digits),
+            EngineOutput::Expression(expr) => write!(f, "{}", expr),
+            EngineOutput::Toggled(rule, true) => write!(f, "{}: on", rule),
+            EngineOutput::Toggled(rule, false) => write!(f, "{}: off", rule)
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum EngineError {
+    Lex(LexError),
+    Parse(ParseError),
+    // A name with neither a value nor a stored expression
+    Unbound(String),
+    Evaluation(NumericError),
+    // A name whose stored expression depends on itself
+    Cycle(String)
+}
+
+impl EngineError {
+    // Where the error is in the input, evaluation errors have no position
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            EngineError::Lex(error) => Some(error.span),
+            EngineError::Parse(error) => Some(error.span),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for EngineError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EngineError::Lex(error) => write!(f, "{}", error),
+            EngineError::Parse(error) => write!(f, "{}", error),
+            EngineError::Unbound(name) => write!(f, "{} has no value", name),
+            EngineError::Evaluation(error) => write!(f, "{}", error),
+            EngineError::Cycle(name) => write!(f, "{} is defined in terms of itself", name)
+        }
+    }
+}
+
+impl Error for EngineError {}
+
+impl From<LexError> for EngineError {
+    fn from(error: LexError) -> EngineError {
+        EngineError::Lex(error)
+    }
+}
+
+impl From<ParseError> for EngineError {
+    fn from(error: ParseError) -> EngineError {
+        EngineError::Parse(error)
+    }
+}
+
+// Number theory on the values of their arguments
+const NUMBER_THEORY_FUNCTIONS: [&str; 5] = ["lcm", "mod_inverse", "is_prime", "factor", "totient"];
 
 pub struct Engine {
     variable_map: HashMap<String, usize>,
     variable_list: Vec<Numeric>,
+    // Whether each variable holds a value, or only stands for a name
+    bound: Vec<bool>,
     expr_map: HashMap<String, usize>,
-    expr_list: Vec<Rc<Expr>>
+    expr_list: Vec<Rc<Expr>>,
+    functions: HashMap<String, UserFunction>,
+    simplifier: Simplifier,
+    // Every expression is cleaned up and differentiated in one pool, so
+    // the results are shared and remembered from one command to the next
+    pool: ExprPool
 }
 
 impl Engine {
@@ -17,29 +132,71 @@ impl Engine {
         Engine {
             variable_map: HashMap::new(),
             variable_list: Vec::new(),
+            bound: Vec::new(),
             expr_map: HashMap::new(),
-            expr_list: Vec::new()
+            expr_list: Vec::new(),
+            functions: HashMap::new(),
+            simplifier: Simplifier::new(),
+            pool: ExprPool::new()
         }
     }
 
-    fn eval_expr_with(&self,key: &String, values: &Vec<Numeric>) -> Option<Numeric> {
+    fn eval_expr_with(&self,key: &String, values: &Vec<Numeric>) -> Option<Result<Numeric, NumericError>> {
         match self.get_expression(key) {
             Some(expr) => return Some(expr.eval(&values)),
             None => return None
         }
     }
 
-    fn eval_expr(&self, key: &String) -> Option<Numeric> {
+    pub fn eval_expr(&self, key: &String) -> Result<Numeric, EngineError> {
         match self.get_expression(key) {
-            Some(expr) => return Some(expr.eval(&self.variable_list)),
-            None => return None
+            Some(expr) => return self.resolve(&expr, &mut vec![key.clone()]),
+            None => return Err(EngineError::Unbound(key.clone()))
         }
     }
 
-    fn get_variable(&self, key: &String) -> Option<Numeric> {
+    // Evaluates expr, a variable without a value takes the value of the
+    // expression stored under its name. Meeting a name from in_progress
+    // again is a cycle.
+    fn resolve(&self, expr: &Rc<Expr>, in_progress: &mut Vec<String>) -> Result<Numeric, EngineError> {
+        let mut values = self.variable_list.clone();
+        let mut unbound: Option<String> = None;
+        let mut names: Vec<(&String, &usize)> = self.variable_map.iter().collect();
+        names.sort_by_key(|&(_name, index)| *index);
+        for (name, &index) in names {
+            if self.bound[index] || !expr.depends_on_variable(index) {
+                continue;
+            }
+            if in_progress.contains(name) {
+                return Err(EngineError::Cycle(name.clone()))
+            }
+            let stored = match self.get_expression(name) {
+                Some(stored) => stored,
+                None => {
+                    unbound = unbound.or_else(|| Some(name.clone()));
+                    continue;
+                }
+            };
+            in_progress.push(name.clone());
+            let value = self.resolve(&stored, in_progress);
+            in_progress.pop();
+            // A cycle further on is worse than an unbound name
+            match value {
+                Ok(value) => values[index] = value,
+                Err(EngineError::Unbound(name)) => unbound = unbound.or(Some(name)),
+                Err(error) => return Err(error)
+            }
+        }
+        match unbound {
+            Some(name) => return Err(EngineError::Unbound(name)),
+            None => return expr.eval(&values).map_err(EngineError::Evaluation)
+        }
+    }
+
+    pub fn get_variable(&self, key: &String) -> Option<Numeric> {
         match self.variable_map.get(key) {
-            Some(index) => Some(self.variable_list[*index]),
-            None => None
+            Some(index) if self.bound[*index] => Some(self.variable_list[*index].clone()),
+            _ => None
         }
     }
 
@@ -50,16 +207,22 @@ impl Engine {
         }
     }
 
-    fn assign_variable(&mut self, key: &String, val: Numeric) -> usize {
+    pub fn assign_variable(&mut self, key: &String, val: Numeric) -> usize {
+        let index = self.get_or_insert_variable(key);
+        self.variable_list[index] = val;
+        self.bound[index] = true;
+        return index
+    }
+
+    // Index of the variable named key, a new variable has no value
+    fn get_or_insert_variable(&mut self, key: &String) -> usize {
         match self.get_index_of_variable(key) {
-            Some(index) => {
-                self.variable_list[index] = val;
-                return index
-            },
+            Some(index) => return index,
             None => {
                 let new_index = self.variable_list.len();
                 self.variable_map.insert(key.clone(), new_index);
-                self.variable_list.push(val);
+                self.variable_list.push(Numeric::from_integer(0));
+                self.bound.push(false);
                 return new_index
             }
         }
@@ -94,100 +257,597 @@ impl Engine {
         }
     }
 
-    pub fn interpret(&mut self, expr_as_string: &String) {
-        let key = self.parse_string(&expr_as_string);
-        println!("  {}", self.eval_expr(&key).unwrap());
+    // Stores expr under key, the variable key then stands for expr
+    fn assign_symbolic(&mut self, key: &String, expr: &Rc<Expr>) {
+        if let Some(index) = self.get_index_of_variable(key) {
+            self.bound[index] = false;
+        }
+        self.assign_expression(key, expr);
+    }
+
+    // In x = x + 1 the x on the right is the previous x, its value or the
+    // expression stored under it
+    fn inline_previous(&self, key: &String, expr: &Rc<Expr>) -> Rc<Expr> {
+        let index = match self.get_index_of_variable(key) {
+            Some(index) if expr.depends_on_variable(index) => index,
+            _ => return Rc::clone(expr)
+        };
+        let previous = if self.bound[index] {
+            Expr::from_numeric(self.variable_list[index].clone()).clone_to_heap()
+        } else {
+            match self.get_expression(key) {
+                Some(previous) => previous,
+                None => return Rc::clone(expr)
+            }
+        };
+        let mut replacements: HashMap<usize, Rc<Expr>> = HashMap::new();
+        replacements.insert(index, previous);
+        return Expr::substitute(expr, &replacements)
     }
 
-    fn parse_string(&mut self, expr_as_string: &String) -> String {
-        let tokenized_string = lexer::tokenize_string(&expr_as_string);
+    // Working precision in significant digits for elementary functions of
+    // exact values, None evaluates them with machine floats
+    pub fn set_precision(&mut self, digits: Option<usize>) {
+        bigfloat::set_precision(digits);
+    }
 
-        let mut postfixed_tokens: Vec<Token> = Vec::new();
-        let mut assignment = false;
-        if tokenized_string.len() > 2 &&
-           tokenized_string[0].is_variable() && 
-           tokenized_string[1].is_assignment() {
-            assignment = true;
-            postfixed_tokens = lexer::infix_to_postfix(&tokenized_string[2..].to_vec());
+    // Handles "precision N", where N = 0 goes back to machine floats and N
+    // is at most bigfloat::MAX_PRECISION. The argument starts at offset in
+    // the input.
+    fn interpret_precision(&mut self, argument: &str, offset: usize) -> Result<EngineOutput, EngineError> {
+        let digits = argument.trim();
+        let start = offset + argument.len() - argument.trim_start().len();
+        match digits.parse::<usize>() {
+            Ok(0) => {
+                self.set_precision(None);
+                return Ok(EngineOutput::Precision(None))
+            },
+            Ok(count) if count > bigfloat::MAX_PRECISION => {
+                let span = Span::new(start, start + digits.len());
+                let message = format!("precision is at most {} digits", bigfloat::MAX_PRECISION);
+                return Err(EngineError::Parse(ParseError::new(message, span)))
+            },
+            Ok(digits) => {
+                self.set_precision(Some(digits));
+                return Ok(EngineOutput::Precision(Some(digits)))
+            },
+            Err(_) => {
+                let span = Span::new(start, start + digits.len());
+                return Err(EngineError::Parse(ParseError::new("precision expects a number of digits".to_string(), span)))
+            }
+        }
+    }
+
+    pub fn set_rule(&mut self, rule: Rule, enabled: bool) {
+        if enabled {
+            self.simplifier.enable(rule);
         } else {
-            postfixed_tokens = lexer::infix_to_postfix(&tokenized_string);
-        }
-        let mut stack: Vec<Rc<Expr>> = Vec::new();
-        for token in postfixed_tokens.iter() {
-            match token {
-                Token::Operator(op) => {
-                    let a = stack.pop().unwrap();
-                    let b = stack.pop().unwrap();
-
-                    match op.op {
-                        OperatorType::Add => stack.push(
-                            Expr::binary_from_heap(&a, &b, BinaryFunction::Add)
-                            .clone_to_heap()
-                        ),
-                        OperatorType::Mul => stack.push(
-                            Expr::binary_from_heap(&a, &b, BinaryFunction::Mul)
-                            .clone_to_heap()
-                        ),
-                        _ => continue
-                    }
-                },
-                Token::Term(term) => {
-                    match term {
-                        TermToken::Number(numeric) => {
-                            stack.push(Expr::from_numeric(*numeric).clone_to_heap())
-                        },
-                        TermToken::VariableKey(key) => {
-                            let index = match self.get_index_of_variable(key) {
-                                Some(index) => {
-                                    index
-                                },
-                                None => {
-                                    self.assign_variable(key, Numeric::from_integer(0))
-                                }
-                            };
-                            stack.push(Expr::from_key(index).clone_to_heap())
+            self.simplifier.disable(rule);
+        }
+    }
+
+    // Handles "enable rule" and "disable rule"
+    fn interpret_toggle(&mut self, argument: &str, offset: usize, enabled: bool) -> Result<EngineOutput, EngineError> {
+        let name = argument.trim();
+        let start = offset + argument.len() - argument.trim_start().len();
+        match Rule::from_name(name) {
+            Some(rule) => {
+                self.set_rule(rule, enabled);
+                return Ok(EngineOutput::Toggled(name.to_string(), enabled))
+            },
+            None => {
+                let span = Span::new(start, start + name.len());
+                return Err(EngineError::Parse(ParseError::new(format!("unknown rule {}", name), span)))
+            }
+        }
+    }
+
+    pub fn add_rule(&mut self, rule: RewriteRule) {
+        self.simplifier.add_rewrite(rule);
+    }
+
+    // Handles "rule pattern -> replacement", where the pattern starts at
+    // offset in the input. The names in the pattern are its variables,
+    // stored as the hidden variables "rule:a", "rule:b", ...
+    fn interpret_rule(&mut self, input: &str, offset: usize) -> Result<EngineOutput, EngineError> {
+        let arrow = match input[offset..].find("->") {
+            Some(arrow) => offset + arrow,
+            None => {
+                let start = input.len() - input[offset..].trim_start().len();
+                let span = Span::new(start, input.trim_end().len());
+                return Err(EngineError::Parse(ParseError::new("rule expects pattern -> replacement".to_string(), span)))
+            }
+        };
+        let pattern = self.parse_part(input, offset, arrow)?;
+        let replacement = self.parse_part(input, arrow + 2, input.len())?;
+
+        let mut names = Vec::new();
+        variable_names(&pattern, &mut names);
+        let mut scope: HashMap<String, usize> = HashMap::new();
+        let mut variables: HashSet<usize> = HashSet::new();
+        for name in names {
+            let index = self.get_or_insert_variable(&format!("rule:{}", name));
+            scope.insert(name, index);
+            variables.insert(index);
+        }
+        let pattern_expr = self.build_expression(&pattern, &scope)?;
+        let pattern_expr = self.cleaned(&pattern_expr);
+        if let Expr::IndepVar(_) = *pattern_expr {
+            return Err(EngineError::Parse(ParseError::new("the pattern of a rule cannot be a single name".to_string(), pattern.span)))
+        }
+        let replacement_expr = self.build_expression(&replacement, &scope)?;
+        let replacement_expr = self.cleaned(&replacement_expr);
+        self.add_rule(RewriteRule::new(Pattern::new(&pattern_expr, variables), &replacement_expr));
+        return Ok(EngineOutput::Defined(format!("rule {} -> {}", input[offset..arrow].trim(), input[arrow + 2..].trim())))
+    }
+
+    fn parse(&self, input: &String) -> Result<Ast, EngineError> {
+        let tokens = {
+            let functions = &self.functions;
+            let defined = defined_name(input);
+            // Any other name followed by a parenthesis multiplies
+            lexer::tokenize_spanned_with(input, &|name| {
+                UnaryFunction::from_name(name).is_some() ||
+                is_command(name) ||
+                functions.contains_key(name) ||
+                defined == Some(name)
+            })?
+        };
+        return Ok(parser::parse_tokens(&tokens, input)?)
+    }
+
+    // Parses input[start..end], blanking out the rest of the input so that
+    // errors point into the whole input
+    fn parse_part(&self, input: &str, start: usize, end: usize) -> Result<Ast, EngineError> {
+        let blanked: String = input[..start].chars().map(|_| ' ').collect();
+        return self.parse(&(blanked + &input[start..end]))
+    }
+
+    pub fn interpret(&mut self, expr_as_string: &String) -> Result<EngineOutput, EngineError> {
+        // The arguments of these commands start at offset in the input
+        if let Some(argument) = command_argument(expr_as_string, "precision") {
+            let offset = expr_as_string.len() - argument.len();
+            return self.interpret_precision(argument, offset)
+        }
+        if let Some(argument) = command_argument(expr_as_string, "enable") {
+            let offset = expr_as_string.len() - argument.len();
+            return self.interpret_toggle(argument, offset, true)
+        }
+        if let Some(argument) = command_argument(expr_as_string, "disable") {
+            let offset = expr_as_string.len() - argument.len();
+            return self.interpret_toggle(argument, offset, false)
+        }
+
+        if let Some(argument) = command_argument(expr_as_string, "rule") {
+            let offset = expr_as_string.len() - argument.len();
+            return self.interpret_rule(expr_as_string, offset)
+        }
+
+        let ast = self.parse(expr_as_string)?;
+
+        match ast.kind {
+            AstKind::Definition(ref name, ref parameters, ref body) => {
+                return self.define_function(name, parameters, body)
+            },
+            AstKind::Assignment(ref key, ref value) => {
+                let result = self.build_expression(value, &HashMap::new())?;
+                let result = self.cleaned(&result);
+                let result = self.inline_previous(key, &result);
+                match self.resolve(&result, &mut vec![key.clone()]) {
+                    Ok(value) => {
+                        // Expressions of variables follow their changes
+                        if result.depends_on_any_variable() {
+                            self.assign_symbolic(key, &result);
+                        } else {
+                            self.assign_variable(key, value.clone());
+                            self.assign_expression(key, &Expr::from_numeric(value.clone()).clone_to_heap());
                         }
-                    }
+                        return Ok(EngineOutput::Value(value))
+                    },
+                    Err(EngineError::Unbound(_)) => {
+                        self.assign_symbolic(key, &result);
+                        return Ok(EngineOutput::Stored(key.clone()))
+                    },
+                    // Kept as an expression so it is evaluated again later
+                    Err(EngineError::Evaluation(error)) => {
+                        self.assign_symbolic(key, &result);
+                        return Err(EngineError::Evaluation(error))
+                    },
+                    Err(error) => return Err(error)
                 }
+            },
+            _ => {
+                let key = "ans".to_string();
+                let result = self.build_expression(&ast, &HashMap::new())?;
+                let result = self.cleaned(&result);
+                let result = self.inline_previous(&key, &result);
+                let output = match ast.kind {
+                    AstKind::Call(ref name, _) if REWRITE_COMMANDS.contains(&name.as_str()) => {
+                        EngineOutput::Expression(self.format_expr(&result))
+                    },
+                    AstKind::Call(ref name, _) if name == "factor" => {
+                        let value = self.resolve(&result, &mut vec![key.clone()])?;
+                        EngineOutput::Expression(factorization(&value).map_err(EngineError::Evaluation)?)
+                    },
+                    // A derivative in names without a value is shown as it is
+                    AstKind::Call(ref name, _) if name == "diff" => {
+                        match self.resolve(&result, &mut vec![key.clone()]) {
+                            Ok(value) => EngineOutput::Value(value),
+                            Err(EngineError::Unbound(_)) => EngineOutput::Expression(self.format_expr(&result)),
+                            Err(error) => return Err(error)
+                        }
+                    },
+                    _ => EngineOutput::Value(self.resolve(&result, &mut vec![key.clone()])?)
+                };
+                // ans is only replaced by a result that succeeded
+                self.assign_symbolic(&key, &result);
+                return Ok(output)
+            }
+        }
+    }
+
+    // The parameters of f(x, y) are stored as the hidden variables "f:x" and
+    // "f:y", a call substitutes its arguments for them
+    fn define_function(&mut self, name: &String, parameters: &Vec<String>, body: &Ast) -> Result<EngineOutput, EngineError> {
+        let mut scope: HashMap<String, usize> = HashMap::new();
+        let mut indices: Vec<usize> = Vec::new();
+        for parameter in parameters.iter() {
+            if scope.contains_key(parameter) {
+                return Err(EngineError::Parse(ParseError::new(format!("parameter {} of {} appears twice", parameter, name), body.span)))
             }
+            let index = self.get_or_insert_variable(&format!("{}:{}", name, parameter));
+            scope.insert(parameter.clone(), index);
+            indices.push(index);
         }
 
-        let result = Rc::clone(&stack[0]);
+        let body = self.build_expression(body, &scope)?;
+        let body = self.cleaned(&body);
+        self.functions.insert(name.clone(), UserFunction { parameters: indices, body });
+        return Ok(EngineOutput::Defined(format!("{}({})", name, parameters.join(", "))))
+    }
 
-        if assignment {
-            let token = tokenized_string[0].clone();
-            let key = match token {
-                Token::Term(term) => {
-                    match term {
-                        TermToken::VariableKey(varkey) => varkey,
-                        _ => "ans".to_string()
+    // Names in scope refer to function parameters instead of engine variables
+    fn build_expression(&mut self, ast: &Ast, scope: &HashMap<String, usize>) -> Result<Rc<Expr>, EngineError> {
+        match ast.kind {
+            AstKind::Number(ref numeric) => return Ok(Expr::from_numeric(numeric.clone()).clone_to_heap()),
+            AstKind::Variable(ref key) => {
+                let index = match scope.get(key) {
+                    Some(index) => *index,
+                    None => match self.get_index_of_variable(key) {
+                        Some(index) => {
+                            index
+                        },
+                        None => {
+                            self.get_or_insert_variable(key)
+                        }
                     }
-                },
-                _ => "ans".to_string()
-            };
+                };
+                return Ok(Expr::from_key(index).clone_to_heap())
+            },
+            AstKind::Neg(ref argument) => return Ok(symexpr_rc::neg(&self.build_expression(argument, scope)?)),
+            AstKind::Binary(op, ref lhs, ref rhs) => {
+                let a = self.build_expression(lhs, scope)?;
+                let b = self.build_expression(rhs, scope)?;
+                match op {
+                    OperatorType::Add => return Ok(symexpr_rc::add(&a, &b)),
+                    OperatorType::Sub => return Ok(symexpr_rc::sub(&a, &b)),
+                    OperatorType::Mul => return Ok(symexpr_rc::mul(&a, &b)),
+                    OperatorType::Div => return Ok(symexpr_rc::div(&a, &b)),
+                    OperatorType::Pow => return Ok(symexpr_rc::pow(&a, &b)),
+                    OperatorType::Mod => return Ok(symexpr_rc::modulo(&a, &b)),
+                    _ => return Err(EngineError::Parse(ParseError::new(format!("{} is not a binary operator", op.symbol()), ast.span)))
+                }
+            },
+            AstKind::Call(ref name, ref arguments) if name == "diff" => {
+                return self.build_derivative(arguments, scope, ast.span)
+            },
+            AstKind::Call(ref name, ref arguments) if name == "simplify" => {
+                if arguments.len() != 1 {
+                    return Err(EngineError::Parse(ParseError::new(format!("simplify takes 1 argument, got {}", arguments.len()), ast.span)))
+                }
+                let expr = self.build_expression(&arguments[0], scope)?;
+                return Ok(self.simplifier.simplify(&self.inline_stored(&expr, None, &mut Vec::new())))
+            },
+            AstKind::Call(ref name, ref arguments) if name == "expand" => {
+                if arguments.len() != 1 {
+                    return Err(EngineError::Parse(ParseError::new(format!("expand takes 1 argument, got {}", arguments.len()), ast.span)))
+                }
+                let expr = self.build_expression(&arguments[0], scope)?;
+                return expand::expand(&self.inline_stored(&expr, None, &mut Vec::new()))
+                    .map_err(EngineError::Evaluation)
+            },
+            AstKind::Call(ref name, ref arguments) if name == "collect" => {
+                if arguments.len() != 2 {
+                    return Err(EngineError::Parse(ParseError::new(format!("collect takes 2 arguments, got {}", arguments.len()), ast.span)))
+                }
+                let key = match self.variable_argument(&arguments[1], scope) {
+                    Some(key) => key,
+                    None => return Err(EngineError::Parse(ParseError::new("collect expects a variable to collect by".to_string(), arguments[1].span)))
+                };
+                let expr = self.build_expression(&arguments[0], scope)?;
+                return Ok(expand::collect(&self.inline_stored(&expr, Some(key), &mut Vec::new()), key))
+            },
+            AstKind::Call(ref name, ref arguments) if POLYNOMIAL_COMMANDS.contains(&name.as_str()) => {
+                return self.build_polynomial_command(name, arguments, scope, ast.span)
+            },
+            AstKind::Call(ref name, ref arguments) if NUMBER_THEORY_FUNCTIONS.contains(&name.as_str()) => {
+                return self.build_number_theory(name, arguments, scope, ast.span)
+            },
+            AstKind::Call(ref name, ref arguments) => {
+                let mut built: Vec<Rc<Expr>> = Vec::new();
+                for argument in arguments.iter() {
+                    built.push(self.build_expression(argument, scope)?);
+                }
+                return self.call_function(name, &built)
+                    .map_err(|message| EngineError::Parse(ParseError::new(message, ast.span)))
+            },
+            _ => return Err(EngineError::Parse(ParseError::new("assignments cannot be nested".to_string(), ast.span)))
+        }
+    }
 
-            if result.depends_on_any_variable() {
-                self.assign_expression(&key, &result);
-            } else {
-                let const_result = result.eval(&self.variable_list);
+    // diff(expr, x) and diff(expr, x, n), the n-th derivative of expr with
+    // respect to the variable x
+    fn build_derivative(&mut self, arguments: &Vec<Ast>, scope: &HashMap<String, usize>, span: Span) -> Result<Rc<Expr>, EngineError> {
+        if arguments.len() != 2 && arguments.len() != 3 {
+            return Err(EngineError::Parse(ParseError::new(format!("diff takes 2 or 3 arguments, got {}", arguments.len()), span)))
+        }
+        let key = match self.variable_argument(&arguments[1], scope) {
+            Some(key) => key,
+            None => return Err(EngineError::Parse(ParseError::new("diff expects a variable to differentiate by".to_string(), arguments[1].span)))
+        };
+        let order = match arguments.get(2).map(|argument| &argument.kind) {
+            None => 1,
+            Some(AstKind::Number(Numeric::Integer(order))) if *order >= 0 => *order,
+            Some(_) => return Err(EngineError::Parse(ParseError::new("the order of diff must be a non-negative integer".to_string(), arguments[2].span)))
+        };
+        let expr = self.build_expression(&arguments[0], scope)?;
+        let expr = self.inline_stored(&expr, Some(key), &mut Vec::new());
+        let mut derivative = self.pool.intern(&expr);
+        for _ in 0..order {
+            derivative = self.pool.derivative(derivative, key);
+        }
+        return Ok(self.pool.get(derivative))
+    }
+
+    fn cleaned(&mut self, expr: &Rc<Expr>) -> Rc<Expr> {
+        Expr::cleaned_with(expr, &mut self.pool)
+    }
 
-                self.assign_variable(&key, const_result);
-                self.assign_expression(&key, &Expr::from_numeric(const_result).clone_to_heap());
+    // quo(a, b) and rem(a, b) of the long division of a by b, gcd(a, b),
+    // sqf(a) and discriminant(a), for polynomials in one variable
+    fn build_polynomial_command(&mut self, name: &str, arguments: &[Ast], scope: &HashMap<String, usize>, span: Span) -> Result<Rc<Expr>, EngineError> {
+        let count = if name == "sqf" || name == "discriminant" { 1 } else { 2 };
+        if arguments.len() != count {
+            let plural = if count == 1 { "argument" } else { "arguments" };
+            return Err(EngineError::Parse(ParseError::new(format!("{} takes {} {}, got {}", name, count, plural, arguments.len()), span)))
+        }
+        let mut exprs = Vec::new();
+        for argument in arguments.iter() {
+            let expr = self.build_expression(argument, scope)?;
+            exprs.push(self.cleaned(&self.inline_stored(&expr, None, &mut Vec::new())));
+        }
+        // The gcd of integers is the one of number theory
+        if name == "gcd" {
+            match (exprs[0].deref(), exprs[1].deref()) {
+                (Expr::Numeric(a), Expr::Numeric(b)) if a.is_integer() && b.is_integer() => {
+                    return numtheory::gcd(a, b)
+                        .map(|gcd| Expr::from_numeric(gcd).move_to_heap())
+                        .map_err(EngineError::Evaluation)
+                },
+                _ => {}
             }
-            return key
+        }
+        let keys: Vec<usize> = (0..self.variable_list.len())
+            .filter(|key| exprs.iter().any(|expr| expr.depends_on_variable(*key)))
+            .collect();
+        let polynomials: Option<Vec<Polynomial>> = if keys.len() > 1 {
+            None
         } else {
-            self.assign_expression(&"ans".to_string(), &result);
+            exprs.iter()
+                .map(|expr| Polynomial::from_expr(expr, &keys))
+                .collect::<Result<Option<Vec<Polynomial>>, NumericError>>()
+                .map_err(EngineError::Evaluation)?
+        };
+        let polynomials = match polynomials {
+            Some(polynomials) => polynomials,
+            None => return Err(EngineError::Parse(ParseError::new(format!("{} expects polynomials in one variable", name), span)))
+        };
+        let zero_divisor = || EngineError::Evaluation(NumericError::DivisionByZero);
+        match name {
+            "quo" => return polynomials[0].div_rem(&polynomials[1]).map(|(quotient, _)| quotient.to_expr()).ok_or_else(zero_divisor),
+            "rem" => return polynomials[0].div_rem(&polynomials[1]).map(|(_, remainder)| remainder.to_expr()).ok_or_else(zero_divisor),
+            "gcd" => {
+                return polynomials[0].gcd(&polynomials[1])
+                    .map(|gcd| gcd.to_expr())
+                    .ok_or_else(|| EngineError::Parse(ParseError::new("gcd expects polynomials in one variable".to_string(), span)))
+            },
+            "sqf" => {
+                let (constant, factors) = match polynomials[0].square_free() {
+                    Some(decomposition) => decomposition,
+                    None => return Ok(Expr::from_integer(0).move_to_heap())
+                };
+                let mut product = vec![Expr::from_numeric(constant).move_to_heap()];
+                for (factor, exponent) in factors {
+                    let exponent = Expr::from_integer(exponent as i64).move_to_heap();
+                    product.push(symexpr_rc::pow(&factor.to_expr(), &exponent));
+                }
+                return Ok(self.cleaned(&symexpr_rc::product(product)))
+            },
+            _ => {
+                match polynomials[0].discriminant() {
+                    Some(discriminant) => return Ok(Expr::from_numeric(discriminant).move_to_heap()),
+                    None => return Err(EngineError::Evaluation(NumericError::Domain("discriminant of a constant polynomial")))
+                }
+            }
+        }
+    }
+
+    // The index of the variable argument names, None if it is not a name
+    fn variable_argument(&mut self, argument: &Ast, scope: &HashMap<String, usize>) -> Option<usize> {
+        match argument.kind {
+            AstKind::Variable(ref name) => {
+                match scope.get(name) {
+                    Some(index) => return Some(*index),
+                    None => return Some(self.get_or_insert_variable(name))
+                }
+            },
+            _ => return None
+        }
+    }
+
+    // expr with every name without a value replaced by the expression
+    // stored under it, so that derivatives and rewrites see through the
+    // names. The variable except is left alone.
+    fn inline_stored(&self, expr: &Rc<Expr>, except: Option<usize>, in_progress: &mut Vec<usize>) -> Rc<Expr> {
+        let mut replacements: HashMap<usize, Rc<Expr>> = HashMap::new();
+        for (name, &index) in self.variable_map.iter() {
+            if self.bound[index] || except == Some(index) || in_progress.contains(&index) || !expr.depends_on_variable(index) {
+                continue;
+            }
+            if let Some(stored) = self.get_expression(name) {
+                in_progress.push(index);
+                replacements.insert(index, self.inline_stored(&stored, except, in_progress));
+                in_progress.pop();
+            }
+        }
+        return Expr::substitute(expr, &replacements)
+    }
+
+    // expr printed with the names of the engine's variables
+    fn format_expr(&self, expr: &Rc<Expr>) -> String {
+        let mut names = vec![String::new(); self.variable_list.len()];
+        for (name, &index) in self.variable_map.iter() {
+            names[index] = name.clone();
+        }
+        return expr.format_with(&|key| names[key].clone())
+    }
+
+    fn call_function(&self, name: &String, arguments: &Vec<Rc<Expr>>) -> Result<Rc<Expr>, String> {
+        if let Some(function) = UnaryFunction::from_name(name) {
+            if arguments.len() != 1 {
+                return Err(format!("{} takes 1 argument, got {}", name, arguments.len()))
+            }
+            return Ok(symexpr_rc::apply(function, &arguments[0]))
+        }
+        match self.functions.get(name) {
+            Some(function) => {
+                if arguments.len() != function.parameters.len() {
+                    return Err(format!("{} takes {} arguments, got {}", name, function.parameters.len(), arguments.len()))
+                }
+                let replacements: HashMap<usize, Rc<Expr>> = function.parameters.iter().cloned()
+                    .zip(arguments.iter().cloned())
+                    .collect();
+                return Ok(Expr::substitute(&function.body, &replacements))
+            },
+            None => return Err(format!("unknown function {}", name))
+        }
+    }
+
+    // lcm(a, b), mod_inverse(a, n), is_prime(n), factor(n) and totient(n),
+    // of the values the arguments have when the call is read. is_prime is
+    // 1 or 0 and factor the number itself, interpret shows the factors.
+    fn build_number_theory(&mut self, name: &str, arguments: &[Ast], scope: &HashMap<String, usize>, span: Span) -> Result<Rc<Expr>, EngineError> {
+        let count = if name == "lcm" || name == "mod_inverse" { 2 } else { 1 };
+        if arguments.len() != count {
+            let plural = if count == 1 { "argument" } else { "arguments" };
+            return Err(EngineError::Parse(ParseError::new(format!("{} takes {} {}, got {}", name, count, plural, arguments.len()), span)))
+        }
+        let mut values = Vec::new();
+        for argument in arguments.iter() {
+            let expr = self.build_expression(argument, scope)?;
+            let expr = self.cleaned(&self.inline_stored(&expr, None, &mut Vec::new()));
+            values.push(self.resolve(&expr, &mut Vec::new())?);
         }
+        let result = match name {
+            "lcm" => numtheory::lcm(&values[0], &values[1]),
+            "mod_inverse" => numtheory::mod_inverse(&values[0], &values[1]),
+            "is_prime" => numtheory::is_prime(&values[0]).map(|prime| Numeric::from_integer(prime as i64)),
+            "factor" => numtheory::factorize(&values[0]).map(|_| values[0].clone()),
+            _ => numtheory::totient(&values[0])
+        };
+        return result
+            .map(|value| Expr::from_numeric(value).move_to_heap())
+            .map_err(EngineError::Evaluation)
+    }
+}
+
+// The prime factors of an integer with their multiplicities, 12 is 2^2*3
+fn factorization(value: &Numeric) -> Result<String, NumericError> {
+    let factors: Vec<String> = numtheory::factorize(value)?.into_iter()
+        .map(|(prime, multiplicity)| match multiplicity {
+            1 => prime.to_string(),
+            _ => format!("{}^{}", prime, multiplicity)
+        })
+        .collect();
+    if factors.is_empty() {
+        return Ok(value.to_string())
+    }
+    return Ok(factors.join("*"))
+}
+
+// Names handled by the engine instead of a function or variable
+fn is_command(name: &str) -> bool {
+    name == "diff" || REWRITE_COMMANDS.contains(&name) || NUMBER_THEORY_FUNCTIONS.contains(&name)
+}
+
+// The name f of a definition f(x, y) = body at the start of input, which
+// is a call although f is not defined yet
+fn defined_name(input: &str) -> Option<&str> {
+    let input = input.trim_start();
+    let length = input.find(|ch: char| !(ch.is_alphanumeric() || ch == '_')).unwrap_or(input.len());
+    let (name, rest) = input.split_at(length);
+    if name.is_empty() || !rest.starts_with('(') || !rest.contains('=') {
+        return None
+    }
+    return Some(name)
+}
+
+// Names of the variables in ast, each once in order of appearance
+fn variable_names(ast: &Ast, names: &mut Vec<String>) {
+    match ast.kind {
+        AstKind::Variable(ref name) if !names.contains(name) => names.push(name.clone()),
+        AstKind::Neg(ref argument) => variable_names(argument, names),
+        AstKind::Binary(_, ref lhs, ref rhs) => {
+            variable_names(lhs, names);
+            variable_names(rhs, names);
+        },
+        AstKind::Call(_, ref arguments) => {
+            for argument in arguments.iter() {
+                variable_names(argument, names);
+            }
+        },
+        _ => {}
+    }
+}
 
-        return "ans".to_string()
+// The rest of the input after command, if the input starts with it. Not a
+// variable whose name starts with the command, nor an assignment to a
+// variable named like it.
+fn command_argument<'a>(input: &'a str, command: &str) -> Option<&'a str> {
+    let argument = input.trim_start().strip_prefix(command)?;
+    if argument.trim_start().starts_with('=') {
+        return None
+    }
+    if argument.trim().is_empty() || argument.starts_with(char::is_whitespace) {
+        return Some(argument)
     }
+    return None
+}
+
+#[cfg(test)]
+fn run(engine: &mut Engine, input: &str) -> Result<EngineOutput, EngineError> {
+    engine.interpret(&String::from(input))
+}
+
+#[cfg(test)]
+fn value(number: i64) -> Result<EngineOutput, EngineError> {
+    Ok(EngineOutput::Value(Numeric::from_integer(number)))
 }
 
 #[cfg(test)]
 #[test]
 fn test_symengine_assignment_and_evaluation() {
     let mut engine = Engine::new();
-    let key = String::from("x");
     assert_eq!(engine.get_variable(&"x".to_string()), None);
     let x_index = engine.assign_variable(&"x".to_string(), Numeric::from_integer(0));
     assert_eq!(engine.get_index_of_variable(&"x".to_string()), Some(x_index));
@@ -195,9 +855,9 @@ fn test_symengine_assignment_and_evaluation() {
     assert_eq!(engine.get_expression(&"f".to_string()), None);
     let f_index = engine.assign_expression(&"f".to_string(), &Expr::from_key(x_index).clone_to_heap());
     assert_eq!(engine.get_index_of_expression(&"f".to_string()), Some(f_index));
-    assert_eq!(engine.eval_expr(&"f".to_string()), Some(Numeric::from_integer(0)));
+    assert_eq!(engine.eval_expr(&"f".to_string()), Ok(Numeric::from_integer(0)));
     let custom_values: Vec<Numeric> = vec![Numeric::from_real(2.)];
-    assert_eq!(engine.eval_expr_with(&"f".to_string(), &custom_values), Some(Numeric::from_real(2.)));
+    assert_eq!(engine.eval_expr_with(&"f".to_string(), &custom_values), Some(Ok(Numeric::from_real(2.))));
 }
 
 #[test]
@@ -205,4 +865,266 @@ fn test_parser() {
     let mut engine = Engine::new();
     engine.assign_variable(&"x".to_string(), Numeric::from_integer(2));
 
-}
\ No newline at end of file
+    assert_eq!(run(&mut engine, "y = 17 % 5 * x"), value(4));
+    assert_eq!(run(&mut engine, "x * 17 % 5"), value(4));
+    assert_eq!(run(&mut engine, "rate = 7 mod 4"), value(3));
+    assert_eq!(engine.get_variable(&"rate".to_string()), Some(Numeric::from_integer(3)));
+    assert_eq!(run(&mut engine, "rate * x"), value(6));
+    assert_eq!(run(&mut engine, "-x^2 + 10 / 4 - 1"),
+               Ok(EngineOutput::Value(Numeric::from_rational(::rational::Rational::new(-5, 2)))));
+    assert_eq!(run(&mut engine, "2^3^2 mod 1000"), value(512));
+    assert!(run(&mut engine, "3^1000000 mod 1000007").is_ok());
+    assert_eq!(run(&mut engine, "(x + 3)^-1 mod 7"), value(3));
+    assert_eq!(run(&mut engine, "x % 0"), Err(EngineError::Evaluation(NumericError::DivisionByZero)));
+}
+
+#[test]
+fn test_precision_command() {
+    let mut engine = Engine::new();
+
+    assert_eq!(run(&mut engine, "precision 40"), Ok(EngineOutput::Precision(Some(40))));
+    assert_eq!(bigfloat::precision(), Some(40));
+    let error = run(&mut engine, "  precision x1").unwrap_err();
+    assert_eq!(error.span(), Some(Span::new(12, 14)));
+    assert_eq!(bigfloat::precision(), Some(40));
+    let error = run(&mut engine, "precision 1000000000").unwrap_err();
+    assert_eq!(error.to_string(), "precision is at most 1000 digits");
+    assert_eq!(error.span(), Some(Span::new(10, 20)));
+    assert_eq!(bigfloat::precision(), Some(40));
+    assert_eq!(run(&mut engine, "precision 0"), Ok(EngineOutput::Precision(None)));
+    assert_eq!(bigfloat::precision(), None);
+    // Unless it is a variable
+    assert_eq!(run(&mut engine, "precision = 3"), value(3));
+    assert_eq!(run(&mut engine, "2precision"), value(6));
+    assert_eq!(bigfloat::precision(), None);
+}
+
+#[test]
+fn test_function_calls() {
+    let mut engine = Engine::new();
+    engine.assign_variable(&"x".to_string(), Numeric::from_integer(2));
+
+    assert_eq!(run(&mut engine, "sin(0) + exp(0)"), Ok(EngineOutput::Value(Numeric::from_real(1.))));
+    // x is a variable, so x(3) multiplies
+    assert_eq!(run(&mut engine, "x(3) + 2(x)"), value(10));
+
+    assert_eq!(run(&mut engine, "f(a, b) = a + b^2"), Ok(EngineOutput::Defined("f(a, b)".to_string())));
+    assert_eq!(run(&mut engine, "f(x, 3) * 2"), value(22));
+    assert_eq!(run(&mut engine, "g = f(x - 1, x)"), value(5));
+    // Free variables of the body are looked up when evaluated
+    run(&mut engine, "h(t) = t * x").unwrap();
+    assert_eq!(run(&mut engine, "h(5)"), value(10));
+    engine.assign_variable(&"x".to_string(), Numeric::from_integer(4));
+    assert_eq!(engine.eval_expr(&"ans".to_string()), Ok(Numeric::from_integer(20)));
+
+    // Names that are not functions multiply, whatever was typed before
+    assert_eq!(run(&mut engine, "unknown(1)"), Err(EngineError::Unbound("unknown".to_string())));
+    assert_eq!(run(&mut engine, "unknown = 3"), value(3));
+    assert_eq!(run(&mut engine, "unknown(1)"), value(3));
+    assert!(run(&mut engine, "f(1)").is_err());
+    assert!(run(&mut engine, "sin(1, 2)").is_err());
+}
+
+#[test]
+fn test_expression_pool() {
+    let mut engine = Engine::new();
+
+    // Cleanups are remembered by the engine's pool from one command to the next
+    assert_eq!(run(&mut engine, "y = (x + 0) * 1 + sin(x * 1)^2"), Ok(EngineOutput::Stored("y".to_string())));
+    let size = engine.pool.len();
+    assert_eq!(run(&mut engine, "z = (x + 0) * 1 + sin(x * 1)^2"), Ok(EngineOutput::Stored("z".to_string())));
+    assert_eq!(engine.pool.len(), size);
+}
+
+#[test]
+fn test_engine_errors() {
+    let mut engine = Engine::new();
+
+    assert_eq!(run(&mut engine, "1 + $"), Err(EngineError::Lex(LexError { character: '$', span: Span::new(4, 5), numeric: None })));
+    assert_eq!(run(&mut engine, "+").unwrap_err().span(), Some(Span::new(0, 1)));
+    assert_eq!(run(&mut engine, "nothing"), Err(EngineError::Unbound("nothing".to_string())));
+    assert_eq!(engine.eval_expr(&"missing".to_string()), Err(EngineError::Unbound("missing".to_string())));
+
+    // Names without a value are resolved through their stored expressions
+    assert_eq!(run(&mut engine, "y = x + 1"), Ok(EngineOutput::Stored("y".to_string())));
+    assert_eq!(run(&mut engine, "z = 2y"), Ok(EngineOutput::Stored("z".to_string())));
+    assert_eq!(run(&mut engine, "x = 3"), value(3));
+    assert_eq!(run(&mut engine, "z"), value(8));
+    assert_eq!(run(&mut engine, "ans + 1"), value(9));
+    assert_eq!(run(&mut engine, "x = x + 1"), value(4));
+    assert_eq!(run(&mut engine, "z"), value(10));
+    // A failing command leaves ans alone
+    assert_eq!(run(&mut engine, "1/0"), Err(EngineError::Evaluation(NumericError::DivisionByZero)));
+    assert_eq!(run(&mut engine, "ans * 2"), value(20));
+    assert_eq!(run(&mut engine, "u = (1/0)^0"), Err(EngineError::Evaluation(NumericError::DivisionByZero)));
+
+    assert_eq!(run(&mut engine, "w = v"), Ok(EngineOutput::Stored("w".to_string())));
+    assert_eq!(run(&mut engine, "v = w + 1"), Err(EngineError::Cycle("v".to_string())));
+    assert_eq!(run(&mut engine, "w"), Err(EngineError::Unbound("v".to_string())));
+}
+
+#[test]
+fn test_number_theory_functions() {
+    let mut engine = Engine::new();
+    let shown = |text: &str| Ok(EngineOutput::Expression(text.to_string()));
+
+    assert_eq!(run(&mut engine, "x = 5"), value(5));
+    assert_eq!(run(&mut engine, "x^-1 mod 7"), value(3));
+    assert_eq!(run(&mut engine, "lcm(4, 6)"), value(12));
+    assert_eq!(run(&mut engine, "lcm(x, 4) + 1"), value(21));
+    assert_eq!(run(&mut engine, "mod_inverse(x, 7)"), value(3));
+    assert_eq!(run(&mut engine, "is_prime(97) + is_prime(91)"), value(1));
+    assert_eq!(run(&mut engine, "totient(36)"), value(12));
+    // factor shows the factorization, and is the number inside expressions
+    assert_eq!(run(&mut engine, "factor(360)"), shown("2^3*3^2*5"));
+    assert_eq!(run(&mut engine, "factor(-7)"), shown("-1*7"));
+    assert_eq!(run(&mut engine, "factor(12) + 1"), value(13));
+
+    assert_eq!(run(&mut engine, "2^-1 mod 4").unwrap_err().to_string(), "domain error: no modular inverse, arguments are not coprime");
+    assert_eq!(run(&mut engine, "lcm(4)").unwrap_err().to_string(), "lcm takes 2 arguments, got 1");
+    assert_eq!(run(&mut engine, "totient(0)").unwrap_err().to_string(), "domain error: totient of a non-positive integer");
+    assert_eq!(run(&mut engine, "factor(1/2)").unwrap_err().to_string(), "domain error: number theory expects integer arguments");
+    assert_eq!(run(&mut engine, "lcm(q, 4)"), Err(EngineError::Unbound("q".to_string())));
+    assert_eq!(run(&mut engine, "mod_inverse(2, 4)"), Err(EngineError::Evaluation(NumericError::Domain("no modular inverse, arguments are not coprime"))));
+}
+
+#[test]
+fn test_diff_command() {
+    let mut engine = Engine::new();
+
+    assert_eq!(run(&mut engine, "x = 3"), value(3));
+    assert_eq!(run(&mut engine, "diff(x^2, x)"), value(6));
+    assert_eq!(run(&mut engine, "diff(x^3 + x, x, 2)"), value(18));
+    assert_eq!(run(&mut engine, "diff(x^3, x, 0)"), value(27));
+    assert_eq!(run(&mut engine, "diff(x^3, x, 4)"), value(0));
+
+    // Derivatives follow the variables they depend on
+    assert_eq!(run(&mut engine, "d = diff(x^2 * y, x)"), Ok(EngineOutput::Stored("d".to_string())));
+    assert_eq!(run(&mut engine, "y = 2"), value(2));
+    assert_eq!(run(&mut engine, "d"), value(12));
+    assert_eq!(run(&mut engine, "x = 5"), value(5));
+    assert_eq!(run(&mut engine, "d"), value(20));
+
+    // Stored expressions are differentiated through their names
+    assert_eq!(run(&mut engine, "u = t^2 + 4t"), Ok(EngineOutput::Stored("u".to_string())));
+    assert_eq!(run(&mut engine, "w = u^2"), Ok(EngineOutput::Stored("w".to_string())));
+    assert_eq!(run(&mut engine, "t = 1"), value(1));
+    assert_eq!(run(&mut engine, "diff(u, t)"), value(6));
+    assert_eq!(run(&mut engine, "diff(w, t)"), value(60));
+    assert_eq!(run(&mut engine, "g(s) = diff(s^3, s)"), Ok(EngineOutput::Defined("g(s)".to_string())));
+    assert_eq!(run(&mut engine, "g(2)"), value(12));
+
+    assert_eq!(run(&mut engine, "diff(v^3, v)"), Ok(EngineOutput::Expression("3*v^2".to_string())));
+    // Derivatives are remembered by the engine's pool from one command to the next
+    run(&mut engine, "diff(sin(v)^3 * exp(v), v, 4)").unwrap();
+    let size = engine.pool.len();
+    run(&mut engine, "diff(sin(v)^3 * exp(v), v, 4)").unwrap();
+    assert_eq!(engine.pool.len(), size);
+    assert_eq!(run(&mut engine, "diff(x)").unwrap_err().to_string(), "diff takes 2 or 3 arguments, got 1");
+    assert_eq!(run(&mut engine, "diff(x^2, 2)").unwrap_err().span(), Some(Span::new(10, 11)));
+    assert_eq!(run(&mut engine, "diff(x^2, x, -1)").unwrap_err().to_string(), "the order of diff must be a non-negative integer");
+}
+
+#[test]
+fn test_simplify_command() {
+    let mut engine = Engine::new();
+    let shown = |text: &str| Ok(EngineOutput::Expression(text.to_string()));
+
+    assert_eq!(run(&mut engine, "simplify(x*(x + y) - x*y)"), shown("x^2"));
+    assert_eq!(run(&mut engine, "simplify(sin(t)^2 + cos(t)^2)"), shown("1"));
+    assert_eq!(run(&mut engine, "simplify((a/b) / (a/c))"), shown("c/b"));
+    assert_eq!(run(&mut engine, "u = (x + 1)*(x - 1)"), Ok(EngineOutput::Stored("u".to_string())));
+    assert_eq!(run(&mut engine, "simplify(u)"), shown("x^2 - 1"));
+
+    // Simplified expressions evaluate like any other
+    assert_eq!(run(&mut engine, "x = 3"), value(3));
+    assert_eq!(run(&mut engine, "simplify(u) + 1"), value(9));
+    assert_eq!(run(&mut engine, "ans"), value(9));
+
+    assert_eq!(run(&mut engine, "disable distribution"), Ok(EngineOutput::Toggled("distribution".to_string(), false)));
+    assert_eq!(run(&mut engine, "simplify(y*(y + 1))"), shown("y*(1 + y)"));
+    assert_eq!(run(&mut engine, "enable distribution"), Ok(EngineOutput::Toggled("distribution".to_string(), true)));
+    assert_eq!(run(&mut engine, "simplify(y*(y + 1))"), shown("y + y^2"));
+
+    assert_eq!(run(&mut engine, "disable nothing").unwrap_err().span(), Some(Span::new(8, 15)));
+    assert_eq!(run(&mut engine, "simplify(x, y)").unwrap_err().to_string(), "simplify takes 1 argument, got 2");
+    // enable and disable are variables when assigned to
+    assert_eq!(run(&mut engine, "enable = 3"), value(3));
+    assert_eq!(run(&mut engine, "disable=enable + 1"), value(4));
+}
+
+#[test]
+fn test_rule_command() {
+    let mut engine = Engine::new();
+    let shown = |expr: &str| Ok(EngineOutput::Expression(expr.to_string()));
+    assert_eq!(run(&mut engine, "rule cosh(a)^2 - sinh(a)^2 -> 1"),
+               Ok(EngineOutput::Defined("rule cosh(a)^2 - sinh(a)^2 -> 1".to_string())));
+    assert_eq!(run(&mut engine, "simplify(3 + cosh(x*y)^2 - sinh(x*y)^2)"), shown("4"));
+    // Sums and products match in any order and in part
+    run(&mut engine, "rule ln(a) + ln(b) -> ln(a*b)").unwrap();
+    assert_eq!(run(&mut engine, "simplify(ln(x) + 2 + ln(y) + ln(z))"), shown("2 + ln(x*y*z)"));
+    run(&mut engine, "rule sin(a)*cos(a) -> sin(2a)/2").unwrap();
+    assert_eq!(run(&mut engine, "simplify(4*cos(t)*sin(t))"), shown("2*sin(2*t)"));
+    // The pattern variables do not touch the engine's variables
+    run(&mut engine, "a = 5").unwrap();
+    assert_eq!(run(&mut engine, "simplify(cosh(a)^2 - sinh(a)^2)"), shown("1"));
+    assert_eq!(run(&mut engine, "a"), value(5));
+
+    let error = run(&mut engine, "rule a -> 1").unwrap_err();
+    assert_eq!(error.to_string(), "the pattern of a rule cannot be a single name");
+    let error = run(&mut engine, "rule sin(a) 3").unwrap_err();
+    assert_eq!(error.span(), Some(Span::new(5, 13)));
+    let error = run(&mut engine, "rule sin(a) -> )").unwrap_err();
+    assert_eq!(error.span(), Some(Span::new(15, 16)));
+    // rule, enable and disable are variables when assigned to
+    assert_eq!(run(&mut engine, "rule = 3"), value(3));
+    assert_eq!(run(&mut engine, "enable=rule + 1"), value(4));
+    assert_eq!(run(&mut engine, "disable = enable * rule"), value(12));
+}
+
+#[test]
+fn test_expand_and_collect_commands() {
+    let mut engine = Engine::new();
+    let shown = |expr: &str| Ok(EngineOutput::Expression(expr.to_string()));
+    assert_eq!(run(&mut engine, "expand(k(m + n))"), shown("k*m + k*n"));
+    assert_eq!(run(&mut engine, "expand((x + 1)^3)"), shown("1 + x^3 + 3*x + 3*x^2"));
+    assert_eq!(run(&mut engine, "expand(exp(x + 2y))"), shown("exp(x)*exp(2*y)"));
+    assert_eq!(run(&mut engine, "collect(expand((x + a)*(x + b)), x)"), shown("x^2 + x*(a + b) + a*b"));
+    // Stored expressions are expanded through their names
+    run(&mut engine, "u = (t - 1)*(t + 1)").unwrap();
+    assert_eq!(run(&mut engine, "expand(u)"), shown("t^2 - 1"));
+    run(&mut engine, "t = 3").unwrap();
+    assert_eq!(run(&mut engine, "expand(u) + 1"), value(9));
+
+    assert_eq!(run(&mut engine, "expand(x, y)").unwrap_err().to_string(), "expand takes 1 argument, got 2");
+    assert_eq!(run(&mut engine, "expand((x + 1)^4000000000)"), Err(EngineError::Evaluation(NumericError::Overflow)));
+    assert_eq!(run(&mut engine, "collect(x)").unwrap_err().to_string(), "collect takes 2 arguments, got 1");
+    let error = run(&mut engine, "collect(x, 2)").unwrap_err();
+    assert_eq!(error.to_string(), "collect expects a variable to collect by");
+    assert_eq!(error.span(), Some(Span::new(11, 12)));
+}
+
+#[test]
+fn test_polynomial_commands() {
+    let mut engine = Engine::new();
+    let shown = |expr: &str| Ok(EngineOutput::Expression(expr.to_string()));
+    assert_eq!(run(&mut engine, "quo(x^3 - 2x + 5, 2x + 1)"), shown("1/2*x^2 - 7/8 - 1/4*x"));
+    assert_eq!(run(&mut engine, "rem(x^3 - 2x + 5, 2x + 1)"), shown("47/8"));
+    assert_eq!(run(&mut engine, "gcd(x^3 - x^2 - x + 1, 3x^2 - 3)"), shown("x^2 - 1"));
+    assert_eq!(run(&mut engine, "gcd(12, 18)"), shown("6"));
+    assert_eq!(run(&mut engine, "sqf(2x^4 - 6x^3 + 6x^2 - 2x)"), shown("2*x*(x - 1)^3"));
+    assert_eq!(run(&mut engine, "discriminant(x^2 - 2)"), shown("8"));
+    // Stored expressions are polynomials through their names
+    run(&mut engine, "p = (t - 1)^2*(t + 3)").unwrap();
+    assert_eq!(run(&mut engine, "gcd(p, t^2 - 1)"), shown("t - 1"));
+    run(&mut engine, "t = 2").unwrap();
+    assert_eq!(run(&mut engine, "quo(p, t - 1) + 1"), value(6));
+
+    assert_eq!(run(&mut engine, "quo(x)").unwrap_err().to_string(), "quo takes 2 arguments, got 1");
+    assert_eq!(run(&mut engine, "sqf(x, x)").unwrap_err().to_string(), "sqf takes 1 argument, got 2");
+    assert_eq!(run(&mut engine, "rem(x, 0)"), Err(EngineError::Evaluation(NumericError::DivisionByZero)));
+    assert_eq!(run(&mut engine, "quo(x*y, x)").unwrap_err().to_string(), "quo expects polynomials in one variable");
+    assert_eq!(run(&mut engine, "gcd(sin(x), x)").unwrap_err().to_string(), "gcd expects polynomials in one variable");
+    assert_eq!(run(&mut engine, "discriminant(5)").unwrap_err().to_string(), "domain error: discriminant of a constant polynomial");
+    assert_eq!(run(&mut engine, "sqf(x^4000000000)"), Err(EngineError::Evaluation(NumericError::Overflow)));
+}
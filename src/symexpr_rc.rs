@@ -1,43 +1,103 @@
 #![allow(dead_code)]
 
+use std::cmp::Ordering;
 use std::rc::Rc;
 use std::collections::HashMap;
 use std::ops::{Deref};
-use numeric::{Numeric, RealType, ComplexType, IntegerType};
+use numeric::{Numeric, NumericError, NumericLevel, RealType, ComplexType, IntegerType};
+use interval::Interval;
+use numtheory;
 
 type ExprMap = HashMap<String, Rc<Expr>>;
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Expr {
     Numeric(Numeric),
     IndepVar(usize),
     Unary(UnaryExpr),
-    Binary(BinaryExpr)
+    Binary(BinaryExpr),
+    Nary(NaryExpr)
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub enum UnaryFunction {
-    Neg, Exp
+    Neg, Exp, Ln, Sqrt,
+    Sin, Cos, Tan, Asin, Acos, Atan,
+    Sinh, Cosh, Tanh
 }
 
-#[derive(Clone, PartialEq, Debug)]
+impl UnaryFunction {
+    // Built-in function called by name, negation has no name
+    pub fn from_name(name: &str) -> Option<UnaryFunction> {
+        match name {
+            "exp" => Some(UnaryFunction::Exp),
+            "ln" => Some(UnaryFunction::Ln),
+            "sqrt" => Some(UnaryFunction::Sqrt),
+            "sin" => Some(UnaryFunction::Sin),
+            "cos" => Some(UnaryFunction::Cos),
+            "tan" => Some(UnaryFunction::Tan),
+            "asin" => Some(UnaryFunction::Asin),
+            "acos" => Some(UnaryFunction::Acos),
+            "atan" => Some(UnaryFunction::Atan),
+            "sinh" => Some(UnaryFunction::Sinh),
+            "cosh" => Some(UnaryFunction::Cosh),
+            "tanh" => Some(UnaryFunction::Tanh),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            UnaryFunction::Neg => None,
+            UnaryFunction::Exp => Some("exp"),
+            UnaryFunction::Ln => Some("ln"),
+            UnaryFunction::Sqrt => Some("sqrt"),
+            UnaryFunction::Sin => Some("sin"),
+            UnaryFunction::Cos => Some("cos"),
+            UnaryFunction::Tan => Some("tan"),
+            UnaryFunction::Asin => Some("asin"),
+            UnaryFunction::Acos => Some("acos"),
+            UnaryFunction::Atan => Some("atan"),
+            UnaryFunction::Sinh => Some("sinh"),
+            UnaryFunction::Cosh => Some("cosh"),
+            UnaryFunction::Tanh => Some("tanh")
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub enum BinaryFunction {
+    Div, Pow, Mod
+}
+
+// Sums and products take any number of operands
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum NaryFunction {
     Add, Mul
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct UnaryExpr {
     function: UnaryFunction,
     argument: Rc<Expr>
 }
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct BinaryExpr {
     function: BinaryFunction,
     lhs: Rc<Expr>,
     rhs: Rc<Expr>
 }
 
+// Built by add and mul, the operands of a canonical sum or product are
+// flat and sorted, with numbers folded into a single constant or
+// coefficient and like terms or powers combined
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct NaryExpr {
+    function: NaryFunction,
+    operands: Vec<Rc<Expr>>
+}
+
 impl Expr {
     pub fn zero() -> Expr {
         Expr::from_integer(0)
@@ -85,6 +145,17 @@ impl Expr {
         }
     }
 
+    // Position of the variant in the total order
+    fn rank(&self) -> u8 {
+        match self {
+            Expr::Numeric(_) => return 0,
+            Expr::IndepVar(_) => return 1,
+            Expr::Unary(_) => return 2,
+            Expr::Binary(_) => return 3,
+            Expr::Nary(_) => return 4
+        }
+    }
+
     pub fn unary_from_heap(argument: &Rc<Expr>, function: UnaryFunction) -> Expr {
         Expr::Unary(
             UnaryExpr{
@@ -123,12 +194,43 @@ impl Expr {
         )
     }
 
-    pub fn eval(&self, values: &Vec<Numeric>) -> Numeric {
+    // Operands as given, without bringing them to canonical form
+    pub fn nary_from_heap(operands: &[Rc<Expr>], function: NaryFunction) -> Expr {
+        Expr::Nary(
+            NaryExpr{
+                function: function,
+                operands: operands.to_vec()
+            }
+        )
+    }
+
+    pub fn nary_from(operands: &[Expr], function: NaryFunction) -> Expr {
+        Expr::Nary(
+            NaryExpr{
+                function: function,
+                operands: operands.iter().map(Expr::clone_to_heap).collect()
+            }
+        )
+    }
+
+    pub fn eval(&self, values: &Vec<Numeric>) -> Result<Numeric, NumericError> {
         match self {
-            Expr::Numeric(numeric) => return numeric.clone(),
-            Expr::IndepVar(key) => return values[*key],
+            Expr::Numeric(numeric) => return Ok(numeric.clone()),
+            Expr::IndepVar(key) => return Ok(values[*key].clone()),
             Expr::Unary(expr) => return expr.eval(values),
-            Expr::Binary(expr) => return expr.eval(values)
+            Expr::Binary(expr) => return expr.eval(values),
+            Expr::Nary(expr) => return expr.eval(values)
+        }
+    }
+
+    // Guaranteed enclosure of the value over the given variable intervals
+    pub fn eval_interval(&self, values: &Vec<Interval>) -> Result<Interval, NumericError> {
+        match self {
+            Expr::Numeric(numeric) => return Interval::from_numeric(numeric),
+            Expr::IndepVar(key) => return Ok(values[*key]),
+            Expr::Unary(expr) => return expr.eval_interval(values),
+            Expr::Binary(expr) => return expr.eval_interval(values),
+            Expr::Nary(expr) => return expr.eval_interval(values)
         }
     }
 
@@ -138,6 +240,7 @@ impl Expr {
             Expr::Unary(expr) => return expr.argument.depends_on_any_variable(),
             Expr::Binary(expr) => return expr.lhs.depends_on_any_variable() ||
                                          expr.rhs.depends_on_any_variable(),
+            Expr::Nary(expr) => return expr.operands.iter().any(|operand| operand.depends_on_any_variable()),
             _ => return false
         }
     }
@@ -148,63 +251,762 @@ impl Expr {
             Expr::Unary(expr) => return expr.argument.depends_on_variable(key),
             Expr::Binary(expr) => return expr.lhs.depends_on_variable(key) ||
                                          expr.rhs.depends_on_variable(key),
+            Expr::Nary(expr) => return expr.operands.iter().any(|operand| operand.depends_on_variable(key)),
             _ => return false
         }
     }
 
-    fn trim(expr: &Rc<Expr>) -> Rc<Expr> {
+    // Replaces the variables with keys in replacements, sharing every
+    // subexpression that does not contain one of them
+    pub fn substitute(expr: &Rc<Expr>, replacements: &HashMap<usize, Rc<Expr>>) -> Rc<Expr> {
         match expr.deref() {
-            Expr::Unary(unary_exp) => {
-                Expr::trim(&unary_exp.argument)
+            Expr::IndepVar(key) => {
+                match replacements.get(key) {
+                    Some(replacement) => return Rc::clone(replacement),
+                    None => return Rc::clone(expr)
+                }
+            },
+            Expr::Unary(unary_expr) => {
+                let argument = Expr::substitute(&unary_expr.argument, replacements);
+                if Rc::ptr_eq(&argument, &unary_expr.argument) {
+                    return Rc::clone(expr)
+                }
+                if unary_expr.function == UnaryFunction::Neg {
+                    return neg(&argument)
+                }
+                return Expr::unary_from_heap(&argument, unary_expr.function.clone()).move_to_heap()
             },
             Expr::Binary(binary_expr) => {
-                match binary_expr.trim() {
-                    Some(binary_rc) => return binary_rc,
-                    None => return Rc::clone(expr)
+                let lhs = Expr::substitute(&binary_expr.lhs, replacements);
+                let rhs = Expr::substitute(&binary_expr.rhs, replacements);
+                if Rc::ptr_eq(&lhs, &binary_expr.lhs) && Rc::ptr_eq(&rhs, &binary_expr.rhs) {
+                    return Rc::clone(expr)
                 }
+                return Expr::binary_from_heap(&lhs, &rhs, binary_expr.function.clone()).move_to_heap()
+            },
+            Expr::Nary(nary_expr) => {
+                let operands: Vec<Rc<Expr>> = nary_expr.operands.iter()
+                    .map(|operand| Expr::substitute(operand, replacements))
+                    .collect();
+                if operands.iter().zip(&nary_expr.operands).all(|(new, old)| Rc::ptr_eq(new, old)) {
+                    return Rc::clone(expr)
+                }
+                return HeapBuilder::new().nary(nary_expr.function.clone(), operands)
             },
             _ => return Rc::clone(expr)
         }
     }
+
+    // Applies the cleanup rules bottom-up, such as 0 + a = a, 1 * a = a,
+    // a / a = 1, a^1 = a and -(-a) = a, and folds sums, products and
+    // quotients of exact numbers. Unchanged subexpressions are shared with
+    // expr.
+    pub fn cleaned(expr: &Rc<Expr>) -> Rc<Expr> {
+        Expr::cleaned_with(expr, &mut HeapBuilder::new())
+    }
+
+    // Cleanup building its nodes through builder
+    pub fn cleaned_with(expr: &Rc<Expr>, builder: &mut dyn Builder) -> Rc<Expr> {
+        if let Some(result) = builder.recall(Operation::Cleaned, expr) {
+            return result
+        }
+        let result = match expr.deref() {
+            Expr::Unary(unary_expr) => {
+                let argument = Expr::cleaned_with(&unary_expr.argument, builder);
+                if unary_expr.function == UnaryFunction::Neg {
+                    shared(expr, builder.neg(&argument))
+                } else if Rc::ptr_eq(&argument, &unary_expr.argument) {
+                    Rc::clone(expr)
+                } else {
+                    builder.apply(unary_expr.function.clone(), &argument)
+                }
+            },
+            Expr::Binary(binary_expr) => {
+                let lhs = Expr::cleaned_with(&binary_expr.lhs, builder);
+                let rhs = Expr::cleaned_with(&binary_expr.rhs, builder);
+                let cleaned = BinaryExpr { function: binary_expr.function.clone(), lhs, rhs };
+                match cleaned.trim(builder) {
+                    Some(result) => result,
+                    None if Rc::ptr_eq(&cleaned.lhs, &binary_expr.lhs) && Rc::ptr_eq(&cleaned.rhs, &binary_expr.rhs) => {
+                        Rc::clone(expr)
+                    },
+                    None => builder.build(Expr::Binary(cleaned))
+                }
+            },
+            Expr::Nary(nary_expr) => {
+                let operands = nary_expr.operands.iter()
+                    .map(|operand| Expr::cleaned_with(operand, builder))
+                    .collect();
+                shared(expr, builder.nary(nary_expr.function.clone(), operands))
+            },
+            _ => Rc::clone(expr)
+        };
+        builder.remember(Operation::Cleaned, expr, &result);
+        return result
+    }
+
+    // Derivative with respect to the variable key, cleaned up
+    pub fn derivative(expr: &Rc<Expr>, key: usize) -> Rc<Expr> {
+        Expr::derivative_with(expr, key, &mut HeapBuilder::new())
+    }
+
+    // Derivative building its nodes through builder
+    pub fn derivative_with(expr: &Rc<Expr>, key: usize, builder: &mut dyn Builder) -> Rc<Expr> {
+        if let Some(result) = builder.recall(Operation::Derivative(key), expr) {
+            return result
+        }
+        let derivative = if !expr.depends_on_variable(key) {
+            builder.integer(0)
+        } else {
+            match expr.deref() {
+                Expr::IndepVar(_key) => builder.integer(1),
+                Expr::Unary(unary_expr) => unary_expr.derivative(expr, key, builder),
+                Expr::Binary(binary_expr) => binary_expr.derivative(expr, key, builder),
+                Expr::Nary(nary_expr) => nary_expr.derivative(key, builder),
+                _ => builder.integer(0)
+            }
+        };
+        let result = Expr::cleaned_with(&derivative, builder);
+        builder.remember(Operation::Derivative(key), expr, &result);
+        return result
+    }
+
+    // The term with its negative sign or coefficient removed, None for a
+    // term without one. -x gives x, -2 x gives 2 x and -3 gives 3.
+    pub fn without_sign(expr: &Rc<Expr>) -> Option<Rc<Expr>> {
+        match expr.deref() {
+            Expr::Numeric(numeric) if is_negative(numeric) => {
+                return Some(Expr::from_numeric(-numeric.clone()).move_to_heap())
+            },
+            Expr::Unary(unary_expr) if unary_expr.function == UnaryFunction::Neg => {
+                return Some(Rc::clone(&unary_expr.argument))
+            },
+            Expr::Nary(nary_expr) if nary_expr.function == NaryFunction::Mul => {
+                let positive = Expr::without_sign(&nary_expr.operands[0])?;
+                let mut factors = nary_expr.operands.clone();
+                factors[0] = positive;
+                return Some(product(factors))
+            },
+            _ => return None
+        }
+    }
+
+    // Infix form with the variables named by name, such as 1 - 2*x^2.
+    // Parsing it gives the same expression back.
+    pub fn format_with(&self, name: &dyn Fn(usize) -> String) -> String {
+        let mut out = String::new();
+        self.write_infix(name, &mut out);
+        return out
+    }
+
+    // Binding strength of the printed form, 1 for sums up to 5 for atoms
+    fn print_precedence(&self) -> u8 {
+        match self {
+            Expr::Numeric(numeric) if numeric.level() == NumericLevel::Complex => return 1,
+            Expr::Numeric(numeric) if numeric.level() == NumericLevel::Rational => return 2,
+            Expr::Numeric(numeric) if is_negative(numeric) => return 3,
+            Expr::Unary(unary_expr) if unary_expr.function == UnaryFunction::Neg => return 3,
+            Expr::Binary(binary_expr) if binary_expr.function == BinaryFunction::Pow => return 4,
+            Expr::Binary(_) => return 2,
+            Expr::Nary(nary_expr) if nary_expr.function == NaryFunction::Add => return 1,
+            Expr::Nary(_) => return 2,
+            _ => return 5
+        }
+    }
+
+    // Prints self, parenthesized if it binds less than precedence
+    fn write_operand(&self, precedence: u8, name: &dyn Fn(usize) -> String, out: &mut String) {
+        if self.print_precedence() < precedence {
+            out.push('(');
+            self.write_infix(name, out);
+            out.push(')');
+        } else {
+            self.write_infix(name, out);
+        }
+    }
+
+    fn write_infix(&self, name: &dyn Fn(usize) -> String, out: &mut String) {
+        match self {
+            Expr::Numeric(numeric) => out.push_str(&numeric.to_string()),
+            Expr::IndepVar(key) => out.push_str(&name(*key)),
+            Expr::Unary(unary_expr) => {
+                match unary_expr.function.name() {
+                    Some(function) => {
+                        out.push_str(function);
+                        out.push('(');
+                        unary_expr.argument.write_infix(name, out);
+                        out.push(')');
+                    },
+                    None => {
+                        out.push('-');
+                        unary_expr.argument.write_operand(2, name, out);
+                    }
+                }
+            },
+            Expr::Binary(binary_expr) => {
+                let (symbol, precedences) = match binary_expr.function {
+                    BinaryFunction::Div => ("/", (2, 3)),
+                    BinaryFunction::Mod => (" % ", (2, 3)),
+                    BinaryFunction::Pow => ("^", (5, 4))
+                };
+                binary_expr.lhs.write_operand(precedences.0, name, out);
+                out.push_str(symbol);
+                binary_expr.rhs.write_operand(precedences.1, name, out);
+            },
+            Expr::Nary(nary_expr) if nary_expr.function == NaryFunction::Add => {
+                // Terms without a sign first, so x^2 - y^2 rather than -y^2 + x^2
+                let (negative, positive): (Vec<_>, Vec<_>) = nary_expr.operands.iter()
+                    .map(|term| (term, Expr::without_sign(term)))
+                    .partition(|(_term, without_sign)| without_sign.is_some());
+                for (position, (term, _)) in positive.iter().enumerate() {
+                    if position > 0 {
+                        out.push_str(" + ");
+                    }
+                    term.write_operand(1, name, out);
+                }
+                for (position, (_, without_sign)) in negative.iter().enumerate() {
+                    out.push_str(if position == 0 && positive.is_empty() { "-" } else { " - " });
+                    without_sign.as_ref().unwrap().write_operand(2, name, out);
+                }
+            },
+            Expr::Nary(nary_expr) => {
+                // Later factors are parenthesized so a*(b % c) stays as it is
+                for (position, factor) in nary_expr.operands.iter().enumerate() {
+                    if position > 0 {
+                        out.push('*');
+                    }
+                    factor.write_operand(if position == 0 { 2 } else { 3 }, name, out);
+                }
+            }
+        }
+    }
+}
+
+// Operations whose results a builder may remember
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Operation {
+    Cleaned,
+    Derivative(usize)
+}
+
+// Creates the nodes of rewritten expressions. Rewrites such as cleanup and
+// derivatives take a builder, so that an arena can intern the nodes and
+// remember results instead of allocating a fresh tree every time.
+pub trait Builder {
+    fn build(&mut self, expr: Expr) -> Rc<Expr>;
+
+    // Result of operation on expr remembered earlier, if any
+    fn recall(&self, operation: Operation, expr: &Rc<Expr>) -> Option<Rc<Expr>>;
+
+    fn remember(&mut self, operation: Operation, expr: &Rc<Expr>, result: &Rc<Expr>);
+
+    fn integer(&mut self, value: IntegerType) -> Rc<Expr> {
+        self.build(Expr::from_integer(value))
+    }
+
+    fn apply(&mut self, function: UnaryFunction, arg: &Rc<Expr>) -> Rc<Expr> {
+        self.build(Expr::unary_from_heap(arg, function))
+    }
+
+    // Canonical -arg, a product with coefficient -1
+    fn neg(&mut self, arg: &Rc<Expr>) -> Rc<Expr> {
+        let minus_one = self.integer(-1);
+        product_of(self, vec![minus_one, Rc::clone(arg)])
+    }
+
+    fn binary(&mut self, function: BinaryFunction, lhs: &Rc<Expr>, rhs: &Rc<Expr>) -> Rc<Expr> {
+        self.build(Expr::binary_from_heap(lhs, rhs, function))
+    }
+
+    // Canonical sum or product of operands
+    fn nary(&mut self, function: NaryFunction, operands: Vec<Rc<Expr>>) -> Rc<Expr> {
+        match function {
+            NaryFunction::Add => return sum_of(self, operands),
+            NaryFunction::Mul => return product_of(self, operands)
+        }
+    }
+
+    fn add(&mut self, lhs: &Rc<Expr>, rhs: &Rc<Expr>) -> Rc<Expr> {
+        sum_of(self, vec![Rc::clone(lhs), Rc::clone(rhs)])
+    }
+
+    fn sub(&mut self, lhs: &Rc<Expr>, rhs: &Rc<Expr>) -> Rc<Expr> {
+        let negated = self.neg(rhs);
+        self.add(lhs, &negated)
+    }
+
+    fn mul(&mut self, lhs: &Rc<Expr>, rhs: &Rc<Expr>) -> Rc<Expr> {
+        product_of(self, vec![Rc::clone(lhs), Rc::clone(rhs)])
+    }
+
+    fn div(&mut self, lhs: &Rc<Expr>, rhs: &Rc<Expr>) -> Rc<Expr> {
+        self.binary(BinaryFunction::Div, lhs, rhs)
+    }
+
+    fn pow(&mut self, lhs: &Rc<Expr>, rhs: &Rc<Expr>) -> Rc<Expr> {
+        self.binary(BinaryFunction::Pow, lhs, rhs)
+    }
+}
+
+// expr itself when the rewritten result is equal to it, keeping it shared
+fn shared(expr: &Rc<Expr>, result: Rc<Expr>) -> Rc<Expr> {
+    if result == *expr {
+        return Rc::clone(expr)
+    }
+    return result
+}
+
+// A constant without a value, such as 1 / 0. Such a term is not cancelled,
+// so that evaluation still reports the error.
+fn is_undefined(expr: &Expr) -> bool {
+    !expr.depends_on_any_variable() && expr.eval(&Vec::new()).is_err()
+}
+
+// Operands of nested applications of function, in order
+fn flatten(function: &NaryFunction, operands: Vec<Rc<Expr>>, flat: &mut Vec<Rc<Expr>>) {
+    for operand in operands {
+        match operand.deref() {
+            Expr::Nary(nary_expr) if nary_expr.function == *function => {
+                flatten(function, nary_expr.operands.clone(), flat)
+            },
+            _ => flat.push(operand)
+        }
+    }
+}
+
+// Sorted node of function over operands, an operand by itself when alone
+fn nary_node<B: Builder + ?Sized>(builder: &mut B, function: NaryFunction, mut operands: Vec<Rc<Expr>>) -> Rc<Expr> {
+    operands.sort();
+    match operands.len() {
+        0 if function == NaryFunction::Add => return builder.integer(0),
+        0 => return builder.integer(1),
+        1 => return operands.pop().unwrap(),
+        _ => return builder.build(Expr::Nary(NaryExpr { function, operands }))
+    }
+}
+
+// Numeric coefficient of a term and the rest of it, None for a number
+fn split_term<B: Builder + ?Sized>(builder: &mut B, term: &Rc<Expr>) -> (Numeric, Option<Rc<Expr>>) {
+    match term.deref() {
+        Expr::Numeric(numeric) => return (numeric.clone(), None),
+        Expr::Unary(unary_expr) if unary_expr.function == UnaryFunction::Neg => {
+            let (coefficient, rest) = split_term(builder, &unary_expr.argument);
+            return (-coefficient, rest)
+        },
+        Expr::Nary(nary_expr) if nary_expr.function == NaryFunction::Mul => {
+            if let Expr::Numeric(numeric) = nary_expr.operands[0].deref() {
+                let rest = nary_node(builder, NaryFunction::Mul, nary_expr.operands[1..].to_vec());
+                return (numeric.clone(), Some(rest))
+            }
+            return (Numeric::one(), Some(Rc::clone(term)))
+        },
+        _ => return (Numeric::one(), Some(Rc::clone(term)))
+    }
+}
+
+// Terms of the sum in -(a + b)
+fn negated_sum(expr: &Expr) -> Option<&Vec<Rc<Expr>>> {
+    match expr {
+        Expr::Unary(unary_expr) if unary_expr.function == UnaryFunction::Neg => {
+            match unary_expr.argument.deref() {
+                Expr::Nary(nary_expr) if nary_expr.function == NaryFunction::Add => return Some(&nary_expr.operands),
+                _ => return None
+            }
+        },
+        _ => return None
+    }
+}
+
+// Canonical sum, x + 2 x + 1 + 2 = 3 + 3 x. Left unsimplified if adding
+// the coefficients fails.
+fn sum_of<B: Builder + ?Sized>(builder: &mut B, operands: Vec<Rc<Expr>>) -> Rc<Expr> {
+    let mut flat = Vec::new();
+    flatten(&NaryFunction::Add, operands, &mut flat);
+    // A term -(a + b) is -a - b
+    if flat.iter().any(|term| negated_sum(term).is_some()) {
+        let mut terms = Vec::new();
+        for term in flat {
+            match negated_sum(&term) {
+                Some(inner) => {
+                    for inner_term in inner {
+                        terms.push(builder.neg(inner_term))
+                    }
+                },
+                None => terms.push(term)
+            }
+        }
+        return sum_of(builder, terms)
+    }
+    let mut constant = Numeric::zero();
+    let mut like_terms: Vec<(Rc<Expr>, Numeric)> = Vec::new();
+    let mut positions: HashMap<Rc<Expr>, usize> = HashMap::new();
+    for term in &flat {
+        let (coefficient, rest) = split_term(builder, term);
+        let combined = match rest {
+            None => constant.clone().checked_add(coefficient).map(|sum| constant = sum),
+            Some(rest) => {
+                match positions.get(&rest) {
+                    Some(&position) => {
+                        let like_term = &mut like_terms[position];
+                        like_term.1.clone().checked_add(coefficient).map(|sum| like_term.1 = sum)
+                    },
+                    None => {
+                        positions.insert(Rc::clone(&rest), like_terms.len());
+                        like_terms.push((rest, coefficient));
+                        Ok(())
+                    }
+                }
+            }
+        };
+        if combined.is_err() {
+            return nary_node(builder, NaryFunction::Add, flat)
+        }
+    }
+    let mut terms = Vec::new();
+    for (rest, coefficient) in like_terms {
+        if coefficient.is_zero() && is_undefined(&rest) {
+            return nary_node(builder, NaryFunction::Add, flat)
+        } else if coefficient.is_unity() {
+            terms.push(rest)
+        } else if !coefficient.is_zero() {
+            let coefficient = builder.build(Expr::from_numeric(coefficient));
+            terms.push(product_of(builder, vec![coefficient, rest]))
+        }
+    }
+    if !constant.is_zero() || terms.is_empty() {
+        terms.push(builder.build(Expr::from_numeric(constant)))
+    }
+    return nary_node(builder, NaryFunction::Add, terms)
+}
+
+// Moves the numbers and negations of factor into coefficient and its
+// other factors into factors
+fn split_factors(factor: &Rc<Expr>, coefficient: &mut Numeric, factors: &mut Vec<Rc<Expr>>) -> Result<(), NumericError> {
+    match factor.deref() {
+        Expr::Numeric(numeric) => *coefficient = coefficient.clone().checked_mul(numeric.clone())?,
+        Expr::Unary(unary_expr) if unary_expr.function == UnaryFunction::Neg => {
+            *coefficient = -coefficient.clone();
+            split_factors(&unary_expr.argument, coefficient, factors)?
+        },
+        Expr::Nary(nary_expr) if nary_expr.function == NaryFunction::Mul => {
+            for operand in &nary_expr.operands {
+                split_factors(operand, coefficient, factors)?
+            }
+        },
+        _ => factors.push(Rc::clone(factor))
+    }
+    Ok(())
+}
+
+// Exponents of the factors with the same base, along with the factor
+// itself for when it is the only one
+type Powers = (Rc<Expr>, Vec<Rc<Expr>>, Rc<Expr>);
+
+// Canonical product, 2 x (-y) x = -2 x^2 y, with a coefficient of -1
+// kept as a negation. Left unsimplified if multiplying the coefficients
+// fails.
+fn product_of<B: Builder + ?Sized>(builder: &mut B, operands: Vec<Rc<Expr>>) -> Rc<Expr> {
+    let mut coefficient = Numeric::one();
+    let mut flat = Vec::new();
+    for operand in &operands {
+        if split_factors(operand, &mut coefficient, &mut flat).is_err() {
+            let mut flat = Vec::new();
+            flatten(&NaryFunction::Mul, operands, &mut flat);
+            return nary_node(builder, NaryFunction::Mul, flat)
+        }
+    }
+    if coefficient.is_zero() {
+        return builder.build(Expr::from_numeric(coefficient))
+    }
+    let mut powers: Vec<Powers> = Vec::new();
+    let mut positions: HashMap<Rc<Expr>, usize> = HashMap::new();
+    for factor in flat {
+        let (base, exponent) = match factor.deref() {
+            Expr::Binary(power) if power.function == BinaryFunction::Pow => {
+                (Rc::clone(&power.lhs), Rc::clone(&power.rhs))
+            },
+            _ => (Rc::clone(&factor), builder.integer(1))
+        };
+        match positions.get(&base) {
+            Some(&position) => powers[position].1.push(exponent),
+            None => {
+                positions.insert(Rc::clone(&base), powers.len());
+                powers.push((base, vec![exponent], factor))
+            }
+        }
+    }
+    let mut factors = Vec::new();
+    for (base, exponents, factor) in powers {
+        if exponents.len() == 1 {
+            factors.push(factor);
+            continue
+        }
+        let exponent = sum_of(builder, exponents);
+        if exponent.is_zero() && is_undefined(&base) {
+            let mut flat = Vec::new();
+            flatten(&NaryFunction::Mul, operands, &mut flat);
+            return nary_node(builder, NaryFunction::Mul, flat)
+        } else if exponent.is_unity() {
+            factors.push(base)
+        } else if !exponent.is_zero() {
+            factors.push(builder.pow(&base, &exponent))
+        }
+    }
+    if factors.is_empty() {
+        return builder.build(Expr::from_numeric(coefficient))
+    }
+    if (-coefficient.clone()).is_unity() {
+        let product = nary_node(builder, NaryFunction::Mul, factors);
+        return builder.apply(UnaryFunction::Neg, &product)
+    }
+    if !coefficient.is_unity() {
+        factors.push(builder.build(Expr::from_numeric(coefficient)))
+    }
+    return nary_node(builder, NaryFunction::Mul, factors)
+}
+
+// Allocates every node on the heap and remembers results only for its own
+// lifetime, so a subexpression shared within one tree is rewritten once.
+// The keys are kept alive to stop their addresses from being reused.
+pub struct HeapBuilder {
+    results: HashMap<(Operation, *const Expr), KeptResult>
+}
+
+// Key expression and the result remembered for it
+type KeptResult = (Rc<Expr>, Rc<Expr>);
+
+impl Default for HeapBuilder {
+    fn default() -> HeapBuilder {
+        HeapBuilder::new()
+    }
+}
+
+impl HeapBuilder {
+    pub fn new() -> HeapBuilder {
+        HeapBuilder { results: HashMap::new() }
+    }
+}
+
+impl Builder for HeapBuilder {
+    fn build(&mut self, expr: Expr) -> Rc<Expr> {
+        expr.move_to_heap()
+    }
+
+    fn recall(&self, operation: Operation, expr: &Rc<Expr>) -> Option<Rc<Expr>> {
+        let (_key, result) = self.results.get(&(operation, Rc::as_ptr(expr)))?;
+        Some(Rc::clone(result))
+    }
+
+    fn remember(&mut self, operation: Operation, expr: &Rc<Expr>, result: &Rc<Expr>) {
+        self.results.insert((operation, Rc::as_ptr(expr)), (Rc::clone(expr), Rc::clone(result)));
+    }
+}
+
+// Below zero, complex numbers are never negative
+fn is_negative(numeric: &Numeric) -> bool {
+    numeric.level() != NumericLevel::Complex && numeric.compare(&Numeric::zero()) == Some(Ordering::Less)
+}
+
+// a^b mod n of integers in [0, n) without computing a^b, a negative b
+// takes the modular inverse of a. The power and its floored remainder
+// otherwise, and for a negative modulus.
+fn eval_modular_power(base: Numeric, exponent: Numeric, modulus: Numeric) -> Result<Numeric, NumericError> {
+    if base.is_integer() && exponent.is_integer() && modulus.is_integer() &&
+       (is_negative(&exponent) || !is_negative(&modulus)) {
+        return numtheory::mod_pow(&base, &exponent, &modulus)
+    }
+    return base.checked_pow_numeric(&exponent)?.checked_rem(modulus)
+}
+
+// Integers and rationals, operations on them are folded by cleanup
+fn is_exact(expr: &Expr) -> bool {
+    match expr {
+        Expr::Numeric(numeric) => {
+            match numeric.level() {
+                NumericLevel::Integer | NumericLevel::Rational => return true,
+                _ => return false
+            }
+        },
+        _ => return false
+    }
 }
 
 impl UnaryExpr {
-    fn eval(&self, values: &Vec<Numeric>) -> Numeric {
+    pub fn function(&self) -> &UnaryFunction {
+        &self.function
+    }
+
+    pub fn argument(&self) -> &Rc<Expr> {
+        &self.argument
+    }
+
+    fn eval(&self, values: &Vec<Numeric>) -> Result<Numeric, NumericError> {
+        let argument = self.argument.eval(values)?;
+        match self.function {
+            UnaryFunction::Neg => return Ok(-argument),
+            UnaryFunction::Exp => return argument.checked_exp(),
+            UnaryFunction::Ln => return argument.checked_ln(),
+            UnaryFunction::Sqrt => return argument.checked_apply(Numeric::sqrt),
+            UnaryFunction::Sin => return argument.checked_apply(Numeric::sin),
+            UnaryFunction::Cos => return argument.checked_apply(Numeric::cos),
+            UnaryFunction::Tan => return argument.checked_apply(Numeric::tan),
+            UnaryFunction::Asin => return argument.checked_apply(Numeric::asin),
+            UnaryFunction::Acos => return argument.checked_apply(Numeric::acos),
+            UnaryFunction::Atan => return argument.checked_apply(Numeric::atan),
+            UnaryFunction::Sinh => return argument.checked_apply(Numeric::sinh),
+            UnaryFunction::Cosh => return argument.checked_apply(Numeric::cosh),
+            UnaryFunction::Tanh => return argument.checked_apply(Numeric::tanh)
+        }
+    }
+
+    fn eval_interval(&self, values: &Vec<Interval>) -> Result<Interval, NumericError> {
+        let argument = self.argument.eval_interval(values)?;
         match self.function {
-            UnaryFunction::Neg => return -self.argument.eval(values),
-            UnaryFunction::Exp => return self.argument.eval(values).exp()
+            UnaryFunction::Neg => return Ok(-argument),
+            UnaryFunction::Exp => return Ok(argument.exp()),
+            UnaryFunction::Ln => return argument.ln(),
+            UnaryFunction::Sqrt => return argument.sqrt(),
+            UnaryFunction::Sin => return Ok(argument.sin()),
+            UnaryFunction::Cos => return Ok(argument.cos()),
+            UnaryFunction::Tan => return argument.tan(),
+            UnaryFunction::Asin => return argument.asin(),
+            UnaryFunction::Acos => return argument.acos(),
+            UnaryFunction::Atan => return Ok(argument.atan()),
+            UnaryFunction::Sinh => return Ok(argument.sinh()),
+            UnaryFunction::Cosh => return Ok(argument.cosh()),
+            UnaryFunction::Tanh => return Ok(argument.tanh())
+        }
+    }
+}
+
+impl UnaryExpr {
+    // Chain rule, du/dx times the derivative of the function at u. expr is
+    // the whole unary expression, shared in the result where it reappears.
+    fn derivative(&self, expr: &Rc<Expr>, key: usize, b: &mut dyn Builder) -> Rc<Expr> {
+        let u = &self.argument;
+        let du = Expr::derivative_with(u, key, b);
+        let one = b.integer(1);
+        let two = b.integer(2);
+        match self.function {
+            UnaryFunction::Neg => return b.neg(&du),
+            UnaryFunction::Exp => return b.mul(&du, expr),
+            UnaryFunction::Ln => return b.div(&du, u),
+            UnaryFunction::Sqrt => {
+                let twice = b.mul(&two, expr);
+                return b.div(&du, &twice)
+            },
+            UnaryFunction::Sin => {
+                let cos = b.apply(UnaryFunction::Cos, u);
+                return b.mul(&du, &cos)
+            },
+            UnaryFunction::Cos => {
+                let sin = b.apply(UnaryFunction::Sin, u);
+                let product = b.mul(&du, &sin);
+                return b.neg(&product)
+            },
+            UnaryFunction::Tan => {
+                let cos = b.apply(UnaryFunction::Cos, u);
+                let square = b.pow(&cos, &two);
+                return b.div(&du, &square)
+            },
+            UnaryFunction::Asin | UnaryFunction::Acos => {
+                // du / sqrt(1 - u^2), negated for acos
+                let square = b.pow(u, &two);
+                let difference = b.sub(&one, &square);
+                let root = b.apply(UnaryFunction::Sqrt, &difference);
+                let quotient = b.div(&du, &root);
+                if self.function == UnaryFunction::Acos {
+                    return b.neg(&quotient)
+                }
+                return quotient
+            },
+            UnaryFunction::Atan => {
+                let square = b.pow(u, &two);
+                let sum = b.add(&one, &square);
+                return b.div(&du, &sum)
+            },
+            UnaryFunction::Sinh => {
+                let cosh = b.apply(UnaryFunction::Cosh, u);
+                return b.mul(&du, &cosh)
+            },
+            UnaryFunction::Cosh => {
+                let sinh = b.apply(UnaryFunction::Sinh, u);
+                return b.mul(&du, &sinh)
+            },
+            UnaryFunction::Tanh => {
+                let cosh = b.apply(UnaryFunction::Cosh, u);
+                let square = b.pow(&cosh, &two);
+                return b.div(&du, &square)
+            }
         }
     }
 }
 
 impl BinaryExpr {
-    fn eval(&self, values: &Vec<Numeric>) -> Numeric {
+    pub fn function(&self) -> &BinaryFunction {
+        &self.function
+    }
+
+    pub fn lhs(&self) -> &Rc<Expr> {
+        &self.lhs
+    }
+
+    pub fn rhs(&self) -> &Rc<Expr> {
+        &self.rhs
+    }
+
+    fn eval(&self, values: &Vec<Numeric>) -> Result<Numeric, NumericError> {
+        if let (BinaryFunction::Mod, Expr::Binary(power)) = (&self.function, self.lhs.deref()) {
+            if power.function == BinaryFunction::Pow {
+                let base = power.lhs.eval(values)?;
+                let exponent = power.rhs.eval(values)?;
+                return eval_modular_power(base, exponent, self.rhs.eval(values)?)
+            }
+        }
+        let lhs = self.lhs.eval(values)?;
+        let rhs = self.rhs.eval(values)?;
+        match self.function {
+            BinaryFunction::Div => return lhs.checked_div(rhs),
+            BinaryFunction::Pow => return lhs.checked_pow_numeric(&rhs),
+            BinaryFunction::Mod => return lhs.checked_rem(rhs)
+        }
+    }
+
+    fn eval_interval(&self, values: &Vec<Interval>) -> Result<Interval, NumericError> {
+        let lhs = self.lhs.eval_interval(values)?;
+        let rhs = self.rhs.eval_interval(values)?;
         match self.function {
-            BinaryFunction::Add => return self.lhs.eval(values) +
-                                          self.rhs.eval(values),
-            BinaryFunction::Mul => return self.lhs.eval(values) *
-                                          self.rhs.eval(values)
+            BinaryFunction::Div => return lhs.checked_div(rhs),
+            BinaryFunction::Pow => return lhs.checked_pow_interval(rhs),
+            BinaryFunction::Mod => return lhs.checked_rem(rhs)
         }
     }
 
-    fn trim(&self) -> Option<Rc<Expr>> {
+    // The cleaned form when a rule applies to the operands, None otherwise
+    fn trim(&self, builder: &mut dyn Builder) -> Option<Rc<Expr>> {
+        // Powers are left alone, 3^1000000 mod 7 would otherwise expand
+        if self.function != BinaryFunction::Pow && is_exact(&self.lhs) && is_exact(&self.rhs) {
+            if let Ok(value) = self.eval(&Vec::new()) {
+                if is_exact(&Expr::Numeric(value.clone())) {
+                    return Some(builder.build(Expr::from_numeric(value)))
+                }
+            }
+        }
         match self.function {
-            BinaryFunction::Add => {
-                if self.lhs.is_zero() {
-                    return Some(Rc::clone(&self.rhs))
-                } else if self.rhs.is_zero() {
+            BinaryFunction::Div => {
+                if self.rhs.is_unity() {
                     return Some(Rc::clone(&self.lhs))
+                } else if self.lhs == self.rhs && !self.rhs.is_zero() && !is_undefined(&self.rhs) {
+                    return Some(builder.integer(1))
                 } else {
                     return None
                 }
             },
-            BinaryFunction::Mul => {
-                if self.lhs.is_zero() || self.rhs.is_zero() {
-                    return Some(Rc::new(Expr::zero()))
-                } else if self.lhs.is_unity() {
-                    return Some(Rc::clone(&self.rhs))
-                } else if self.rhs.is_unity() {
+            BinaryFunction::Pow => {
+                if self.rhs.is_unity() {
                     return Some(Rc::clone(&self.lhs))
+                } else if self.rhs.is_zero() && !is_undefined(&self.lhs) {
+                    return Some(builder.integer(1))
                 } else {
                     return None
                 }
@@ -212,26 +1014,178 @@ impl BinaryExpr {
             _ => return None
         }
     }
+
+    fn derivative(&self, expr: &Rc<Expr>, key: usize, builder: &mut dyn Builder) -> Rc<Expr> {
+        let (a, b) = (&self.lhs, &self.rhs);
+        let da = Expr::derivative_with(a, key, builder);
+        let db = Expr::derivative_with(b, key, builder);
+        match self.function {
+            BinaryFunction::Div => {
+                let left = builder.mul(&da, b);
+                let right = builder.mul(a, &db);
+                let numerator = builder.sub(&left, &right);
+                let two = builder.integer(2);
+                let denominator = builder.pow(b, &two);
+                return builder.div(&numerator, &denominator)
+            },
+            BinaryFunction::Pow => {
+                if !b.depends_on_variable(key) {
+                    // b a^(b - 1) da
+                    let one = builder.integer(1);
+                    let exponent = builder.sub(b, &one);
+                    let power = builder.pow(a, &exponent);
+                    let factor = builder.mul(b, &power);
+                    return builder.mul(&factor, &da)
+                }
+                let ln_a = builder.apply(UnaryFunction::Ln, a);
+                if !a.depends_on_variable(key) {
+                    let factor = builder.mul(expr, &ln_a);
+                    return builder.mul(&factor, &db)
+                }
+                let left = builder.mul(&db, &ln_a);
+                let product = builder.mul(b, &da);
+                let right = builder.div(&product, a);
+                let sum = builder.add(&left, &right);
+                return builder.mul(expr, &sum)
+            },
+            BinaryFunction::Mod => {
+                // a mod n = a - n floor(a / n), where n floor(a / n) = a - a mod n
+                if !b.depends_on_variable(key) {
+                    return da
+                }
+                let difference = builder.sub(a, expr);
+                let quotient = builder.div(&difference, b);
+                let product = builder.mul(&db, &quotient);
+                return builder.sub(&da, &product)
+            }
+        }
+    }
+}
+
+impl NaryExpr {
+    pub fn function(&self) -> &NaryFunction {
+        &self.function
+    }
+
+    pub fn operands(&self) -> &Vec<Rc<Expr>> {
+        &self.operands
+    }
+
+    fn eval(&self, values: &Vec<Numeric>) -> Result<Numeric, NumericError> {
+        let mut result = self.operands[0].eval(values)?;
+        for operand in &self.operands[1..] {
+            let value = operand.eval(values)?;
+            result = match self.function {
+                NaryFunction::Add => result.checked_add(value)?,
+                NaryFunction::Mul => result.checked_mul(value)?
+            };
+        }
+        Ok(result)
+    }
+
+    fn eval_interval(&self, values: &Vec<Interval>) -> Result<Interval, NumericError> {
+        let mut result = self.operands[0].eval_interval(values)?;
+        for operand in &self.operands[1..] {
+            let value = operand.eval_interval(values)?;
+            result = match self.function {
+                NaryFunction::Add => result + value,
+                NaryFunction::Mul => result * value
+            };
+        }
+        Ok(result)
+    }
+
+    // Sum of the derivatives, or the product rule over every factor
+    fn derivative(&self, key: usize, builder: &mut dyn Builder) -> Rc<Expr> {
+        let mut terms = Vec::new();
+        for (position, operand) in self.operands.iter().enumerate() {
+            if !operand.depends_on_variable(key) {
+                continue
+            }
+            let derivative = Expr::derivative_with(operand, key, builder);
+            match self.function {
+                NaryFunction::Add => terms.push(derivative),
+                NaryFunction::Mul => {
+                    let mut factors = self.operands.clone();
+                    factors[position] = derivative;
+                    terms.push(builder.nary(NaryFunction::Mul, factors))
+                }
+            }
+        }
+        return builder.nary(NaryFunction::Add, terms)
+    }
+}
+
+// Total order sorting the operands of sums and products: numbers, then
+// variables by key, then unary, binary and n-ary expressions, each by
+// function and then operands
+impl Ord for Expr {
+    fn cmp(&self, other: &Expr) -> Ordering {
+        match (self, other) {
+            (Expr::Numeric(lhs), Expr::Numeric(rhs)) => return lhs.total_cmp(rhs),
+            (Expr::IndepVar(lhs), Expr::IndepVar(rhs)) => return lhs.cmp(rhs),
+            (Expr::Unary(lhs), Expr::Unary(rhs)) => {
+                return (&lhs.function, &lhs.argument).cmp(&(&rhs.function, &rhs.argument))
+            },
+            (Expr::Binary(lhs), Expr::Binary(rhs)) => {
+                return (&lhs.function, &lhs.lhs, &lhs.rhs).cmp(&(&rhs.function, &rhs.lhs, &rhs.rhs))
+            },
+            (Expr::Nary(lhs), Expr::Nary(rhs)) => {
+                return (&lhs.function, &lhs.operands).cmp(&(&rhs.function, &rhs.operands))
+            },
+            _ => return self.rank().cmp(&other.rank())
+        }
+    }
+}
+
+impl PartialOrd for Expr {
+    fn partial_cmp(&self, other: &Expr) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 pub fn neg(arg: &Rc<Expr>) -> Rc<Expr> {
-    Rc::new(Expr::unary_from_heap(&arg, UnaryFunction::Neg))
+    HeapBuilder::new().neg(arg)
 }
 
 pub fn exp(arg: &Rc<Expr>) -> Rc<Expr> {
     Rc::new(Expr::unary_from_heap(&arg, UnaryFunction::Exp))
 }
 
+pub fn apply(function: UnaryFunction, arg: &Rc<Expr>) -> Rc<Expr> {
+    Rc::new(Expr::unary_from_heap(&arg, function))
+}
+
 pub fn add(lhs: &Rc<Expr>, rhs: &Rc<Expr>) -> Rc<Expr> {
-    Rc::new(Expr::binary_from_heap(&lhs, &rhs, BinaryFunction::Add))
+    HeapBuilder::new().add(lhs, rhs)
 }
 
 pub fn sub(lhs: &Rc<Expr>, rhs: &Rc<Expr>) -> Rc<Expr> {
-    Rc::new(Expr::binary_from_heap(&lhs, &neg(&rhs), BinaryFunction::Add))
+    HeapBuilder::new().sub(lhs, rhs)
 }
 
 pub fn mul(lhs: &Rc<Expr>, rhs: &Rc<Expr>) -> Rc<Expr> {
-    Rc::new(Expr::binary_from_heap(&lhs, &rhs, BinaryFunction::Mul))
+    HeapBuilder::new().mul(lhs, rhs)
+}
+
+pub fn sum(operands: Vec<Rc<Expr>>) -> Rc<Expr> {
+    HeapBuilder::new().nary(NaryFunction::Add, operands)
+}
+
+pub fn product(operands: Vec<Rc<Expr>>) -> Rc<Expr> {
+    HeapBuilder::new().nary(NaryFunction::Mul, operands)
+}
+
+pub fn div(lhs: &Rc<Expr>, rhs: &Rc<Expr>) -> Rc<Expr> {
+    Rc::new(Expr::binary_from_heap(&lhs, &rhs, BinaryFunction::Div))
+}
+
+pub fn pow(lhs: &Rc<Expr>, rhs: &Rc<Expr>) -> Rc<Expr> {
+    Rc::new(Expr::binary_from_heap(&lhs, &rhs, BinaryFunction::Pow))
+}
+
+pub fn modulo(lhs: &Rc<Expr>, rhs: &Rc<Expr>) -> Rc<Expr> {
+    Rc::new(Expr::binary_from_heap(&lhs, &rhs, BinaryFunction::Mod))
 }
 
 #[cfg(test)]
@@ -248,10 +1202,10 @@ fn test_expr_on_stack_and_heap() {
     let values: Vec<Numeric> = Vec::new();
     let a = Expr::from_integer(1);
     let b = Expr::from_integer(2);
-    let c_on_heap = Expr::binary_from_heap(&a.clone_to_heap(), &b.clone_to_heap(), BinaryFunction::Add).clone_to_heap();
-    let c_on_stack = Expr::binary_from(&a, &b, BinaryFunction::Add);
-    assert_eq!(c_on_heap.eval(&values), Numeric::from_integer(3));
-    assert_eq!(c_on_stack.eval(&values), Numeric::from_integer(3));
+    let c_on_heap = Expr::nary_from_heap(&[a.clone_to_heap(), b.clone_to_heap()], NaryFunction::Add).clone_to_heap();
+    let c_on_stack = Expr::nary_from(&[a, b], NaryFunction::Add);
+    assert_eq!(c_on_heap.eval(&values), Ok(Numeric::from_integer(3)));
+    assert_eq!(c_on_stack.eval(&values), Ok(Numeric::from_integer(3)));
 }
 
 #[test]
@@ -265,22 +1219,14 @@ fn test_evaluation_of_expr_with_variables() {
     let y = Expr::from_key(1);
 
     // x + 1 + y
-    let f = Expr::binary_from(
-        &x, 
-        &Expr::binary_from(
-            &one,
-            &y,
-            BinaryFunction::Add
-        ),
-        BinaryFunction::Add
-    );
+    let f = Expr::nary_from(&[x, one, y], NaryFunction::Add);
 
     assert!(f.depends_on_any_variable());
     assert!(f.depends_on_variable(0));
     assert!(f.depends_on_variable(1));
     assert!(!f.depends_on_variable(2));
 
-    assert_eq!(f.eval(&values), Numeric::from_integer(6));
+    assert_eq!(f.eval(&values), Ok(Numeric::from_integer(6)));
 }
 
 #[test]
@@ -293,20 +1239,140 @@ fn test_trimming() {
     let two = Expr::from_integer(2);
     let x = Expr::from_key(0);
 
-    let f = Expr::binary_from(
-        &zero,
-        &x,
-        BinaryFunction::Add
-    ).clone_to_heap();
+    let f = Expr::nary_from(&[zero, x], NaryFunction::Add).clone_to_heap();
+    let g = Expr::nary_from(&[two.clone(), two], NaryFunction::Add).clone_to_heap();
+
+    assert_eq!(*Expr::cleaned(&f), Expr::IndepVar(0));
+    assert_eq!(*Expr::cleaned(&g), Expr::from_integer(4));
+    assert_eq!(Expr::cleaned(&f).eval(&values), f.eval(&values));
+}
+
+#[test]
+fn test_basic_cleanup() {
+    let x = Expr::from_key(0).clone_to_heap();
+    let one = Expr::from_integer(1).clone_to_heap();
+    let zero = Expr::zero().clone_to_heap();
 
-    let g = Expr::binary_from(
-        &two,
-        &two,
-        BinaryFunction::Add
-    ).clone_to_heap();
+    let one_plus_x = add(&one, &x);
+    assert!(Rc::ptr_eq(&Expr::cleaned(&one_plus_x), &one_plus_x));
 
-    assert_eq!(*Expr::trim(&f), Expr::IndepVar(0));
-    assert_eq!(*Expr::trim(&g), *g);
+    // 1 * (1 + x) = 1 + x
+    assert_eq!(Expr::cleaned(&mul(&one, &one_plus_x)), one_plus_x);
+    // 0 * (1 + x) = 0
+    assert_eq!(Expr::cleaned(&mul(&zero, &one_plus_x)), zero);
+    // 0 + (1 + x) = 1 + x
+    assert_eq!(Expr::cleaned(&add(&zero, &one_plus_x)), one_plus_x);
+    // (1 + x) / (1 + x) = 1
+    assert_eq!(Expr::cleaned(&div(&one_plus_x, &one_plus_x)), one);
+    // -(-x)^1 = x, 0 / 0 is left for evaluation to report
+    assert_eq!(Expr::cleaned(&pow(&neg(&neg(&x)), &one)), x);
+    assert_eq!(*Expr::cleaned(&div(&zero, &zero)), *div(&zero, &zero));
+    // So are (1 / 0)^0 and (1 / 0) / (1 / 0)
+    let undefined = div(&one, &zero);
+    assert_eq!(Expr::cleaned(&pow(&undefined, &zero)).eval(&Vec::new()), Err(NumericError::DivisionByZero));
+    assert_eq!(Expr::cleaned(&div(&undefined, &undefined)).eval(&Vec::new()), Err(NumericError::DivisionByZero));
+}
+
+#[test]
+fn test_canonical_form() {
+    let x = Expr::from_key(0).clone_to_heap();
+    let y = Expr::from_key(1).clone_to_heap();
+    let z = Expr::from_key(2).clone_to_heap();
+    let one = Expr::from_integer(1).clone_to_heap();
+    let two = Expr::from_integer(2).clone_to_heap();
+    let three = Expr::from_integer(3).clone_to_heap();
+
+    // Order and grouping do not matter
+    assert_eq!(sub(&one, &x), add(&neg(&x), &one));
+    assert_eq!(mul(&x, &y), mul(&y, &x));
+    assert_eq!(add(&add(&x, &y), &z), add(&x, &add(&z, &y)));
+    assert_eq!(*add(&add(&x, &y), &z), Expr::nary_from_heap(&[x.clone(), y.clone(), z.clone()], NaryFunction::Add));
+
+    // Numbers are folded and like terms combined
+    assert_eq!(add(&x, &mul(&two, &x)), mul(&three, &x));
+    assert_eq!(sum(vec![Rc::clone(&two), Rc::clone(&x), Rc::clone(&one)]), add(&x, &three));
+    assert_eq!(*sub(&x, &x), Expr::zero());
+    assert_eq!(mul(&x, &x), pow(&x, &two));
+    assert_eq!(mul(&pow(&x, &y), &x), pow(&x, &add(&y, &one)));
+    assert_eq!(product(vec![Rc::clone(&two), Rc::clone(&x), Rc::clone(&three)]), mul(&Expr::from_integer(6).clone_to_heap(), &x));
+    assert_eq!(*mul(&x, &pow(&x, &neg(&one))), Expr::from_integer(1));
+
+    // Negation is a coefficient of -1
+    assert_eq!(neg(&neg(&x)), x);
+    assert_eq!(*neg(&two), Expr::from_integer(-2));
+    assert_eq!(mul(&neg(&x), &neg(&y)), mul(&x, &y));
+    assert_eq!(add(&mul(&two, &x), &neg(&x)), x);
+}
+
+#[test]
+fn test_printing() {
+    let x = Expr::from_key(0).clone_to_heap();
+    let y = Expr::from_key(1).clone_to_heap();
+    let two = Expr::from_integer(2).clone_to_heap();
+    let half = Expr::from_numeric(Numeric::from_rational(::rational::Rational::new(1, 2))).clone_to_heap();
+    let names = |key: usize| ["x", "y"][key].to_string();
+    let print = |expr: &Rc<Expr>| expr.format_with(&names);
+
+    assert_eq!(print(&sub(&Expr::from_integer(1).clone_to_heap(), &mul(&two, &pow(&x, &two)))), "1 - 2*x^2");
+    assert_eq!(print(&mul(&neg(&x), &add(&x, &y))), "-x*(x + y)");
+    assert_eq!(print(&neg(&add(&x, &y))), "-(x + y)");
+    assert_eq!(print(&pow(&neg(&x), &half)), "(-x)^(1/2)");
+    assert_eq!(print(&pow(&pow(&x, &y), &neg(&two))), "(x^y)^(-2)");
+    assert_eq!(print(&div(&x, &mul(&two, &y))), "x/(2*y)");
+    assert_eq!(print(&mul(&x, &modulo(&y, &two))), "x*(y % 2)");
+    assert_eq!(print(&apply(UnaryFunction::Sin, &sub(&x, &half))), "sin(x - 1/2)");
+}
+
+#[test]
+fn test_hashing() {
+    use std::collections::HashSet;
+
+    let x = Expr::from_key(0).clone_to_heap();
+    let mut seen = HashSet::new();
+    seen.insert(pow(&Expr::from_real(-0.0).move_to_heap(), &x));
+    assert!(seen.contains(&pow(&Expr::from_real(0.0).move_to_heap(), &x)));
+    assert!(!seen.contains(&pow(&Expr::from_integer(0).move_to_heap(), &x)));
+    // NaN equals itself, a NaN key is found again
+    let nan = Expr::from_real(f64::NAN).move_to_heap();
+    assert_eq!(nan, Expr::from_real(f64::NAN).move_to_heap());
+    seen.insert(pow(&nan, &x));
+    assert!(seen.contains(&pow(&nan, &x)));
+}
+
+#[test]
+fn test_derivatives() {
+    let values: Vec<Numeric> = vec![Numeric::from_integer(5), Numeric::from_integer(2)];
+    let x = Expr::from_key(0).clone_to_heap();
+    let y = Expr::from_key(1).clone_to_heap();
+    let one = Expr::from_integer(1).clone_to_heap();
+    let two = Expr::from_integer(2).clone_to_heap();
+    let at = |expr: &Rc<Expr>| expr.eval(&values).unwrap().to_real();
+
+    // d/dx (1 + x) = 1 and d/dx (1 + x)^2 = 2(1 + x) = 12 at x = 5
+    let one_plus_x = add(&one, &x);
+    assert_eq!(*Expr::derivative(&one_plus_x, 0), Expr::from_integer(1));
+    assert_eq!(Expr::derivative(&pow(&one_plus_x, &two), 0).eval(&values), Ok(Numeric::from_integer(12)));
+    assert_eq!(*Expr::derivative(&one_plus_x, 1), Expr::zero());
+
+    // The exponential function is its own derivative
+    let e_to_x = exp(&x);
+    assert_eq!(Expr::derivative(&e_to_x, 0), e_to_x);
+
+    // x y / (x - y) and x^y, checked against the closed forms
+    let ratio = div(&mul(&x, &y), &sub(&x, &y));
+    assert_eq!(Expr::derivative(&ratio, 0).eval(&values), Ok(Numeric::from_rational(::rational::Rational::new(-4, 9))));
+    let power = pow(&x, &y);
+    assert!((at(&Expr::derivative(&power, 1)) - 25. * 5f64.ln()).abs() < 1e-12);
+    assert!((at(&Expr::derivative(&pow(&y, &y), 1)) - 4. * (2f64.ln() + 1.)).abs() < 1e-12);
+
+    // Chain rule through the named functions
+    let sin_x2 = apply(UnaryFunction::Sin, &pow(&x, &two));
+    assert!((at(&Expr::derivative(&sin_x2, 0)) - 10. * 25f64.cos()).abs() < 1e-12);
+    let atan = apply(UnaryFunction::Atan, &div(&y, &x));
+    assert!((at(&Expr::derivative(&atan, 1)) - 5. / 29.).abs() < 1e-12);
+    let ln_cosh = apply(UnaryFunction::Ln, &apply(UnaryFunction::Cosh, &y));
+    assert!((at(&Expr::derivative(&ln_cosh, 1)) - 2f64.tanh()).abs() < 1e-12);
+    assert_eq!(Expr::derivative(&modulo(&pow(&x, &two), &y), 0).eval(&values), Ok(Numeric::from_integer(10)));
 }
 
 #[test]
@@ -315,7 +1381,90 @@ fn test_arithmetic() {
     let a = Expr::from_integer(2).clone_to_heap();
     let b = Expr::from_integer(3).clone_to_heap();
 
-    assert_eq!(add(&a, &b).eval(&values), Numeric::from_integer(5));
-    assert_eq!(sub(&a, &b).eval(&values), Numeric::from_integer(-1));
-    assert_eq!(mul(&a, &b).eval(&values), Numeric::from_integer(6));
-}
\ No newline at end of file
+    assert_eq!(add(&a, &b).eval(&values), Ok(Numeric::from_integer(5)));
+    assert_eq!(sub(&a, &b).eval(&values), Ok(Numeric::from_integer(-1)));
+    assert_eq!(mul(&a, &b).eval(&values), Ok(Numeric::from_integer(6)));
+    assert_eq!(modulo(&neg(&b), &a).eval(&values), Ok(Numeric::from_integer(1)));
+    assert_eq!(div(&a, &b).eval(&values), Ok(Numeric::from_rational(::rational::Rational::new(2, 3))));
+    assert_eq!(pow(&a, &b).eval(&values), Ok(Numeric::from_integer(8)));
+    assert_eq!(pow(&a, &neg(&b)).eval(&values), Ok(Numeric::from_rational(::rational::Rational::new(1, 8))));
+    assert_eq!(div(&a, &Expr::zero().clone_to_heap()).eval(&values), Err(NumericError::DivisionByZero));
+}
+
+#[test]
+fn test_modular_power_is_not_expanded() {
+    let values: Vec<Numeric> = Vec::new();
+    let base = Expr::from_integer(7).clone_to_heap();
+    let exponent = Expr::from_integer(10).clone_to_heap();
+    let huge = pow(&exponent, &Expr::from_integer(30).clone_to_heap());
+    let modulus = Expr::from_integer(13).clone_to_heap();
+
+    // 7^(10^30) would not fit in memory
+    assert_eq!(modulo(&pow(&base, &huge), &modulus).eval(&values), Ok(Numeric::from_integer(9)));
+    assert_eq!(modulo(&pow(&base, &exponent), &neg(&modulus)).eval(&values), Ok(Numeric::from_integer(-9)));
+    // Negative exponents take the modular inverse, 7^-1 = 2 mod 13
+    let inverse = pow(&base, &Expr::from_integer(-1).clone_to_heap());
+    assert_eq!(modulo(&inverse, &modulus).eval(&values), Ok(Numeric::from_integer(2)));
+    let even = Expr::from_integer(14).clone_to_heap();
+    assert_eq!(modulo(&pow(&even, &Expr::from_integer(-1).clone_to_heap()), &Expr::from_integer(4).clone_to_heap()).eval(&values),
+               Err(NumericError::Domain("no modular inverse, arguments are not coprime")));
+}
+
+#[test]
+fn test_evaluation_errors() {
+    let values: Vec<Numeric> = vec![Numeric::from_integer(1000)];
+    let x = Expr::from_key(0).clone_to_heap();
+    let one = Expr::from_integer(1).clone_to_heap();
+
+    assert_eq!(exp(&x).eval(&values), Err(NumericError::Overflow));
+    assert_eq!(add(&one, &exp(&x)).eval(&values), Err(NumericError::Overflow));
+    assert_eq!(exp(&neg(&x)).eval(&values), Ok(Numeric::from_real((-1000f64).exp())));
+}
+
+#[test]
+fn test_interval_evaluation() {
+    let values = vec![Interval::new(-1., 2.), Interval::new(0.5, 1.)];
+    let x = Expr::from_key(0).clone_to_heap();
+    let y = Expr::from_key(1).clone_to_heap();
+    let three = Expr::from_integer(3).clone_to_heap();
+
+    // x * y - 3 lies in [-4, -1] over the box
+    let f = sub(&mul(&x, &y), &three).eval_interval(&values).unwrap();
+    assert_eq!(f, Interval::new(-4., -1.));
+
+    // A raw product x * x is evaluated without knowing both factors are
+    // equal, the canonical product is x^2
+    let g = Expr::nary_from_heap(&[Rc::clone(&x), Rc::clone(&x)], NaryFunction::Mul).eval_interval(&values).unwrap();
+    assert!(g.contains(-2.) && g.contains(4.));
+    let square = mul(&x, &x).eval_interval(&values).unwrap();
+    assert!(!square.contains(-0.5) && square.contains(0.) && square.contains(4.));
+
+    let h = exp(&neg(&y)).eval_interval(&values).unwrap();
+    assert!(h.contains((-1f64).exp()) && h.contains((-0.5f64).exp()));
+    let complex = Expr::from_complex(ComplexType::i()).clone_to_heap();
+    assert!(add(&x, &complex).eval_interval(&values).is_err());
+}
+
+#[test]
+fn test_named_functions_and_substitution() {
+    let values: Vec<Numeric> = vec![Numeric::from_integer(4), Numeric::zero()];
+    let x = Expr::from_key(0).clone_to_heap();
+    let y = Expr::from_key(1).clone_to_heap();
+
+    assert_eq!(UnaryFunction::from_name("sqrt"), Some(UnaryFunction::Sqrt));
+    assert_eq!(UnaryFunction::from_name("f"), None);
+    assert_eq!(apply(UnaryFunction::Sqrt, &x).eval(&values), Ok(Numeric::from_integer(2)));
+    assert_eq!(apply(UnaryFunction::Cos, &y).eval(&values), Ok(Numeric::one()));
+    assert!(apply(UnaryFunction::Ln, &y).eval(&values).is_err());
+    let range = apply(UnaryFunction::Atan, &x).eval_interval(&vec![Interval::new(0., 1.)]).unwrap();
+    assert!(range.contains(0.) && range.contains(1f64.atan()));
+
+    // sin(x) + y with x = y + 1 and y unchanged
+    let f = add(&apply(UnaryFunction::Sin, &x), &y);
+    let mut replacements = HashMap::new();
+    replacements.insert(0, add(&y, &Expr::from_integer(1).clone_to_heap()));
+    let g = Expr::substitute(&f, &replacements);
+    assert_eq!(g.eval(&values), Ok(Numeric::from_integer(1).sin()));
+    assert!(!g.depends_on_variable(0));
+    assert!(Rc::ptr_eq(&Expr::substitute(&y, &replacements), &y));
+}
@@ -0,0 +1,52 @@
+extern crate sym;
+
+use sym::{Engine, EngineOutput, EngineError, Numeric, NumericError, Span};
+
+fn run(engine: &mut Engine, input: &str) -> Result<EngineOutput, EngineError> {
+    engine.interpret(&String::from(input))
+}
+
+fn value(number: i64) -> Result<EngineOutput, EngineError> {
+    Ok(EngineOutput::Value(Numeric::from_integer(number)))
+}
+
+#[test]
+fn test_session() {
+    let mut engine = Engine::new();
+
+    assert_eq!(run(&mut engine, "x = 5"), value(5));
+    assert_eq!(engine.get_variable(&"x".to_string()), Some(Numeric::from_integer(5)));
+    assert_eq!(run(&mut engine, "f(a, b) = 2a + b^2"), Ok(EngineOutput::Defined("f(a, b)".to_string())));
+    assert_eq!(run(&mut engine, "f(x, 3) mod 4"), value(3));
+    assert_eq!(run(&mut engine, "diff(f(x, x), x)"), value(12));
+    assert_eq!(run(&mut engine, "u = t^2"), Ok(EngineOutput::Stored("u".to_string())));
+    assert_eq!(run(&mut engine, "diff(u, t)"), Ok(EngineOutput::Expression("2*t".to_string())));
+    assert_eq!(run(&mut engine, "y = x / 2"), Ok(EngineOutput::Value(Numeric::from_rational(sym::Rational::new(5, 2)))));
+    run(&mut engine, "rule sin(a)*cos(a) -> sin(2a)/2").unwrap();
+    assert_eq!(run(&mut engine, "simplify(2*sin(x)*cos(x))"), Ok(EngineOutput::Expression("sin(2*x)".to_string())));
+    assert_eq!(run(&mut engine, "collect(expand((s + t)^2), t)"), Ok(EngineOutput::Expression("t^2 + s^2 + 2*t*s".to_string())));
+    assert_eq!(run(&mut engine, "sqf(s^3 + s^2)"), Ok(EngineOutput::Expression("s^2*(1 + s)".to_string())));
+    assert_eq!(run(&mut engine, "precision 30"), Ok(EngineOutput::Precision(Some(30))));
+    assert_eq!(run(&mut engine, "precision 0"), Ok(EngineOutput::Precision(None)));
+}
+
+#[test]
+fn test_errors_carry_their_position() {
+    let mut engine = Engine::new();
+
+    let error = run(&mut engine, "2 * (x + 1").unwrap_err();
+    assert_eq!(error.span(), Some(Span::new(4, 5)));
+    assert_eq!(error.to_string(), "unclosed parenthesis");
+    assert_eq!(error.span().unwrap().underline("2 * (x + 1"), "2 * (x + 1\n    ^");
+
+    match run(&mut engine, "1 # 2") {
+        Err(EngineError::Lex(error)) => assert_eq!(error.character, '#'),
+        other => panic!("expected a lexing error, got {:?}", other)
+    }
+    assert_eq!(run(&mut engine, "1 / 0"), Err(EngineError::Evaluation(NumericError::DivisionByZero)));
+    assert_eq!(run(&mut engine, "t + 1"), Err(EngineError::Unbound("t".to_string())));
+    assert_eq!(run(&mut engine, "a = b"), Ok(EngineOutput::Stored("a".to_string())));
+    assert_eq!(run(&mut engine, "b = 2a"), Err(EngineError::Cycle("b".to_string())));
+    assert_eq!(run(&mut engine, "b = 3"), value(3));
+    assert_eq!(run(&mut engine, "a"), value(3));
+}
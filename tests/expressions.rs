@@ -0,0 +1,59 @@
+extern crate sym;
+
+use std::collections::HashMap;
+use sym::{Expr, UnaryFunction, Numeric, AstKind, Interval, Token, Span};
+
+#[test]
+fn test_building_and_evaluating_expressions() {
+    // (x + 1) * sin(y)
+    let x = Expr::from_key(0).clone_to_heap();
+    let y = Expr::from_key(1).clone_to_heap();
+    let one = Expr::from_integer(1).clone_to_heap();
+    let expr = sym::mul(&sym::add(&x, &one), &sym::apply(UnaryFunction::Sin, &y));
+
+    let values = vec![Numeric::from_integer(2), Numeric::from_real(0.)];
+    assert_eq!(expr.eval(&values), Ok(Numeric::from_real(0.)));
+    assert!(expr.depends_on_variable(1));
+
+    let enclosure = expr.eval_interval(&vec![Interval::new(1., 2.), Interval::new(0., 1.)]).unwrap();
+    assert!(enclosure.contains(3. * 1f64.sin()));
+
+    let mut replacements = HashMap::new();
+    replacements.insert(1, Expr::from_integer(0).clone_to_heap());
+    let substituted = Expr::substitute(&expr, &replacements);
+    assert!(!substituted.depends_on_variable(1));
+
+    // d/dx (x + 1) sin(y) = sin(y)
+    let derivative = Expr::derivative(&expr, 0);
+    assert_eq!(*derivative, *sym::apply(UnaryFunction::Sin, &y));
+}
+
+#[test]
+fn test_lexer_entry_points() {
+    let tokens = sym::tokenize_string(&String::from("2x + sin(y)")).unwrap();
+    assert_eq!(format!("{:?}", tokens), "[2, *, \"x\", +, sin(), (, \"y\", )]");
+
+    let spanned = sym::tokenize_spanned_with(&String::from("f(t)"), &|name: &str| name == "f").unwrap();
+    assert_eq!(spanned[0], (Token::Function("f".to_string()), Span::new(0, 1)));
+    assert_eq!(sym::tokenize_string(&String::from("1 $")).unwrap_err().span, Span::new(2, 3));
+}
+
+#[test]
+fn test_parser_entry_point() {
+    let ast = sym::parse_string(&String::from("y = -x^2 + 1")).unwrap();
+    assert_eq!(format!("{:?}", ast), "(= y (+ (neg (^ x 2)) 1))");
+    match ast.kind {
+        AstKind::Assignment(ref name, _) => assert_eq!(name, "y"),
+        _ => panic!("expected an assignment")
+    }
+
+    let error = sym::parse_string(&String::from("(1 + 2")).unwrap_err();
+    assert_eq!((error.span.start, error.span.end), (0, 1));
+}
+
+#[test]
+fn test_number_theory() {
+    let n = Numeric::from_integer(360);
+    assert_eq!(sym::gcd(&n, &Numeric::from_integer(84)), Ok(Numeric::from_integer(12)));
+    assert_eq!(sym::is_prime(&Numeric::from_integer(1000003)), Ok(true));
+}